@@ -28,4 +28,4 @@ fn main() {
     println!("You guessed: {guess}");
     // ANCHOR_END: print_guess
 }
-// ANCHOR: all
+// ANCHOR_END: all
@@ -71,7 +71,6 @@ mod tests {
         limit_tracker.set_value(80);
         // ANCHOR: here
 
-        // ANCHOR: here
         assert_eq!(mock_messenger.sent_messages.borrow().len(), 1);
     }
 }
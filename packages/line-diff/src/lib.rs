@@ -0,0 +1,110 @@
+//! Line-by-line diffs between two texts.
+
+/// One line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Line<'a> {
+    /// In both texts.
+    Same(&'a str),
+    /// Only in the old text.
+    Removed(&'a str),
+    /// Only in the new text.
+    Added(&'a str),
+}
+
+/// Diff `old` and `new` line by line, keeping as many lines the same as
+/// possible. Where lines were replaced, the removed ones come first.
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // The length of the longest common subsequence of `old[i..]` and
+    // `new[j..]`, for every `i` and `j`.
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len()
+            && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1])
+        {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Only the changed parts of a diff, each with up to `context` unchanged lines
+/// around it, in unified diff format: each part starts with a header such as
+/// `@@ -3,4 +3,5 @@`, giving where it starts and how many lines it spans in the
+/// old and new text.
+pub fn unified(lines: &[Line<'_>], context: usize) -> String {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    // Group the changes whose context would touch or overlap.
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for index in changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = vec![];
+    for (start, end) in hunks {
+        // Where the hunk starts in each text, counting from 1.
+        let old_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+
+        output.push(format!(
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@"
+        ));
+        output.extend(hunk.iter().map(|line| match line {
+            Line::Same(text) => format!(" {text}"),
+            Line::Removed(text) => format!("-{text}"),
+            Line::Added(text) => format!("+{text}"),
+        }));
+    }
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,40 @@
+use super::*;
+
+#[test]
+fn replaced_lines_are_removed_then_added() {
+    assert_eq!(
+        lines("a\nb\nc", "a\nx\nc\nd"),
+        [
+            Line::Same("a"),
+            Line::Removed("b"),
+            Line::Added("x"),
+            Line::Same("c"),
+            Line::Added("d"),
+        ]
+    );
+}
+
+#[test]
+fn unified_keeps_only_context_around_changes() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10";
+    let new = "1\n2\n3\n4\n5\n6\n7\n8\nnine\n10";
+    assert_eq!(
+        unified(&lines(old, new), 2),
+        "@@ -7,4 +7,4 @@\n 7\n 8\n-9\n+nine\n 10"
+    );
+}
+
+#[test]
+fn unified_splits_distant_changes_into_hunks() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8";
+    let new = "one\n2\n3\n4\n5\n6\n7\n8\n9";
+    assert_eq!(
+        unified(&lines(old, new), 1),
+        "@@ -1,2 +1,2 @@\n-1\n+one\n 2\n@@ -8,1 +8,2 @@\n 8\n+9"
+    );
+}
+
+#[test]
+fn unified_is_empty_without_changes() {
+    assert_eq!(unified(&lines("a\nb", "a\nb"), 3), "");
+}
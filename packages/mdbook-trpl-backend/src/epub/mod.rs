@@ -0,0 +1,236 @@
+use std::io::{self, Write};
+
+use zip_writer::{Method, Writer};
+
+use crate::xhtml::escape;
+
+/// Everything which goes into an EPUB 3 file.
+#[derive(Debug)]
+pub(crate) struct Epub {
+    pub(crate) metadata: Metadata,
+    /// The chapters, in reading order.
+    pub(crate) chapters: Vec<File>,
+    /// Everything else the chapters refer to: the stylesheet and images.
+    pub(crate) resources: Vec<File>,
+    pub(crate) toc: Vec<NavPoint>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Metadata {
+    pub(crate) identifier: String,
+    pub(crate) title: String,
+    pub(crate) authors: Vec<String>,
+    pub(crate) language: String,
+    /// When the book was last changed, like `2024-10-01T12:00:00Z`.
+    pub(crate) modified: String,
+}
+
+/// A file in the EPUB, with a path relative to the directory holding the
+/// package document.
+#[derive(Debug)]
+pub(crate) struct File {
+    pub(crate) path: String,
+    pub(crate) data: Vec<u8>,
+}
+
+/// An entry in the table of contents.
+#[derive(Debug)]
+pub(crate) struct NavPoint {
+    pub(crate) label: String,
+    pub(crate) href: String,
+    pub(crate) children: Vec<NavPoint>,
+}
+
+/// Where the book’s own files live inside the container.
+const ROOT: &str = "EPUB";
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="EPUB/package.opf" media-type="application/oebps-package+xml" />
+</rootfiles>
+</container>
+"#;
+
+impl Epub {
+    pub(crate) fn write<W: Write>(&self, out: W) -> io::Result<W> {
+        let mut zip = Writer::new(out);
+        // The `mimetype` file must come first, uncompressed, so that readers
+        // can recognize the format from the first few bytes.
+        zip.add("mimetype", b"application/epub+zip", Method::Stored)?;
+        zip.add(
+            "META-INF/container.xml",
+            CONTAINER.as_bytes(),
+            Method::Deflated,
+        )?;
+        zip.add(
+            &format!("{ROOT}/package.opf"),
+            self.package().as_bytes(),
+            Method::Deflated,
+        )?;
+        zip.add(
+            &format!("{ROOT}/nav.xhtml"),
+            self.nav().as_bytes(),
+            Method::Deflated,
+        )?;
+        for file in self.chapters.iter().chain(&self.resources) {
+            zip.add(
+                &format!("{ROOT}/{}", file.path),
+                &file.data,
+                Method::Deflated,
+            )?;
+        }
+        zip.finish()
+    }
+
+    /// The package document, which lists every file and the reading order.
+    fn package(&self) -> String {
+        let Metadata {
+            identifier,
+            title,
+            authors,
+            language,
+            modified,
+        } = &self.metadata;
+
+        let mut opf = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{language}">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="book-id">{}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:language>{language}</dc:language>
+"#,
+            escape(identifier),
+            escape(title),
+        );
+        for author in authors {
+            opf.push_str(&format!(
+                "<dc:creator>{}</dc:creator>\n",
+                escape(author)
+            ));
+        }
+        opf.push_str(&format!(
+            r#"<meta property="dcterms:modified">{modified}</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav" />
+"#
+        ));
+
+        for (index, file) in self.chapters.iter().enumerate() {
+            opf.push_str(&item(&format!("chapter-{index}"), &file.path));
+        }
+        for (index, file) in self.resources.iter().enumerate() {
+            opf.push_str(&item(&format!("resource-{index}"), &file.path));
+        }
+
+        opf.push_str("</manifest>\n<spine>\n");
+        for index in 0..self.chapters.len() {
+            opf.push_str(&format!("<itemref idref=\"chapter-{index}\" />\n"));
+        }
+        opf.push_str("</spine>\n</package>\n");
+        opf
+    }
+
+    /// The navigation document, which is the table of contents readers show.
+    fn nav(&self) -> String {
+        let Metadata {
+            title, language, ..
+        } = &self.metadata;
+        let title = escape(title);
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+</head>
+<body>
+<nav epub:type="toc" id="toc">
+<h1>{title}</h1>
+{}</nav>
+</body>
+</html>
+"#,
+            nav_list(&self.toc)
+        )
+    }
+}
+
+fn item(id: &str, path: &str) -> String {
+    format!(
+        "<item id=\"{id}\" href=\"{}\" media-type=\"{}\" />\n",
+        escape(path),
+        media_type(path)
+    )
+}
+
+fn nav_list(points: &[NavPoint]) -> String {
+    if points.is_empty() {
+        return String::new();
+    }
+    let mut list = String::from("<ol>\n");
+    for point in points {
+        list.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            escape(&point.href),
+            escape(&point.label)
+        ));
+        if !point.children.is_empty() {
+            list.push('\n');
+            list.push_str(&nav_list(&point.children));
+        }
+        list.push_str("</li>\n");
+    }
+    list.push_str("</ol>\n");
+    list
+}
+
+fn media_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("xhtml") => "application/xhtml+xml",
+        Some("css") => "text/css",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A UTC timestamp for `dcterms:modified` from seconds since the Unix epoch.
+pub(crate) fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Howard Hinnant’s `civil_from_days`, for the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests;
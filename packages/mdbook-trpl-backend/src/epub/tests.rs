@@ -0,0 +1,85 @@
+use super::*;
+
+fn epub() -> Epub {
+    Epub {
+        metadata: Metadata {
+            identifier: String::from("urn:trpl:test"),
+            title: String::from("Rust & You"),
+            authors: vec![String::from("Ferris"), String::from("Corro")],
+            language: String::from("en"),
+            modified: String::from("2024-01-01T00:00:00Z"),
+        },
+        chapters: vec![
+            File {
+                path: String::from("ch01-00.xhtml"),
+                data: b"<html />".to_vec(),
+            },
+            File {
+                path: String::from("ch01-01.xhtml"),
+                data: b"<html />".to_vec(),
+            },
+        ],
+        resources: vec![File {
+            path: String::from("img/ferris/panics.svg"),
+            data: b"<svg />".to_vec(),
+        }],
+        toc: vec![NavPoint {
+            label: String::from("1. Getting Started"),
+            href: String::from("ch01-00.xhtml"),
+            children: vec![NavPoint {
+                label: String::from("1.1. Installation"),
+                href: String::from("ch01-01.xhtml"),
+                children: vec![],
+            }],
+        }],
+    }
+}
+
+#[test]
+fn package_lists_every_file_in_reading_order() {
+    let package = epub().package();
+    assert!(package.contains("<dc:title>Rust &amp; You</dc:title>"));
+    assert!(package.contains(
+        "<dc:creator>Ferris</dc:creator>\n<dc:creator>Corro</dc:creator>"
+    ));
+    assert!(package.contains(
+        r#"<meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>"#
+    ));
+    assert!(package.contains(
+        r#"<item id="resource-0" href="img/ferris/panics.svg" media-type="image/svg+xml" />"#
+    ));
+    assert!(package.contains(
+        r#"<spine>
+<itemref idref="chapter-0" />
+<itemref idref="chapter-1" />
+</spine>"#
+    ));
+}
+
+#[test]
+fn nav_nests_sections_under_chapters() {
+    let nav = epub().nav();
+    assert!(nav.contains(
+        r#"<ol>
+<li><a href="ch01-00.xhtml">1. Getting Started</a>
+<ol>
+<li><a href="ch01-01.xhtml">1.1. Installation</a></li>
+</ol>
+</li>
+</ol>"#
+    ));
+}
+
+#[test]
+fn container_starts_with_the_mimetype() {
+    let bytes = epub().write(Vec::new()).unwrap();
+    assert_eq!(&bytes[..4], b"PK\x03\x04");
+    assert_eq!(&bytes[30..58], b"mimetypeapplication/epub+zip");
+}
+
+#[test]
+fn timestamps() {
+    assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+    assert_eq!(timestamp(951_782_400), "2000-02-29T00:00:00Z");
+    assert_eq!(timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+}
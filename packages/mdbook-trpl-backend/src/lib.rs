@@ -0,0 +1,257 @@
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context};
+use mdbook::{
+    errors::Result,
+    renderer::{RenderContext, Renderer},
+    BookItem,
+};
+use serde::Deserialize;
+
+use crate::{
+    epub::{Epub, File, Metadata, NavPoint},
+    xhtml::Links,
+};
+
+mod epub;
+mod xhtml;
+
+/// A renderer which turns _The Rust Programming Language_ into an EPUB, and a
+/// single XHTML document ready for turning into a PDF.
+///
+/// mdBook’s own HTML renderer relies on the book’s CSS and JavaScript to show
+/// listings, notes, and Ferris properly, and other renderers do not know about
+/// them at all, so they come out mangled: captions detached from their code,
+/// hidden lines showing, and no Ferris. This renderer knows about all of them:
+///
+/// - Listings and notes come from the `trpl-listing` and `trpl-note`
+///   preprocessors (which run for this renderer as well as for the HTML
+///   output), and are kept together and styled for print.
+/// - Lines hidden from readers with `# ` are left out of Rust code.
+/// - Code blocks annotated with `does_not_compile`, `panics`, or
+///   `not_desired_behavior` get their Ferris as an image, since there is no
+///   JavaScript to add it.
+/// - Links between chapters and to headings keep working.
+///
+/// It writes `book.epub`, and `print.xhtml` along with the stylesheet and
+/// images it uses, into its output directory. Either can be checked with the
+/// usual tools (like `epubcheck`), and the print document can be given to any
+/// tool which makes PDFs from HTML and CSS. To do that as part of the build,
+/// give the command to run, with `{input}` and `{output}` standing in for the
+/// print document and the PDF:
+///
+/// ```toml
+/// [output.trpl-backend]
+/// pdf-command = ["weasyprint", "{input}", "{output}"]
+/// ```
+///
+/// The EPUB’s identifier comes from the book’s title, unless `identifier` is
+/// set there too, and its modification time from `SOURCE_DATE_EPOCH` when that
+/// is set, so that builds can be reproduced.
+pub struct TrplBackend;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct Config {
+    identifier: Option<String>,
+    pdf_command: Option<Vec<String>>,
+}
+
+const STYLESHEET: &str = include_str!("style.css");
+
+impl Renderer for TrplBackend {
+    fn name(&self) -> &str {
+        "trpl-backend"
+    }
+
+    fn render(&self, ctx: &RenderContext) -> Result<()> {
+        let config: Config = ctx
+            .config
+            .get_deserialized_opt(format!("output.{}", self.name()))?
+            .unwrap_or_default();
+
+        let title = ctx
+            .config
+            .book
+            .title
+            .clone()
+            .unwrap_or_else(|| String::from("Untitled"));
+        let language = ctx
+            .config
+            .book
+            .language
+            .clone()
+            .unwrap_or_else(|| String::from("en"));
+
+        let mut contents = Contents {
+            language: &language,
+            chapters: vec![],
+            print: String::new(),
+            images: BTreeSet::new(),
+        };
+        let toc = contents.add(&ctx.book.sections);
+
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+        let mut resources = vec![File {
+            path: String::from("style.css"),
+            data: STYLESHEET.as_bytes().to_vec(),
+        }];
+        for image in &contents.images {
+            let data = fs::read(src_dir.join(image)).with_context(|| {
+                format!("Unable to read image '{}'", image.display())
+            })?;
+            resources.push(File {
+                path: xhtml::slash_path(image),
+                data,
+            });
+        }
+
+        let epub = Epub {
+            metadata: Metadata {
+                identifier: config
+                    .identifier
+                    .unwrap_or_else(|| format!("urn:trpl:{}", slug(&title))),
+                title: title.clone(),
+                authors: ctx.config.book.authors.clone(),
+                language: language.clone(),
+                modified: epub::timestamp(build_time()?),
+            },
+            chapters: contents.chapters,
+            resources,
+            toc,
+        };
+
+        fs::create_dir_all(&ctx.destination)?;
+        let file = fs::File::create(ctx.destination.join("book.epub"))?;
+        epub.write(file)?;
+
+        let print =
+            xhtml::document(&title, &language, "style.css", &contents.print);
+        fs::write(ctx.destination.join("print.xhtml"), print)?;
+        for resource in &epub.resources {
+            let path = ctx.destination.join(&resource.path);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, &resource.data)?;
+        }
+
+        if let Some(command) = config.pdf_command {
+            make_pdf(&command, &ctx.destination)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The chapters as they go into each kind of output.
+struct Contents<'a> {
+    language: &'a str,
+    chapters: Vec<File>,
+    /// All the chapters, one after another, for the print document.
+    print: String,
+    images: BTreeSet<PathBuf>,
+}
+
+impl Contents<'_> {
+    /// Add `items` and everything nested in them, in order, giving back their
+    /// entries for the table of contents.
+    fn add(&mut self, items: &[BookItem]) -> Vec<NavPoint> {
+        let mut toc = vec![];
+        for item in items {
+            // Part titles and separators only matter for the HTML sidebar, and
+            // draft chapters have nothing to show.
+            let BookItem::Chapter(chapter) = item else {
+                continue;
+            };
+            let Some(path) = chapter.path.as_deref() else {
+                continue;
+            };
+
+            let rendered =
+                xhtml::render(&chapter.content, path, Links::Chapters);
+            let document = xhtml::document(
+                &chapter.name,
+                self.language,
+                &xhtml::relative_to(path, "style.css"),
+                &rendered.body,
+            );
+            let file_name = xhtml::file_name(path);
+            self.chapters.push(File {
+                path: file_name.clone(),
+                data: document.into_bytes(),
+            });
+            self.images.extend(rendered.images);
+
+            let printed =
+                xhtml::render(&chapter.content, path, Links::SingleDocument);
+            self.print.push_str(&format!(
+                "<section class=\"chapter\" id=\"{}\">\n{}</section>\n",
+                xhtml::anchor(path),
+                printed.body
+            ));
+
+            let label = match &chapter.number {
+                Some(number) => format!("{number} {}", chapter.name),
+                None => chapter.name.clone(),
+            };
+            toc.push(NavPoint {
+                label,
+                href: file_name,
+                children: self.add(&chapter.sub_items),
+            });
+        }
+        toc
+    }
+}
+
+/// Run the configured command for making a PDF from the print document.
+fn make_pdf(command: &[String], dir: &Path) -> Result<()> {
+    let args: Vec<String> = command
+        .iter()
+        .map(|arg| {
+            arg.replace("{input}", "print.xhtml")
+                .replace("{output}", "book.pdf")
+        })
+        .collect();
+    let Some((program, args)) = args.split_first() else {
+        bail!("`pdf-command` is empty");
+    };
+
+    let status = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Unable to run `{program}`"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("`{}` failed: {status}", command.join(" ")))
+    }
+}
+
+/// When the book was built, for the EPUB metadata.
+fn build_time() -> Result<u64> {
+    match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid SOURCE_DATE_EPOCH '{epoch}'")),
+        Err(_) => Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()),
+    }
+}
+
+fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
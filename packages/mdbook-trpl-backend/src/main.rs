@@ -0,0 +1,10 @@
+use std::io;
+
+use mdbook::renderer::{RenderContext, Renderer};
+use mdbook_trpl_backend::TrplBackend;
+
+fn main() -> Result<(), String> {
+    let ctx =
+        RenderContext::from_json(io::stdin()).map_err(|e| format!("{e}"))?;
+    TrplBackend.render(&ctx).map_err(|e| format!("{e:?}"))
+}
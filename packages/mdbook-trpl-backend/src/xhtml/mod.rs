@@ -0,0 +1,413 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use mdbook::utils::unique_id_from_content;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use regex::{Captures, Regex};
+
+/// Where the rendered chapter is going, which decides how links between
+/// chapters and paths to images are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Links {
+    /// One XHTML file per chapter, alongside each other as the Markdown files
+    /// are, as in an EPUB.
+    Chapters,
+    /// All the chapters in one document at the root of the book, as for
+    /// printing.
+    SingleDocument,
+}
+
+/// A chapter rendered to XHTML.
+#[derive(Debug)]
+pub(crate) struct Chapter {
+    pub(crate) body: String,
+    /// The local images the chapter shows, relative to the book’s source
+    /// directory.
+    pub(crate) images: BTreeSet<PathBuf>,
+}
+
+/// The Ferris annotations on code blocks, and what each one means, matching
+/// `ferris.js` for the HTML book.
+const FERRIS: &[(&str, &str)] = &[
+    ("does_not_compile", "This code does not compile!"),
+    ("panics", "This code panics!"),
+    (
+        "not_desired_behavior",
+        "This code does not produce the desired behavior.",
+    ),
+];
+
+/// Render the Markdown of the chapter at `source_path`, relative to the book’s
+/// source directory. The Markdown is expected to have been through the
+/// `trpl-listing` and `trpl-note` preprocessors already, so listings and notes
+/// arrive as HTML and only need to be made into valid XHTML here.
+pub(crate) fn render(src: &str, source_path: &Path, links: Links) -> Chapter {
+    let mut renderer = Renderer {
+        source_path,
+        links,
+        images: BTreeSet::new(),
+        ids: HashMap::new(),
+    };
+    let events = renderer.events(src);
+    let mut body = String::with_capacity(src.len());
+    html::push_html(&mut body, events.into_iter());
+    Chapter {
+        body,
+        images: renderer.images,
+    }
+}
+
+/// Wrap a rendered chapter (or several) in a complete XHTML document.
+pub(crate) fn document(
+    title: &str,
+    language: &str,
+    stylesheet: &str,
+    body: &str,
+) -> String {
+    let title = escape(title);
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+<link rel="stylesheet" type="text/css" href="{stylesheet}" />
+</head>
+<body>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// The name of the XHTML file for the chapter at `source_path`.
+pub(crate) fn file_name(source_path: &Path) -> String {
+    format!("{}.xhtml", slash_path(&source_path.with_extension("")))
+}
+
+/// The `id` of the chapter at `source_path` when all the chapters are in one
+/// document.
+pub(crate) fn anchor(source_path: &Path) -> String {
+    slash_path(&source_path.with_extension("")).replace('/', "-")
+}
+
+/// A relative path from the chapter at `source_path` to `path`, where both are
+/// relative to the book’s source directory.
+pub(crate) fn relative_to(source_path: &Path, path: &str) -> String {
+    let depth = source_path
+        .parent()
+        .map(|dir| dir.components().count())
+        .unwrap_or(0);
+    format!("{}{path}", "../".repeat(depth))
+}
+
+struct Renderer<'p> {
+    source_path: &'p Path,
+    links: Links,
+    images: BTreeSet<PathBuf>,
+    /// The heading `id`s given out so far, as mdBook tracks them.
+    ids: HashMap<String, usize>,
+}
+
+impl Renderer<'_> {
+    fn events<'a>(&mut self, src: &'a str) -> Vec<Event<'a>> {
+        let mut rendered = vec![];
+        let mut events = mdbook_trpl::parser(src).peekable();
+        while let Some(event) = events.next() {
+            match event {
+                Event::Start(Tag::Heading {
+                    level,
+                    id,
+                    classes,
+                    attrs,
+                }) => {
+                    let mut inner = vec![];
+                    for event in events.by_ref() {
+                        if matches!(event, Event::End(TagEnd::Heading(_))) {
+                            break;
+                        }
+                        inner.push(self.inline(event));
+                    }
+                    // Give every heading an `id`, the same way mdBook does for
+                    // its HTML, so that links to sections keep working.
+                    let id = id.unwrap_or_else(|| {
+                        unique_id_from_content(&text_of(&inner), &mut self.ids)
+                            .into()
+                    });
+                    rendered.push(Event::Start(Tag::Heading {
+                        level,
+                        id: Some(id),
+                        classes,
+                        attrs,
+                    }));
+                    rendered.extend(inner);
+                    rendered.push(Event::End(TagEnd::Heading(level)));
+                }
+
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let info = match kind {
+                        CodeBlockKind::Fenced(info) => info.to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    let mut code = String::new();
+                    for event in events.by_ref() {
+                        match event {
+                            Event::Text(text) => code.push_str(&text),
+                            Event::End(TagEnd::CodeBlock) => break,
+                            _ => {}
+                        }
+                    }
+                    rendered.push(Event::Html(
+                        self.code_block(&info, &code).into(),
+                    ));
+                }
+
+                // An HTML tag can be split across several events, one per line,
+                // so put them back together before fixing them up.
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    let mut raw = html.to_string();
+                    while let Some(
+                        Event::Html(next) | Event::InlineHtml(next),
+                    ) = events.next_if(|event| {
+                        matches!(event, Event::Html(_) | Event::InlineHtml(_))
+                    }) {
+                        raw.push_str(&next);
+                    }
+                    rendered.push(Event::Html(self.raw_html(&raw).into()));
+                }
+
+                event => rendered.push(self.inline(event)),
+            }
+        }
+        rendered
+    }
+
+    /// Point links and images at where things are in the output.
+    fn inline<'a>(&mut self, event: Event<'a>) -> Event<'a> {
+        match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: self.link(&dest_url).map_or(dest_url, CowStr::from),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: self.image(&dest_url).into(),
+                title,
+                id,
+            }),
+            event => event,
+        }
+    }
+
+    /// The new destination for a link to another chapter, if it is one.
+    fn link(&self, dest: &str) -> Option<String> {
+        if is_external(dest) {
+            return None;
+        }
+        let (path, fragment) = match dest.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (dest, None),
+        };
+        let stem = path.strip_suffix(".md")?;
+        Some(match (self.links, fragment) {
+            (Links::Chapters, Some(fragment)) => {
+                format!("{stem}.xhtml#{fragment}")
+            }
+            (Links::Chapters, None) => format!("{stem}.xhtml"),
+            (Links::SingleDocument, Some(fragment)) => format!("#{fragment}"),
+            (Links::SingleDocument, None) => {
+                format!("#{}", anchor(&self.resolve(path)))
+            }
+        })
+    }
+
+    /// Note down an image the chapter uses, and give the path to write for it.
+    fn image(&mut self, src: &str) -> String {
+        if is_external(src) {
+            return src.to_string();
+        }
+        let resolved = self.resolve(src);
+        let path = slash_path(&resolved);
+        self.images.insert(resolved);
+        match self.links {
+            Links::Chapters => src.to_string(),
+            Links::SingleDocument => path,
+        }
+    }
+
+    /// `path`, relative to the chapter, made relative to the source directory.
+    fn resolve(&self, path: &str) -> PathBuf {
+        let dir = self.source_path.parent().unwrap_or(Path::new(""));
+        let mut resolved = PathBuf::new();
+        for component in dir.join(path).components() {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+                _ => {}
+            }
+        }
+        resolved
+    }
+
+    /// A code block, without the lines hidden from readers, and with Ferris
+    /// showing when it is annotated as not working.
+    fn code_block(&mut self, info: &str, code: &str) -> String {
+        let attributes: Vec<&str> = info
+            .split([',', ' '])
+            .map(str::trim)
+            .filter(|attribute| !attribute.is_empty())
+            .collect();
+        let language = attributes.first().copied().unwrap_or("text");
+
+        let code = if language == "rust" {
+            visible_lines(code)
+        } else {
+            code.to_string()
+        };
+
+        let ferris = FERRIS
+            .iter()
+            .find(|(annotation, _)| attributes.contains(annotation));
+        let mut block = match ferris {
+            Some((annotation, title)) => {
+                let path = format!("img/ferris/{annotation}.svg");
+                self.images.insert(PathBuf::from(&path));
+                let src = match self.links {
+                    Links::Chapters => relative_to(self.source_path, &path),
+                    Links::SingleDocument => path,
+                };
+                format!(
+                    r#"<div class="code {annotation}"><img class="ferris" src="{src}" alt="{title}" title="{title}" />"#
+                )
+            }
+            None => String::from(r#"<div class="code">"#),
+        };
+        block.push_str(&format!(
+            "<pre><code class=\"language-{}\">{}</code></pre></div>\n",
+            escape(language),
+            escape(&code)
+        ));
+        block
+    }
+
+    /// Make raw HTML from the Markdown, or from the preprocessors, into valid
+    /// XHTML: elements which never have content must be closed, and XHTML
+    /// only knows a handful of named entities. Comments are notes for the
+    /// book’s authors, and often have `--` in them, which XML does not allow
+    /// there, so they are dropped.
+    fn raw_html(&mut self, html: &str) -> String {
+        static COMMENT: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+        static SRC: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"\bsrc="([^"]*)""#).unwrap());
+        static VOID: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"<(area|br|col|hr|img|input|meta|source|wbr)\b([^>]*?)\s*/?>",
+            )
+            .unwrap()
+        });
+        static ENTITY: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"&([A-Za-z]+);").unwrap());
+
+        let html = COMMENT.replace_all(html, "");
+        let html = SRC.replace_all(&html, |captures: &Captures| {
+            format!(r#"src="{}""#, self.image(&captures[1]))
+        });
+        let html = VOID.replace_all(&html, "<$1$2 />");
+        ENTITY
+            .replace_all(&html, |captures: &Captures| {
+                match entity(&captures[1]) {
+                    Some(code) => format!("&#{code};"),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// The code point for a named HTML entity which XHTML does not know, for the
+/// ones the book uses.
+fn entity(name: &str) -> Option<u32> {
+    match name {
+        "nbsp" => Some(160),
+        "vert" => Some(124),
+        "ndash" => Some(8211),
+        "mdash" => Some(8212),
+        "hellip" => Some(8230),
+        "larr" => Some(8592),
+        "rarr" => Some(8594),
+        "times" => Some(215),
+        "copy" => Some(169),
+        _ => None,
+    }
+}
+
+/// The lines of Rust code which readers see: mdBook hides lines starting with
+/// `# `, and turns `##` at the start of a line into `#`.
+fn visible_lines(code: &str) -> String {
+    code.lines()
+        .filter_map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("##") {
+                Some(line.replacen("##", "#", 1))
+            } else if trimmed == "#" || trimmed.starts_with("# ") {
+                None
+            } else {
+                Some(line.to_string())
+            }
+        })
+        .flat_map(|line| [line, String::from("\n")])
+        .collect()
+}
+
+fn text_of(events: &[Event]) -> String {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_external(dest: &str) -> bool {
+    dest.starts_with('#')
+        || dest.starts_with('/')
+        || dest.starts_with("mailto:")
+        || dest.contains("://")
+}
+
+pub(crate) fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests;
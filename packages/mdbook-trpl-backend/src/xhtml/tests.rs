@@ -0,0 +1,176 @@
+use super::*;
+
+fn rendered(src: &str) -> Chapter {
+    render(src, Path::new("ch01-01-example.md"), Links::Chapters)
+}
+
+#[test]
+fn hidden_lines_are_left_out_of_rust_code() {
+    let chapter = rendered(
+        "```rust
+# use std::fmt;
+#
+#[derive(Debug)]
+struct Point;
+## not hidden
+```",
+    );
+    assert_eq!(
+        chapter.body,
+        "<div class=\"code\"><pre><code class=\"language-rust\">#[derive(Debug)]
+struct Point;
+# not hidden
+</code></pre></div>
+"
+    );
+}
+
+#[test]
+fn other_languages_keep_every_line() {
+    let chapter = rendered("```console\n# a root prompt\n```");
+    assert!(chapter.body.contains("# a root prompt"));
+}
+
+#[test]
+fn ferris_shows_up_for_annotated_code() {
+    let chapter = rendered(
+        "```rust,ignore,does_not_compile
+let x: i32 = \"nope\";
+```",
+    );
+    assert!(chapter.body.starts_with(
+        r#"<div class="code does_not_compile"><img class="ferris" src="img/ferris/does_not_compile.svg" alt="This code does not compile!" title="This code does not compile!" />"#
+    ));
+    assert!(chapter.body.contains("&quot;nope&quot;"));
+    assert!(chapter
+        .images
+        .contains(Path::new("img/ferris/does_not_compile.svg")));
+}
+
+#[test]
+fn listings_from_the_preprocessor_stay_together() {
+    let chapter = rendered(
+        r##"<figure class="listing" id="listing-1-2">
+<span class="file-name">Filename: src/main.rs</span>
+
+```rust
+fn main() {}
+```
+
+<figcaption><a href="#listing-1-2">Listing 1-2</a>: Some <em>text</em></figcaption>
+</figure>
+"##,
+    );
+    assert_eq!(
+        chapter.body,
+        r##"<figure class="listing" id="listing-1-2">
+<span class="file-name">Filename: src/main.rs</span>
+<div class="code"><pre><code class="language-rust">fn main() {}
+</code></pre></div>
+<figcaption><a href="#listing-1-2">Listing 1-2</a>: Some <em>text</em></figcaption>
+</figure>
+"##
+    );
+}
+
+#[test]
+fn raw_html_is_made_into_xhtml() {
+    let chapter = rendered(
+        r#"<img alt="Two&nbsp;tables" src="img/trpl04-01.svg" class="center"
+style="width: 50%;">
+
+<code>a &vert; b</code>&nbsp;&amp;<br>
+
+<!-- manual-regeneration
+cargo run -- --help
+-->
+"#,
+    );
+    assert!(chapter.body.contains(
+        r#"<img alt="Two&#160;tables" src="img/trpl04-01.svg" class="center"
+style="width: 50%;" />"#
+    ));
+    // Entities outside of HTML blocks are Markdown’s to deal with.
+    assert!(chapter.body.contains("a | b</code>\u{a0}&amp;<br />"));
+    assert!(!chapter.body.contains("manual-regeneration"));
+    assert_eq!(
+        chapter.images.into_iter().collect::<Vec<_>>(),
+        [PathBuf::from("img/trpl04-01.svg")]
+    );
+}
+
+#[test]
+fn headings_get_ids_like_mdbook_gives_them() {
+    let chapter = rendered(
+        "# Hello, `World`!
+
+## Details
+
+## Details
+
+## Custom {#mine}",
+    );
+    assert_eq!(
+        chapter.body,
+        r#"<h1 id="hello-world">Hello, <code>World</code>!</h1>
+<h2 id="details">Details</h2>
+<h2 id="details-1">Details</h2>
+<h2 id="mine">Custom</h2>
+"#
+    );
+}
+
+mod links {
+    use super::*;
+
+    #[test]
+    fn between_chapters() {
+        let chapter = rendered(
+            "[one](ch02-00-guessing-game.md), [two](ch03-01-variables.md#shadowing), \
+             [here](#details), [away](https://www.rust-lang.org/)",
+        );
+        assert_eq!(
+            chapter.body,
+            "<p><a href=\"ch02-00-guessing-game.xhtml\">one</a>, \
+             <a href=\"ch03-01-variables.xhtml#shadowing\">two</a>, \
+             <a href=\"#details\">here</a>, \
+             <a href=\"https://www.rust-lang.org/\">away</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn in_a_single_document() {
+        let chapter = render(
+            "[one](../ch02-00-guessing-game.md), [two](ch03-01-variables.md#shadowing)
+
+![Ferris](../img/ferris/panics.svg)",
+            Path::new("nested/ch01-01.md"),
+            Links::SingleDocument,
+        );
+        assert_eq!(
+            chapter.body,
+            "<p><a href=\"#ch02-00-guessing-game\">one</a>, \
+             <a href=\"#shadowing\">two</a></p>
+<p><img src=\"img/ferris/panics.svg\" alt=\"Ferris\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn ferris_from_a_nested_chapter() {
+        let chapter = render(
+            "```rust,panics\nfn main() { panic!() }\n```",
+            Path::new("nested/ch01-01.md"),
+            Links::Chapters,
+        );
+        assert!(chapter.body.contains(r#"src="../img/ferris/panics.svg""#));
+    }
+}
+
+#[test]
+fn documents_are_complete() {
+    let document =
+        document("Hello & goodbye", "en", "style.css", "<p>Hi!</p>\n");
+    assert!(document.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    assert!(document.contains("<title>Hello &amp; goodbye</title>"));
+    assert!(document.contains("<body>\n<p>Hi!</p>\n</body>"));
+}
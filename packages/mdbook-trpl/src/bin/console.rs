@@ -0,0 +1,42 @@
+use std::io;
+
+use clap::{self, Parser, Subcommand};
+
+use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
+use mdbook_trpl::Console;
+
+fn main() -> Result<(), String> {
+    match Cli::parse().command {
+        Some(Command::Supports { renderer }) => {
+            if Console.supports_renderer(&renderer) {
+                Ok(())
+            } else {
+                Err(format!("Renderer '{renderer}' is unsupported"))
+            }
+        }
+        None => {
+            let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())
+                .map_err(|e| format!("{e}"))?;
+            let processed =
+                Console.run(&ctx, book).map_err(|e| format!("{e}"))?;
+            serde_json::to_writer(io::stdout(), &processed)
+                .map_err(|e| format!("{e}"))
+        }
+    }
+}
+
+/// A preprocessor for checking that the console output shown in _The Rust
+/// Programming Language_ still matches what the listings produce.
+#[derive(Parser, Debug)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Is the renderer supported?
+    ///
+    /// Supported renderers are `'html'`, `'markdown'`, and `'test'`.
+    Supports { renderer: String },
+}
@@ -25,8 +25,8 @@ fn main() -> Result<(), String> {
     }
 }
 
-/// A simple preprocessor for handling figures with images in _The Rust
-/// Programming Language_ book.
+/// A simple preprocessor for numbering and captioning figures with images in
+/// _The Rust Programming Language_ book.
 #[derive(Parser, Debug)]
 struct Cli {
     #[command(subcommand)]
@@ -37,6 +37,7 @@ struct Cli {
 enum Command {
     /// Is the renderer supported?
     ///
-    /// Supported renderers are `'html'`, `'markdown'`, and `'test'`.
+    /// Supported renderers are `'html'`, `'markdown'`, `'test'`, and
+    /// `'trpl-backend'`.
     Supports { renderer: String },
 }
@@ -1,18 +1,32 @@
-use std::io;
+use std::{io, path::PathBuf};
 
 use clap::{self, Parser, Subcommand};
-use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
+use mdbook::{
+    preprocess::{CmdPreprocessor, Preprocessor},
+    MDBook,
+};
 
 use mdbook_trpl::Listing;
 
 fn main() -> Result<(), String> {
     let cli = Cli::parse();
-    if let Some(Command::Supports { renderer }) = cli.command {
-        return if Listing.supports_renderer(&renderer) {
-            Ok(())
-        } else {
-            Err(format!("Renderer '{renderer}' is unsupported"))
-        };
+    match cli.command {
+        Some(Command::Supports { renderer }) => {
+            return if Listing.supports_renderer(&renderer) {
+                Ok(())
+            } else {
+                Err(format!("Renderer '{renderer}' is unsupported"))
+            };
+        }
+        Some(Command::Xgettext { book }) => {
+            let book = MDBook::load(book).map_err(|e| format!("{e}"))?;
+            let pot = Listing
+                .xgettext(&book.book, &book.root, &book.config.book.src)
+                .map_err(|e| format!("{e}"))?;
+            print!("{pot}");
+            return Ok(());
+        }
+        None => {}
     }
 
     let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())
@@ -35,4 +49,14 @@ enum Command {
     ///
     /// All renderers are supported! This is the contract for mdBook.
     Supports { renderer: String },
+
+    /// Print a gettext template with the messages for listings in the book.
+    ///
+    /// These are the labels the preprocessor writes out and each listing’s
+    /// caption, for merging into a translation’s catalog.
+    Xgettext {
+        /// The book’s root directory.
+        #[arg(default_value = ".")]
+        book: PathBuf,
+    },
 }
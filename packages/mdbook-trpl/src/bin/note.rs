@@ -8,12 +8,19 @@ use mdbook_trpl::Note;
 fn main() -> Result<(), String> {
     let cli = Cli::parse();
     let simple_note = Note;
-    if let Some(Command::Supports { renderer }) = cli.command {
-        return if simple_note.supports_renderer(&renderer) {
-            Ok(())
-        } else {
-            Err(format!("Renderer '{renderer}' is unsupported"))
-        };
+    match cli.command {
+        Some(Command::Supports { renderer }) => {
+            return if simple_note.supports_renderer(&renderer) {
+                Ok(())
+            } else {
+                Err(format!("Renderer '{renderer}' is unsupported"))
+            };
+        }
+        Some(Command::Xgettext) => {
+            print!("{}", simple_note.xgettext());
+            return Ok(());
+        }
+        None => {}
     }
 
     let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())
@@ -35,4 +42,8 @@ enum Command {
     ///
     /// All renderers are supported! This is the contract for mdBook.
     Supports { renderer: String },
+
+    /// Print a gettext template with the labels for each kind of note, for
+    /// merging into a translation’s catalog.
+    Xgettext,
 }
@@ -0,0 +1,42 @@
+use std::io;
+
+use clap::{self, Parser, Subcommand};
+
+use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
+use mdbook_trpl::Playground;
+
+fn main() -> Result<(), String> {
+    match Cli::parse().command {
+        Some(Command::Supports { renderer }) => {
+            if Playground.supports_renderer(&renderer) {
+                Ok(())
+            } else {
+                Err(format!("Renderer '{renderer}' is unsupported"))
+            }
+        }
+        None => {
+            let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())
+                .map_err(|e| format!("{e}"))?;
+            let processed =
+                Playground.run(&ctx, book).map_err(|e| format!("{e}"))?;
+            serde_json::to_writer(io::stdout(), &processed)
+                .map_err(|e| format!("{e}"))
+        }
+    }
+}
+
+/// A simple preprocessor for making the async examples in _The Rust
+/// Programming Language_ book runnable on the Rust Playground.
+#[derive(Parser, Debug)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Is the renderer supported?
+    ///
+    /// The only supported renderer is `'html'`.
+    Supports { renderer: String },
+}
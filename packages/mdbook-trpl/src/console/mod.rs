@@ -0,0 +1,315 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::LazyLock,
+};
+
+use anyhow::{anyhow, Context as _};
+use mdbook::{
+    book::Book,
+    preprocess::{Preprocessor, PreprocessorContext},
+    BookItem,
+};
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use regex::Regex;
+
+use crate::CompositeError;
+
+/// A preprocessor which checks that the output shown in the book still matches
+/// what the listings actually produce.
+///
+/// The book shows the output of running a listing by including the listing’s
+/// `output.txt` in a `console` block:
+///
+/// ````markdown
+/// ```console
+/// {{#include ../listings/ch03-common-programming-concepts/no-listing-01-variables-are-immutable/output.txt}}
+/// ```
+/// ````
+///
+/// The first line of each `output.txt` is the command which produced it, like
+/// `$ cargo run`. For every such block, this preprocessor runs that command in
+/// a fresh copy of the listing package (with the `ANCHOR` comments removed, so
+/// line numbers match what readers see) and compares what it prints against the
+/// rest of the file. If any of them differ, the build fails with a diff for
+/// each one.
+///
+/// Things which change from run to run or from machine to machine, like build
+/// times, paths, and test binary hashes, are ignored in the comparison, the same
+/// way `tools/update-rustc.sh` leaves them out when it regenerates the files.
+///
+/// The book’s content passes through unchanged. Since this builds and runs every
+/// listing with shown output, it is slow, so it is not part of the normal build:
+/// enable it only when you want to check for drift. It must run before mdBook’s
+/// own `links` preprocessor, which would otherwise have already replaced the
+/// `{{#include}}` with the file’s contents:
+///
+/// ```toml
+/// [preprocessor.trpl-console]
+/// before = ["links"]
+/// ```
+pub struct TrplConsole;
+
+impl Preprocessor for TrplConsole {
+    fn name(&self) -> &str {
+        "trpl-console"
+    }
+
+    fn run(
+        &self,
+        ctx: &PreprocessorContext,
+        book: Book,
+    ) -> anyhow::Result<Book> {
+        // Canonicalize, so the scratch copies mirror the book’s layout even
+        // when the listing paths go through `..` and symlinks.
+        let root = ctx.root.canonicalize().unwrap_or_else(|_| ctx.root.clone());
+        let src_dir = root.join(&ctx.config.book.src);
+        let scratch_dir = root.join("tmp").join("console");
+
+        let mut checked = HashSet::new();
+        let mut errors = vec![];
+        for item in book.iter() {
+            let BookItem::Chapter(chapter) = item else {
+                continue;
+            };
+            let Some(source_path) = &chapter.source_path else {
+                continue;
+            };
+            let chapter_dir = src_dir
+                .join(source_path)
+                .parent()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| src_dir.clone());
+
+            for include in console_includes(&chapter.content) {
+                let output_path = chapter_dir.join(&include);
+                let output_path =
+                    output_path.canonicalize().unwrap_or(output_path);
+                if !checked.insert(output_path.clone()) {
+                    continue;
+                }
+
+                let result = check_output(&root, &scratch_dir, &output_path)
+                    .with_context(|| {
+                        format!(
+                            "checking '{include}' in {}",
+                            source_path.display()
+                        )
+                    });
+                match result {
+                    Ok(None) => {}
+                    Ok(Some(diff)) => errors.push(anyhow!(
+                        "Output in '{include}' (from {}) has drifted:\n{diff}",
+                        source_path.display()
+                    )),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(book)
+        } else {
+            Err(CompositeError(errors).into())
+        }
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html" || renderer == "markdown" || renderer == "test"
+    }
+}
+
+/// The `output.txt` paths included in `console` blocks in `src`, in order.
+fn console_includes(src: &str) -> Vec<String> {
+    static INCLUDE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^\{\{#include\s+([^}\s]+output\.txt)\s*\}\}$").unwrap()
+    });
+
+    let mut includes = vec![];
+    let mut in_console = false;
+    for event in crate::parser(src) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                in_console = info.split(',').next() == Some("console");
+            }
+            Event::End(TagEnd::CodeBlock) => in_console = false,
+            Event::Text(text) if in_console => {
+                includes.extend(text.lines().filter_map(|line| {
+                    INCLUDE
+                        .captures(line.trim())
+                        .map(|captures| captures[1].to_string())
+                }));
+            }
+            _ => {}
+        }
+    }
+    includes
+}
+
+/// Run the command from the top of `output_path` and compare the result. On a
+/// mismatch, produces a diff of the expected and actual output.
+fn check_output(
+    book_root: &Path,
+    scratch_dir: &Path,
+    output_path: &Path,
+) -> anyhow::Result<Option<String>> {
+    let expected = fs::read_to_string(output_path)
+        .with_context(|| format!("reading {}", output_path.display()))?;
+    let command = expected
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("$ "))
+        .ok_or_else(|| anyhow!("the first line is not a `$ ` command"))?;
+
+    let listing_dir = output_path
+        .parent()
+        .ok_or_else(|| anyhow!("no listing directory"))?;
+    let relative = listing_dir.strip_prefix(book_root).unwrap_or(listing_dir);
+    let relative = relative.strip_prefix("/").unwrap_or(relative);
+    let scratch_listing = scratch_dir.join(relative);
+    prepare_scratch_dir(book_root, scratch_dir, listing_dir, &scratch_listing)?;
+
+    // Match how `tools/update-rustc.sh` produces these files, including
+    // interleaving stdout and stderr the same way.
+    let run = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{command} 2>&1"))
+        .current_dir(&scratch_listing)
+        .env("RUSTFLAGS", "-A unused_variables -A dead_code")
+        .env("RUST_TEST_THREADS", "1")
+        .output()
+        .with_context(|| format!("running `{command}`"))?;
+    let actual =
+        format!("$ {command}\n{}", String::from_utf8_lossy(&run.stdout));
+
+    let expected = normalize(&expected);
+    let actual = normalize(&actual);
+    if expected == actual {
+        Ok(None)
+    } else {
+        Ok(Some(diff(&expected, &actual)))
+    }
+}
+
+/// Copy the listing at `listing_dir` to `scratch_listing`, so that it builds
+/// from scratch without touching the original. The scratch directory mirrors
+/// the book’s layout, so that the listings’ path dependency on `trpl` still
+/// resolves.
+fn prepare_scratch_dir(
+    book_root: &Path,
+    scratch_dir: &Path,
+    listing_dir: &Path,
+    scratch_listing: &Path,
+) -> anyhow::Result<()> {
+    let trpl = scratch_dir.join("packages").join("trpl");
+    if !trpl.exists() {
+        copy_dir(&book_root.join("packages").join("trpl"), &trpl)?;
+    }
+
+    if scratch_listing.exists() {
+        fs::remove_dir_all(scratch_listing)?;
+    }
+    copy_dir(listing_dir, scratch_listing)
+        .with_context(|| format!("copying {}", listing_dir.display()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let source = entry.path();
+        let destination = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            if name != "target" {
+                copy_dir(&source, &destination)?;
+            }
+        } else if source.extension().is_some_and(|ext| ext == "rs") {
+            let contents = fs::read_to_string(&source)?;
+            fs::write(&destination, strip_anchors(&contents))?;
+        } else {
+            fs::copy(&source, &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the `// ANCHOR` and `// ANCHOR_END` comment lines which mark the
+/// parts of a listing the book includes.
+fn strip_anchors(src: &str) -> String {
+    static ANCHOR: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^\s*//\s*ANCHOR(_END)?:\s*[\w_-]+\s*$").unwrap()
+    });
+
+    let mut stripped: String = src
+        .lines()
+        .filter(|line| !ANCHOR.is_match(line))
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    if !src.ends_with('\n') {
+        stripped.pop();
+    }
+    stripped
+}
+
+/// Smooth over the parts of command output which vary between runs and
+/// machines, so that only meaningful differences remain.
+fn normalize(output: &str) -> String {
+    static REPLACEMENTS: LazyLock<Vec<(Regex, &str)>> = LazyLock::new(|| {
+        [
+            (
+                r"(Compiling|Checking) ([^ ]+) v([^ ]+) \(.*\)",
+                "$1 $2 v$3 (file:///projects/$2)",
+            ),
+            (
+                r"(Finished `[^`]+` profile \[[^\]]+\] target\(s\)) in [0-9.]+m?s",
+                "$1 in <time>",
+            ),
+            (r"finished in [0-9.]+s", "finished in <time>"),
+            (r"\(target/debug/deps/([^-\s]+)-[0-9a-f]+\)", "(target/debug/deps/$1-<hash>)"),
+            (
+                r"^(\s*-->\s+).*\.rustup/toolchains/([0-9]+\.[0-9]+)[^/]*",
+                "${1}file:///home/.rustup/toolchains/$2",
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (Regex::new(pattern).unwrap(), replacement)
+        })
+        .collect()
+    });
+
+    output
+        .lines()
+        .map(|line| {
+            REPLACEMENTS.iter().fold(
+                line.trim_end().to_string(),
+                |line, (regex, with)| {
+                    regex.replace_all(&line, *with).into_owned()
+                },
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+/// A line-by-line diff, with `-` for lines only in `expected` and `+` for lines
+/// only in `actual`.
+fn diff(expected: &str, actual: &str) -> String {
+    line_diff::lines(expected, actual)
+        .into_iter()
+        .map(|line| match line {
+            line_diff::Line::Same(text) => format!("  {text}"),
+            line_diff::Line::Removed(text) => format!("- {text}"),
+            line_diff::Line::Added(text) => format!("+ {text}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+use super::*;
+
+#[test]
+fn finds_output_includes_in_console_blocks() {
+    let src = r#"Some text.
+
+```console
+{{#include ../listings/ch01-example/listing-01-01/output.txt}}
+```
+
+```rust
+{{#include ../listings/ch01-example/listing-01-01/output.txt}}
+```
+
+```text
+{{#include ../listings/ch01-example/listing-01-02/output.txt}}
+```
+
+```console
+$ cargo run
+   Compiling example v0.1.0 (file:///projects/example)
+```
+
+```console
+{{#include ../listings/ch01-example/listing-01-03/output.txt}}
+```
+"#;
+
+    assert_eq!(
+        console_includes(src),
+        vec![
+            "../listings/ch01-example/listing-01-01/output.txt",
+            "../listings/ch01-example/listing-01-03/output.txt",
+        ]
+    );
+}
+
+#[test]
+fn strips_anchor_comments() {
+    let src = "// ANCHOR: all\nfn main() {\n    // ANCHOR: here\n    println!(\"hi\");\n    // ANCHOR_END: here\n}\n// ANCHOR_END: all\n";
+    assert_eq!(
+        strip_anchors(src),
+        "fn main() {\n    println!(\"hi\");\n}\n"
+    );
+}
+
+#[test]
+fn normalizes_what_varies_between_runs() {
+    let output = "$ cargo test
+   Compiling adder v0.1.0 (/Users/someone/book/tmp/console/listings/ch11/listing-11-01)
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.57s
+     Running unittests src/lib.rs (target/debug/deps/adder-92948b65e88960b4)
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+   --> /Users/someone/.rustup/toolchains/1.85-aarch64-apple-darwin/lib/rustlib/src/rust/library/core/src/ops/function.rs:250:5   ";
+
+    assert_eq!(
+        normalize(output),
+        "$ cargo test
+   Compiling adder v0.1.0 (file:///projects/adder)
+    Finished `test` profile [unoptimized + debuginfo] target(s) in <time>
+     Running unittests src/lib.rs (target/debug/deps/adder-<hash>)
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in <time>
+   --> file:///home/.rustup/toolchains/1.85/lib/rustlib/src/rust/library/core/src/ops/function.rs:250:5"
+    );
+}
+
+#[test]
+fn output_already_in_the_book_normalizes_to_itself() {
+    let output = "$ cargo run
+   Compiling variables v0.1.0 (file:///projects/variables)
+    Finished `dev` profile [unoptimized + debuginfo] target(s) in 0.30s
+     Running `target/debug/variables`
+The value of x is: 5";
+
+    assert_eq!(normalize(output), normalize(&normalize(output)));
+    assert!(normalize(output).ends_with("The value of x is: 5"));
+}
+
+#[test]
+fn diffs_by_line() {
+    let expected = "$ cargo run\nThe value of x is: 5\nThe value of x is: 6";
+    let actual = "$ cargo run\nThe value of x is: 5\nThe value of x is: 7";
+
+    assert_eq!(
+        diff(expected, actual),
+        "  $ cargo run
+  The value of x is: 5
+- The value of x is: 6
++ The value of x is: 7"
+    );
+}
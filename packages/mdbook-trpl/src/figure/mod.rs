@@ -1,18 +1,56 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::{anyhow, Result};
 use html_parser::{Dom, Node};
 use mdbook::{book::Book, preprocess::Preprocessor, BookItem};
 
-use pulldown_cmark::Event;
+use pulldown_cmark::{html, Event};
 use pulldown_cmark_to_cmark::cmark;
 
-use crate::{config::Mode, CompositeError};
+use crate::{
+    config::Mode,
+    numbering::{attributes, number_book, Kind, Numbering},
+    CompositeError,
+};
 
-/// A simple preprocessor to rewrite `<figure>`s with `<img>`s.
+/// A preprocessor for figures: numbered, captioned images.
+///
+/// Write a figure as a single `<Figure>` tag, on one line like `<Listing>`:
+///
+/// ```markdown
+/// <Figure id="concurrent-work" src="img/trpl17-01.svg" alt="A diagram of …" caption="A *concurrent* workflow" />
+/// ```
+///
+/// Figures are numbered in the order they appear, starting over with each
+/// chapter and counting across all of its sections, as with listings; a
+/// `number` attribute sets the number explicitly, and the count picks up from
+/// there. Anywhere in the book’s text, `{{figure-ref concurrent-work}}` then
+/// becomes a link to the figure with an `id`, with the text “Figure 17-1”.
+///
+/// With no configuration, or with `output-mode = "default"`, that renders as:
+///
+/// ```markdown
+/// <figure id="figure-17-1">
 ///
-/// This is a no-op by default; it only operates on the book chapters when the
-/// `[preprocessor.trpl-figure]` has `output-mode = "simple"`.
+/// <img src="img/trpl17-01.svg" class="center" alt="A diagram of …" />
 ///
-/// Takes in Markdown containing like this:
+/// <figcaption><a href="#figure-17-1">Figure 17-1</a>: A <em>concurrent</em> workflow</figcaption>
+///
+/// </figure>
+/// ```
+///
+/// SVGs often need different colors to be legible on the dark themes. When
+/// there is a dark variant of the image, named like `trpl17-01-dark.svg` next
+/// to the original or given explicitly with a `dark-src` attribute, the figure
+/// gets both images, with the classes `figure-light` and `figure-dark`, and
+/// `theme/figure.css` shows whichever one matches the reader’s theme.
+///
+/// With `output-mode = "simple"`, the figure is just its (light) image and a
+/// plain-text caption, references are just their text, and this also rewrites
+/// any `<figure>`s written directly as HTML, like this:
 ///
 /// ```markdown
 /// <figure>
@@ -24,7 +62,7 @@ use crate::{config::Mode, CompositeError};
 /// </figure>
 /// ```
 ///
-/// Spits out Markdown like this:
+/// Into Markdown like this:
 ///
 /// ```markdown
 ///
@@ -33,11 +71,17 @@ use crate::{config::Mode, CompositeError};
 /// Figure 1-2: A description of the image
 ///
 /// ```
+///
+/// A `<Figure>` without a `src`, `alt`, or `caption`, a duplicate `id`, or a
+/// reference to an `id` no figure has fails the build.
 pub struct TrplFigure;
 
 impl TrplFigure {
     pub fn supports_renderer(&self, renderer: &str) -> bool {
-        renderer == "html" || renderer == "markdown" || renderer == "test"
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
     }
 }
 
@@ -51,15 +95,32 @@ impl Preprocessor for TrplFigure {
         ctx: &mdbook::preprocess::PreprocessorContext,
         mut book: Book,
     ) -> Result<Book> {
-        // The `<figure>`-based output is only replaced in the `Simple` mode.
-        let Mode::Simple = Mode::from_context(ctx, self.name())? else {
-            return Ok(book);
-        };
+        let mode = Mode::from_context(ctx, self.name())?;
+        let src_dir = ctx.root.join(&ctx.config.book.src);
 
+        let numbering = number_book(&book, Kind::Figure)?;
         let mut errors = vec![];
         book.for_each_mut(|item| {
             if let BookItem::Chapter(ref mut chapter) = item {
-                match rewrite_figure(&chapter.content) {
+                let source_path = chapter.source_path.as_deref();
+                let chapter_dir = source_path.and_then(|path| {
+                    src_dir.join(path).parent().map(PathBuf::from)
+                });
+                let rewritten = rewrite_figures(
+                    &numbering,
+                    &chapter.content,
+                    source_path,
+                    chapter_dir.as_deref(),
+                    mode,
+                )
+                .map_err(|reason| anyhow!(reason))
+                .and_then(|rewritten| match mode {
+                    // Hand-written `<figure>`s are only replaced in the
+                    // `Simple` mode.
+                    Mode::Default => Ok(rewritten),
+                    Mode::Simple => rewrite_figure(&rewritten),
+                });
+                match rewritten {
                     Ok(rewritten) => chapter.content = rewritten,
                     Err(reason) => errors.push(reason),
                 }
@@ -74,11 +135,129 @@ impl Preprocessor for TrplFigure {
     }
 }
 
-const OPEN_FIGURE: &'static str = "<figure>";
-const CLOSE_FIGURE: &'static str = "</figure>";
+/// Render each `<Figure>` in the chapter at `source_path`, and replace each
+/// `{{figure-ref}}` with a reference to its figure. Dark variants of images
+/// are looked for relative to `chapter_dir`.
+fn rewrite_figures(
+    numbering: &Numbering,
+    src: &str,
+    source_path: Option<&Path>,
+    chapter_dir: Option<&Path>,
+    mode: Mode,
+) -> Result<String, String> {
+    let mut numbers = numbering.numbers_in(source_path);
+    numbering.rewrite(src, source_path, mode, |line| {
+        let number = numbers.next().ok_or_else(|| {
+            String::from("Cannot number a figure in a draft chapter")
+        })?;
+        let figure = NumberedFigure::from_attributes(
+            attributes(line),
+            number,
+            chapter_dir,
+        )?;
+        Ok(match mode {
+            Mode::Default => figure.html(),
+            Mode::Simple => figure.markdown(),
+        })
+    })
+}
+
+/// A `<Figure>`, ready to render.
+#[derive(Debug, PartialEq)]
+struct NumberedFigure {
+    number: String,
+    src: String,
+    dark_src: Option<String>,
+    alt: String,
+    caption: String,
+}
+
+impl NumberedFigure {
+    fn from_attributes(
+        mut attributes: HashMap<String, String>,
+        number: &str,
+        chapter_dir: Option<&Path>,
+    ) -> Result<NumberedFigure, String> {
+        let mut required = |name: &str| {
+            attributes.remove(name).ok_or_else(|| {
+                format!("Figure {number} is missing its `{name}` attribute")
+            })
+        };
+        let src = required("src")?;
+        let alt = required("alt")?;
+        let caption = required("caption")?;
+        let dark_src = attributes
+            .remove("dark-src")
+            .or_else(|| dark_variant(&src, chapter_dir?));
+
+        Ok(NumberedFigure {
+            number: number.to_string(),
+            src,
+            dark_src,
+            alt,
+            caption,
+        })
+    }
+
+    fn html(&self) -> String {
+        let NumberedFigure {
+            number,
+            src,
+            dark_src,
+            alt,
+            caption,
+        } = self;
+
+        let images = match dark_src {
+            Some(dark_src) => format!(
+                "<img src=\"{src}\" class=\"center figure-light\" alt=\"{alt}\" />\n\
+                 <img src=\"{dark_src}\" class=\"center figure-dark\" alt=\"{alt}\" />"
+            ),
+            None => format!("<img src=\"{src}\" class=\"center\" alt=\"{alt}\" />"),
+        };
+
+        let mut caption_html = String::with_capacity(caption.len() * 2);
+        html::push_html(&mut caption_html, crate::parser(caption));
+        let caption_html = caption_html
+            .trim()
+            .trim_start_matches("<p>")
+            .trim_end_matches("</p>");
+
+        format!(
+            "<figure id=\"figure-{number}\">\n\n{images}\n\n\
+             <figcaption><a href=\"#figure-{number}\">Figure {number}</a>: \
+             {caption_html}</figcaption>\n\n</figure>"
+        )
+    }
+
+    fn markdown(&self) -> String {
+        let NumberedFigure {
+            number,
+            src,
+            alt,
+            caption,
+            ..
+        } = self;
+        format!(
+            "<img src=\"{src}\" class=\"center\" alt=\"{alt}\" />\n\n\
+             Figure {number}: {caption}"
+        )
+    }
+}
+
+/// The dark variant of the SVG at `src`, relative to `chapter_dir`, if there is
+/// one: `img/trpl17-01.svg` has `img/trpl17-01-dark.svg`.
+fn dark_variant(src: &str, chapter_dir: &Path) -> Option<String> {
+    let stem = src.strip_suffix(".svg")?;
+    let dark_src = format!("{stem}-dark.svg");
+    chapter_dir.join(&dark_src).is_file().then_some(dark_src)
+}
+
+const OPEN_FIGURE: &str = "<figure>";
+const CLOSE_FIGURE: &str = "</figure>";
 
-const OPEN_CAPTION: &'static str = "<figcaption>";
-const CLOSE_CAPTION: &'static str = "</figcaption>";
+const OPEN_CAPTION: &str = "<figcaption>";
+const CLOSE_CAPTION: &str = "</figcaption>";
 
 fn rewrite_figure(text: &str) -> Result<String> {
     let final_state = crate::parser(text).try_fold(
@@ -131,25 +310,23 @@ fn rewrite_figure(text: &str) -> Result<String> {
                 {
                     if fig.in_caption {
                         return Err(anyhow!(bad_open(OPEN_CAPTION)));
-                    } else {
-                        if tag.trim().ends_with(CLOSE_CAPTION) {
-                            let text = Dom::parse(tag.as_ref())?
-                                .children
-                                .into_iter()
-                                .filter_map(text_of)
-                                .collect::<String>();
-
-                            if text.is_empty() {
-                                return Err(anyhow!(
-                                    "Missing caption in `<figcaption>`"
-                                ));
-                            }
-
-                            fig.events.push(Event::Text(text.into()));
-                        } else {
-                            fig.events.push(Event::Text("\n".into()));
-                            fig.in_caption = true;
+                    } else if tag.trim().ends_with(CLOSE_CAPTION) {
+                        let text = Dom::parse(tag.as_ref())?
+                            .children
+                            .into_iter()
+                            .filter_map(text_of)
+                            .collect::<String>();
+
+                        if text.is_empty() {
+                            return Err(anyhow!(
+                                "Missing caption in `<figcaption>`"
+                            ));
                         }
+
+                        fig.events.push(Event::Text(text.into()));
+                    } else {
+                        fig.events.push(Event::Text("\n".into()));
+                        fig.in_caption = true;
                     }
                 }
 
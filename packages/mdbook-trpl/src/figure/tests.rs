@@ -58,3 +58,200 @@ fn unclosed_caption() {
     let actual = format!("{:?}", result.unwrap_err());
     assert_eq!(actual, "Unclosed `<figcaption>`");
 }
+
+mod figure_tags {
+    use super::*;
+
+    const SECTION_ONE: &str = r#"See {{figure-ref concurrent}}.
+
+<Figure id="concurrent" src="img/trpl17-01.svg" alt="Two tasks" caption="A *concurrent* workflow" />
+"#;
+
+    const SECTION_TWO: &str = r#"<Figure src="img/trpl17-02.svg" alt="Two more tasks" caption="A parallel workflow" />
+
+Compare {{figure-ref concurrent}}, and `{{figure-ref concurrent}}` in code:
+
+```text
+<Figure src="not/a/figure.svg" />
+```
+"#;
+
+    fn numbered(chapters: &[(&str, &str)]) -> Numbering {
+        let mut numbering = Numbering::new(Kind::Figure);
+        for (path, src) in chapters {
+            numbering
+                .add_chapter(Some(17), Some(Path::new(path)), src)
+                .unwrap();
+        }
+        numbering
+    }
+
+    fn rewrite(
+        numbering: &Numbering,
+        path: &str,
+        src: &str,
+        mode: Mode,
+    ) -> Result<String, String> {
+        rewrite_figures(numbering, src, Some(Path::new(path)), None, mode)
+    }
+
+    #[test]
+    fn numbered_across_sections_with_captions() {
+        let numbering = numbered(&[
+            ("ch17-00-async-await.md", SECTION_ONE),
+            ("ch17-01-futures-and-syntax.md", SECTION_TWO),
+        ]);
+
+        assert_eq!(
+            rewrite(
+                &numbering,
+                "ch17-00-async-await.md",
+                SECTION_ONE,
+                Mode::Default
+            )
+            .unwrap(),
+            r##"See [Figure 17-1](#figure-17-1).
+
+<figure id="figure-17-1">
+
+<img src="img/trpl17-01.svg" class="center" alt="Two tasks" />
+
+<figcaption><a href="#figure-17-1">Figure 17-1</a>: A <em>concurrent</em> workflow</figcaption>
+
+</figure>
+"##
+        );
+
+        assert_eq!(
+            rewrite(
+                &numbering,
+                "ch17-01-futures-and-syntax.md",
+                SECTION_TWO,
+                Mode::Simple
+            )
+            .unwrap(),
+            r#"<img src="img/trpl17-02.svg" class="center" alt="Two more tasks" />
+
+Figure 17-2: A parallel workflow
+
+Compare Figure 17-1, and `Figure 17-1` in code:
+
+```text
+<Figure src="not/a/figure.svg" />
+```
+"#
+        );
+    }
+
+    #[test]
+    fn references_link_across_chapters() {
+        let numbering = numbered(&[
+            ("ch17-00-async-await.md", SECTION_ONE),
+            ("nested/ch17-01-futures-and-syntax.md", SECTION_TWO),
+        ]);
+        let rewritten = rewrite(
+            &numbering,
+            "nested/ch17-01-futures-and-syntax.md",
+            SECTION_TWO,
+            Mode::Default,
+        )
+        .unwrap();
+        assert!(rewritten.contains(
+            "Compare [Figure 17-1](../ch17-00-async-await.md#figure-17-1)"
+        ));
+    }
+
+    #[test]
+    fn explicit_numbers_set_the_count() {
+        let numbering = numbered(&[(
+            "ch17-00-async-await.md",
+            r#"<Figure number="17-5" src="a.svg" alt="A" caption="A" />
+<Figure id="next" src="b.svg" alt="B" caption="B" />"#,
+        )]);
+        assert_eq!(numbering.number("next"), Some("17-6"));
+    }
+
+    #[test]
+    fn dark_variants_are_shown_for_dark_themes() {
+        let src = r#"<Figure src="img/trpl17-01.svg" dark-src="img/night.svg" alt="Tasks" caption="Tasks" />"#;
+        let numbering = numbered(&[("ch17-00-async-await.md", src)]);
+        let rewritten =
+            rewrite(&numbering, "ch17-00-async-await.md", src, Mode::Default)
+                .unwrap();
+        assert!(rewritten.contains(
+            r#"<img src="img/trpl17-01.svg" class="center figure-light" alt="Tasks" />
+<img src="img/night.svg" class="center figure-dark" alt="Tasks" />"#
+        ));
+
+        // The simple output is for print, which only gets the light image.
+        let simple =
+            rewrite(&numbering, "ch17-00-async-await.md", src, Mode::Simple)
+                .unwrap();
+        assert!(!simple.contains("night.svg"));
+    }
+
+    #[test]
+    fn dark_variants_are_found_next_to_the_image() {
+        let dir = std::env::temp_dir().join("mdbook-trpl-figure-dark-variant");
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img/trpl17-01-dark.svg"), "<svg/>").unwrap();
+
+        let figure = NumberedFigure::from_attributes(
+            attributes(
+                r#"<Figure src="img/trpl17-01.svg" alt="A" caption="A" />"#,
+            ),
+            "17-1",
+            Some(&dir),
+        )
+        .unwrap();
+        assert_eq!(figure.dark_src.as_deref(), Some("img/trpl17-01-dark.svg"));
+
+        let figure = NumberedFigure::from_attributes(
+            attributes(
+                r#"<Figure src="img/trpl17-02.svg" alt="A" caption="A" />"#,
+            ),
+            "17-2",
+            Some(&dir),
+        )
+        .unwrap();
+        assert_eq!(figure.dark_src, None);
+    }
+
+    #[test]
+    fn missing_attributes() {
+        let src = r#"<Figure src="a.svg" caption="A" />"#;
+        let numbering = numbered(&[("ch17-00-async-await.md", src)]);
+        assert_eq!(
+            rewrite(&numbering, "ch17-00-async-await.md", src, Mode::Default)
+                .unwrap_err(),
+            "Figure 17-1 is missing its `alt` attribute"
+        );
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let mut numbering = Numbering::new(Kind::Figure);
+        let result = numbering.add_chapter(
+            Some(17),
+            Some(Path::new("ch17-00-async-await.md")),
+            r#"<Figure id="same" src="a.svg" alt="A" caption="A" />
+<Figure id="same" src="b.svg" alt="B" caption="B" />"#,
+        );
+        assert_eq!(result.unwrap_err(), "Duplicate figure id 'same'");
+    }
+
+    #[test]
+    fn unknown_references() {
+        let numbering = Numbering::new(Kind::Figure);
+        assert_eq!(
+            rewrite(
+                &numbering,
+                "ch17-00-async-await.md",
+                "See {{figure-ref nowhere}}.",
+                Mode::Default
+            )
+            .unwrap_err(),
+            "No figure with id 'nowhere' for `{{figure-ref nowhere}}`"
+        );
+    }
+}
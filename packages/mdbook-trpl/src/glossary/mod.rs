@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::anyhow;
+use mdbook::{
+    book::Book,
+    errors::Result,
+    preprocess::{Preprocessor, PreprocessorContext},
+    BookItem,
+};
+use regex::Regex;
+
+use crate::{
+    config::Mode,
+    numbering::{lines_with_code_state, relative_path},
+    CompositeError,
+};
+
+/// A preprocessor which builds a glossary from the terms the text marks up, so
+/// that it cannot drift out of date with the chapters.
+///
+/// Mark a term where the book defines it, with the definition after a `|`:
+///
+/// ```markdown
+/// A _{{term: future | A value which may not be ready yet.}}_ is a value that…
+/// ```
+///
+/// The whole term has to be on one line. Anywhere else, `{{term: future}}`
+/// marks a use of the term without defining it again. Both become just the
+/// term’s text, so the text around them decides how it looks. Terms match
+/// regardless of case.
+///
+/// Then `{{glossary}}`, on a line of its own, becomes a list of every term in
+/// alphabetical order, each with its definition and a link back to the first
+/// place the book uses it. With `output-mode = "simple"`, the list names the
+/// section instead of linking to it.
+///
+/// Defining the same term twice, or using a term which is never defined, fails
+/// the build. Terms inside code blocks are left alone.
+///
+/// ```toml
+/// [preprocessor.trpl-glossary]
+/// ```
+///
+/// The glossary itself usually goes in an appendix, which needs an entry in
+/// `SUMMARY.md` like any other chapter.
+pub struct TrplGlossary;
+
+impl Preprocessor for TrplGlossary {
+    fn name(&self) -> &str {
+        "trpl-glossary"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let mode = Mode::from_context(ctx, self.name())?;
+        build_glossary(&mut book, mode)?;
+        Ok(book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
+    }
+}
+
+/// Collect every term in `book`, then rewrite the terms and the glossary.
+pub(crate) fn build_glossary(book: &mut Book, mode: Mode) -> Result<()> {
+    let mut glossary = Glossary::default();
+    let mut errors = vec![];
+    for item in book.iter() {
+        if let BookItem::Chapter(chapter) = item {
+            if let Err(reason) = glossary.add_chapter(
+                &chapter.name,
+                chapter.source_path.as_deref(),
+                &chapter.content,
+            ) {
+                errors.push(anyhow!(reason));
+            }
+        }
+    }
+    if let Err(reason) = glossary.check() {
+        errors.push(anyhow!(reason));
+    }
+
+    if errors.is_empty() {
+        // `for_each_mut` goes through a chapter’s sections before the chapter
+        // itself, unlike `book.iter()`, so each term’s anchor goes in the
+        // chapter `add_chapter` found it in first, not the first one seen here.
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut chapter) = item {
+                chapter.content = glossary.rewrite(
+                    &chapter.content,
+                    chapter.source_path.as_deref(),
+                    mode,
+                );
+            }
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CompositeError(errors).into())
+    }
+}
+
+/// Every term in the book, by its lowercased name.
+#[derive(Debug, Default)]
+struct Glossary {
+    terms: BTreeMap<String, Term>,
+}
+
+#[derive(Debug)]
+struct Term {
+    /// The name as written where the term is defined, or else where it is
+    /// first used.
+    name: String,
+    definition: Option<String>,
+    /// The name of the section the term is first used in.
+    first_section: String,
+    /// The Markdown file of that section, relative to the book’s source
+    /// directory, if any.
+    first_path: Option<PathBuf>,
+}
+
+static TERM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{term:\s*([^|}]*?)\s*(?:\|\s*([^}]*?)\s*)?\}\}").unwrap()
+});
+
+impl Glossary {
+    /// Collect the terms in one chapter (or section) of the book. Chapters
+    /// must be added in the order they appear in the book.
+    fn add_chapter(
+        &mut self,
+        section: &str,
+        source_path: Option<&Path>,
+        src: &str,
+    ) -> Result<(), String> {
+        for (line, in_code) in lines_with_code_state(src) {
+            if in_code {
+                continue;
+            }
+            for captures in TERM.captures_iter(line) {
+                let name = &captures[1];
+                if name.is_empty() {
+                    return Err(format!(
+                        "No term in `{}` in '{section}'",
+                        &captures[0]
+                    ));
+                }
+                let term =
+                    self.terms.entry(key(name)).or_insert_with(|| Term {
+                        name: name.to_string(),
+                        definition: None,
+                        first_section: section.to_string(),
+                        first_path: source_path.map(PathBuf::from),
+                    });
+                if let Some(definition) = captures.get(2) {
+                    if term.definition.is_some() {
+                        return Err(format!(
+                            "Term '{name}' is defined more than once"
+                        ));
+                    }
+                    term.name = name.to_string();
+                    term.definition = Some(definition.as_str().to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every term has to be defined somewhere.
+    fn check(&self) -> Result<(), String> {
+        let undefined: Vec<_> = self
+            .terms
+            .values()
+            .filter(|term| term.definition.is_none())
+            .map(|term| format!("Term '{}' is never defined", term.name))
+            .collect();
+        if undefined.is_empty() {
+            Ok(())
+        } else {
+            Err(undefined.join("\n"))
+        }
+    }
+
+    /// Replace each term with its text, and `{{glossary}}` with the glossary.
+    /// The first use of each term gets an anchor for the glossary to link to,
+    /// if it is in the chapter at `source_path`.
+    fn rewrite(
+        &self,
+        src: &str,
+        source_path: Option<&Path>,
+        mode: Mode,
+    ) -> String {
+        let mut anchored = HashSet::new();
+        let mut rewritten = String::with_capacity(src.len());
+        for (line, in_code) in lines_with_code_state(src) {
+            if in_code {
+                rewritten.push_str(line);
+            } else if line.trim() == "{{glossary}}" {
+                rewritten.push_str(&self.render(source_path, mode));
+            } else {
+                let line =
+                    TERM.replace_all(line, |captures: &regex::Captures| {
+                        let name = &captures[1];
+                        let key = key(name);
+                        let first_used_here =
+                            self.terms.get(&key).is_some_and(|term| {
+                                term.first_path.as_deref() == source_path
+                            });
+                        match mode {
+                            Mode::Default
+                                if first_used_here
+                                    && anchored.insert(key.clone()) =>
+                            {
+                                format!(
+                                    "<span id=\"{}\">{name}</span>",
+                                    anchor(&key)
+                                )
+                            }
+                            _ => name.to_string(),
+                        }
+                    });
+                rewritten.push_str(&line);
+            }
+            rewritten.push('\n');
+        }
+
+        // As with the listing preprocessor, match the input’s trailing
+        // newline, or lack thereof.
+        if !src.ends_with('\n') {
+            rewritten.pop();
+        }
+        rewritten
+    }
+
+    /// The glossary, for the chapter at `from`.
+    fn render(&self, from: Option<&Path>, mode: Mode) -> String {
+        self.terms
+            .iter()
+            .map(|(key, term)| {
+                let definition = term.definition.as_deref().unwrap_or_default();
+                let first_use = match (mode, &term.first_path) {
+                    (Mode::Default, Some(to)) => {
+                        let anchor = anchor(key);
+                        let link = match from {
+                            Some(from) if from != to => {
+                                format!("{}#{anchor}", relative_path(from, to))
+                            }
+                            _ => format!("#{anchor}"),
+                        };
+                        format!(
+                            "[First used in “{}”]({link})",
+                            term.first_section
+                        )
+                    }
+                    _ => format!("First used in “{}”", term.first_section),
+                };
+                format!("- **{}**: {definition} ({first_use}.)", term.name)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Terms match regardless of case, and of how they are spaced.
+fn key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The `id` for the first use of the term with `key`, like `term-future` or
+/// `term-await-a-future`.
+fn anchor(key: &str) -> String {
+    let slug = key
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("term-{slug}")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,236 @@
+use mdbook::book::{Chapter, SectionNumber};
+
+use super::*;
+
+fn collected(chapters: &[(&str, &str, &str)]) -> Glossary {
+    let mut glossary = Glossary::default();
+    for (section, path, src) in chapters {
+        glossary
+            .add_chapter(section, Some(Path::new(path)), src)
+            .unwrap();
+    }
+    glossary
+}
+
+fn glossary() -> Glossary {
+    collected(&[
+        (
+            "Futures and the Async Syntax",
+            "ch17-01.md",
+            "A _{{term: Future | A value which may not be ready yet.}}_ is…\n\
+             Checking on a future is called _{{term: polling}}_.\n",
+        ),
+        (
+            "Working with Any Number of Futures",
+            "nested/ch17-03.md",
+            "More {{term: future}}s, and more \
+             {{term: polling | Checking whether a future is ready.}}.\n",
+        ),
+    ])
+}
+
+#[test]
+fn collects_terms_from_their_first_use() {
+    let glossary = glossary();
+    let terms: Vec<_> = glossary
+        .terms
+        .iter()
+        .map(|(key, term)| {
+            (
+                key.as_str(),
+                term.name.as_str(),
+                term.definition.as_deref(),
+                term.first_path.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        terms,
+        [
+            (
+                "future",
+                "Future",
+                Some("A value which may not be ready yet."),
+                Some(Path::new("ch17-01.md"))
+            ),
+            (
+                "polling",
+                "polling",
+                Some("Checking whether a future is ready."),
+                Some(Path::new("ch17-01.md"))
+            ),
+        ]
+    );
+    assert_eq!(glossary.check(), Ok(()));
+}
+
+#[test]
+fn anchors_only_the_first_use() {
+    let glossary = glossary();
+    let first = glossary.rewrite(
+        "A _{{term: Future | A value which may not be ready yet.}}_ is…\n\
+         Checking on a future is called _{{term: polling}}_.\n",
+        Some(Path::new("ch17-01.md")),
+        Mode::Default,
+    );
+    assert_eq!(
+        first,
+        "A _<span id=\"term-future\">Future</span>_ is…\n\
+         Checking on a future is called \
+         _<span id=\"term-polling\">polling</span>_.\n"
+    );
+
+    let second = glossary.rewrite(
+        "More {{term: future}}s, and more \
+         {{term: polling | Checking whether a future is ready.}}.",
+        Some(Path::new("nested/ch17-03.md")),
+        Mode::Default,
+    );
+    assert_eq!(second, "More futures, and more polling.");
+}
+
+#[test]
+fn plain_terms_in_simple_mode() {
+    let rewritten = glossary().rewrite(
+        "A _{{term: Future | A value which may not be ready yet.}}_ is…",
+        Some(Path::new("ch17-01.md")),
+        Mode::Simple,
+    );
+    assert_eq!(rewritten, "A _Future_ is…");
+}
+
+#[test]
+fn renders_the_glossary_in_order_with_links() {
+    let rewritten = glossary().rewrite(
+        "# Glossary\n\n{{glossary}}\n",
+        Some(Path::new("appendix/glossary.md")),
+        Mode::Default,
+    );
+    assert_eq!(
+        rewritten,
+        "# Glossary\n\n\
+         - **Future**: A value which may not be ready yet. ([First used in \
+         “Futures and the Async Syntax”](../ch17-01.md#term-future).)\n\
+         - **polling**: Checking whether a future is ready. ([First used in \
+         “Futures and the Async Syntax”](../ch17-01.md#term-polling).)\n"
+    );
+}
+
+#[test]
+fn renders_the_glossary_without_links_in_simple_mode() {
+    let rewritten = glossary().rewrite(
+        "{{glossary}}",
+        Some(Path::new("glossary.md")),
+        Mode::Simple,
+    );
+    assert_eq!(
+        rewritten,
+        "- **Future**: A value which may not be ready yet. (First used in \
+         “Futures and the Async Syntax”.)\n\
+         - **polling**: Checking whether a future is ready. (First used in \
+         “Futures and the Async Syntax”.)"
+    );
+}
+
+#[test]
+fn code_blocks_are_left_alone() {
+    let src =
+        "```markdown\n{{term: future | Not really.}}\n{{glossary}}\n```\n";
+    let mut glossary = Glossary::default();
+    glossary.add_chapter("Example", None, src).unwrap();
+    assert!(glossary.terms.is_empty());
+    assert_eq!(glossary.rewrite(src, None, Mode::Default), src);
+}
+
+#[test]
+fn anchors_go_in_the_first_chapter_even_with_sections() {
+    let mut section = Chapter::new(
+        "Futures and the Async Syntax",
+        String::from("Awaiting a {{term: future}}.\n"),
+        "ch17-01.md",
+        vec![String::from("Async and Await")],
+    );
+    section.number = Some(SectionNumber(vec![17, 1]));
+    let mut chapter = Chapter::new(
+        "Async and Await",
+        String::from(
+            "A {{term: future | A value which may not be ready yet.}}.\n",
+        ),
+        "ch17-00.md",
+        vec![],
+    );
+    chapter.number = Some(SectionNumber(vec![17]));
+    chapter.sub_items.push(BookItem::Chapter(section));
+    let appendix = Chapter::new(
+        "Glossary",
+        String::from("{{glossary}}\n"),
+        "appendix-07-glossary.md",
+        vec![],
+    );
+
+    let mut book = Book::new();
+    book.push_item(chapter);
+    book.push_item(appendix);
+    build_glossary(&mut book, Mode::Default).unwrap();
+
+    let contents: Vec<_> = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => Some(chapter.content.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        contents,
+        [
+            "A <span id=\"term-future\">future</span>.\n",
+            "Awaiting a future.\n",
+            "- **future**: A value which may not be ready yet. ([First used \
+             in “Async and Await”](ch17-00.md#term-future).)\n",
+        ]
+    );
+}
+
+mod errors {
+    use super::*;
+
+    #[test]
+    fn defined_twice() {
+        let mut glossary = Glossary::default();
+        let result = glossary.add_chapter(
+            "Twice",
+            None,
+            "{{term: trait | One.}} and {{term: Trait | Two.}}",
+        );
+        assert_eq!(
+            result,
+            Err("Term 'Trait' is defined more than once".into())
+        );
+    }
+
+    #[test]
+    fn never_defined() {
+        let glossary = collected(&[(
+            "Undefined",
+            "ch01.md",
+            "{{term: crate}} and {{term: module}}",
+        )]);
+        assert_eq!(
+            glossary.check(),
+            Err("Term 'crate' is never defined\n\
+                 Term 'module' is never defined"
+                .into())
+        );
+    }
+
+    #[test]
+    fn empty_term() {
+        let mut glossary = Glossary::default();
+        let result =
+            glossary.add_chapter("Empty", None, "{{term: | Nothing.}}");
+        assert_eq!(
+            result,
+            Err("No term in `{{term: | Nothing.}}` in 'Empty'".into())
+        );
+    }
+}
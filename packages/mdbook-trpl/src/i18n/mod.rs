@@ -0,0 +1,225 @@
+//! Support for translating the text the preprocessors add or move around, for
+//! gettext-style translation workflows like the one `mdbook-i18n-helpers`
+//! provides.
+//!
+//! Translating a chapter’s Markdown works paragraph by paragraph, which misses
+//! the text in a `<Listing>`’s `caption` attribute and the labels the
+//! preprocessors themselves write out, like “Listing” and “Filename:”. So each
+//! preprocessor can list its messages as a gettext template (`.pot`), where
+//! each message has a *context* (`msgctxt`) which stays the same as long as
+//! what it belongs to does, like `trpl-listing:9-3:caption` for the caption of
+//! Listing 9-3, however its English text changes. Translators merge that into
+//! their catalog along with everything else, and the preprocessors read their
+//! translations back out of the same catalog.
+//!
+//! Following `mdbook-i18n-helpers`, the catalog is `<po-dir>/<language>.po`,
+//! where `language` is `book.language` and `po-dir` comes from
+//! `[preprocessor.gettext]`, and is `po` by default. Building the book without
+//! a language, or for a language with no catalog, leaves everything in
+//! English.
+
+use std::{collections::HashMap, fs};
+
+use mdbook::preprocess::PreprocessorContext;
+
+/// A message for translators.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Message {
+    /// The stable identifier for the message, written as the `msgctxt`.
+    pub(crate) context: String,
+    /// The English text, written as the `msgid`.
+    pub(crate) text: String,
+    /// Where the text came from, like `src/ch09-02.md:32`, if anywhere.
+    pub(crate) location: Option<String>,
+}
+
+impl Message {
+    pub(crate) fn new(
+        context: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Message {
+        Message {
+            context: context.into(),
+            text: text.into(),
+            location: None,
+        }
+    }
+
+    pub(crate) fn at(mut self, location: String) -> Message {
+        self.location = Some(location);
+        self
+    }
+}
+
+/// Write `messages` as a gettext template. Messages with the same context and
+/// text become one entry, listing every place they came from.
+pub(crate) fn pot(messages: &[Message]) -> String {
+    let mut entries: Vec<(&str, &str, Vec<&str>)> = vec![];
+    for message in messages {
+        let existing = entries.iter_mut().find(|(context, text, _)| {
+            *context == message.context && *text == message.text
+        });
+        let locations = match existing {
+            Some((_, _, locations)) => locations,
+            None => {
+                entries.push((&message.context, &message.text, vec![]));
+                &mut entries.last_mut().unwrap().2
+            }
+        };
+        if let Some(location) = &message.location {
+            locations.push(location);
+        }
+    }
+
+    let mut pot = String::from(
+        "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n",
+    );
+    for (context, text, locations) in entries {
+        pot.push('\n');
+        for location in locations {
+            pot.push_str(&format!("#: {location}\n"));
+        }
+        pot.push_str(&format!("msgctxt {}\n", quote(context)));
+        pot.push_str(&format!("msgid {}\n", quote(text)));
+        pot.push_str("msgstr \"\"\n");
+    }
+    pot
+}
+
+/// The translations from a gettext catalog.
+#[derive(Debug, Default)]
+pub(crate) struct Catalog {
+    translations: HashMap<(String, String), String>,
+}
+
+impl Catalog {
+    /// The catalog for the language the book is being built in, if there is
+    /// one.
+    pub(crate) fn from_context(
+        ctx: &PreprocessorContext,
+    ) -> Result<Option<Catalog>, String> {
+        let Some(language) = &ctx.config.book.language else {
+            return Ok(None);
+        };
+        let po_dir = ctx
+            .config
+            .get("preprocessor.gettext.po-dir")
+            .and_then(|value| value.as_str())
+            .unwrap_or("po");
+        let path = ctx.root.join(po_dir).join(format!("{language}.po"));
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let po = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        Catalog::parse(&po)
+            .map(Some)
+            .map_err(|e| format!("Bad catalog '{}': {e}", path.display()))
+    }
+
+    /// Read the translations from the text of a `.po` file, where entries
+    /// are separated by blank lines. Entries without a translation and those
+    /// marked `fuzzy` are left out, as `msgfmt` does.
+    pub(crate) fn parse(po: &str) -> Result<Catalog, String> {
+        let mut catalog = Catalog::default();
+        let mut lines = po.lines().enumerate().peekable();
+        while lines.peek().is_some() {
+            let mut context = String::new();
+            let mut id = String::new();
+            let mut translation = String::new();
+            let mut fuzzy = false;
+            let mut field = None;
+
+            for (index, line) in lines.by_ref() {
+                let line = line.trim();
+                if line.is_empty() {
+                    break;
+                }
+                if line.starts_with('#') {
+                    fuzzy |= line.starts_with("#,") && line.contains("fuzzy");
+                    continue;
+                }
+
+                let rest = if let Some(rest) = line.strip_prefix("msgctxt ") {
+                    field = Some(&mut context);
+                    rest
+                } else if let Some(rest) = line.strip_prefix("msgid ") {
+                    field = Some(&mut id);
+                    rest
+                } else if let Some(rest) = line.strip_prefix("msgstr ") {
+                    field = Some(&mut translation);
+                    rest
+                } else if line.starts_with('"') {
+                    line
+                } else {
+                    // The preprocessors have no plural messages.
+                    field = None;
+                    continue;
+                };
+
+                let text = unquote(rest).ok_or_else(|| {
+                    format!("line {}: bad string {rest}", index + 1)
+                })?;
+                if let Some(field) = field.as_deref_mut() {
+                    field.push_str(&text);
+                }
+            }
+
+            if !fuzzy && !id.is_empty() && !translation.is_empty() {
+                catalog.translations.insert((context, id), translation);
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// The translation of `text` in `context`, if there is one.
+    pub(crate) fn get(&self, context: &str, text: &str) -> Option<&str> {
+        self.translations
+            .get(&(context.to_string(), text.to_string()))
+            .map(String::as_str)
+    }
+
+    /// The translation of `text` in `context`, or `text` itself.
+    pub(crate) fn translate<'a>(
+        catalog: Option<&'a Catalog>,
+        context: &str,
+        text: &'a str,
+    ) -> &'a str {
+        catalog
+            .and_then(|catalog| catalog.get(context, text))
+            .unwrap_or(text)
+    }
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            c @ ('"' | '\\') => text.push(c),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,109 @@
+use super::*;
+
+#[test]
+fn template_merges_repeated_messages() {
+    let pot = pot(&[
+        Message::new("trpl-listing:label", "Listing"),
+        Message::new("trpl-listing:9-3:caption", "Opening a \"file\"")
+            .at(String::from("src/ch09-02.md:32")),
+        Message::new("trpl-listing:caption", "Same")
+            .at(String::from("src/ch01-01.md:1")),
+        Message::new("trpl-listing:caption", "Same")
+            .at(String::from("src/ch01-01.md:9")),
+    ]);
+
+    assert_eq!(
+        pot,
+        r#"msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+msgctxt "trpl-listing:label"
+msgid "Listing"
+msgstr ""
+
+#: src/ch09-02.md:32
+msgctxt "trpl-listing:9-3:caption"
+msgid "Opening a \"file\""
+msgstr ""
+
+#: src/ch01-01.md:1
+#: src/ch01-01.md:9
+msgctxt "trpl-listing:caption"
+msgid "Same"
+msgstr ""
+"#
+    );
+}
+
+#[test]
+fn catalog_reads_translations() {
+    let catalog = Catalog::parse(
+        r#"# German translations
+msgid ""
+msgstr ""
+"Language: de\n"
+
+#: src/ch09-02.md:32
+msgctxt "trpl-listing:9-3:caption"
+msgid "Opening a file"
+msgstr ""
+"Eine Datei "
+"öffnen"
+
+msgctxt "trpl-listing:label"
+msgid "Listing"
+msgstr "Codeblock"
+
+#, fuzzy
+msgctxt "trpl-note:label"
+msgid "Warning:"
+msgstr "Achtung:"
+
+msgctxt "trpl-note:label"
+msgid "Tip:"
+msgstr ""
+
+msgid "No context \"here\""
+msgstr "Kein Kontext „hier“"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        catalog.get("trpl-listing:9-3:caption", "Opening a file"),
+        Some("Eine Datei öffnen")
+    );
+    assert_eq!(
+        catalog.get("trpl-listing:label", "Listing"),
+        Some("Codeblock")
+    );
+    assert_eq!(
+        catalog.get("", "No context \"here\""),
+        Some("Kein Kontext „hier“")
+    );
+
+    // Fuzzy and untranslated entries do not count.
+    assert_eq!(catalog.get("trpl-note:label", "Warning:"), None);
+    assert_eq!(catalog.get("trpl-note:label", "Tip:"), None);
+
+    // The context has to match.
+    assert_eq!(
+        catalog.get("trpl-listing:9-4:caption", "Opening a file"),
+        None
+    );
+    assert_eq!(
+        Catalog::translate(
+            Some(&catalog),
+            "trpl-listing:9-4:caption",
+            "Opening a file"
+        ),
+        "Opening a file"
+    );
+}
+
+#[test]
+fn bad_strings_are_an_error() {
+    let result = Catalog::parse("msgid \"unterminated\nmsgstr \"\"\n");
+    assert_eq!(result.unwrap_err(), "line 1: bad string \"unterminated");
+}
@@ -1,15 +1,26 @@
 mod config;
+mod console;
 mod figure;
+mod glossary;
 mod heading;
+mod i18n;
 mod listing;
 mod note;
+mod numbering;
+mod playground;
+mod quiz;
 
 pub use config::Mode;
+pub use console::TrplConsole as Console;
 pub use figure::TrplFigure as Figure;
+pub use glossary::TrplGlossary as Glossary;
 pub use heading::TrplHeading as Heading;
 pub use listing::TrplListing as Listing;
 pub use note::TrplNote as Note;
+pub use numbering::TrplNumbering as Numbering;
+pub use playground::TrplPlayground as Playground;
 use pulldown_cmark::{Options, Parser};
+pub use quiz::TrplQuiz as Quiz;
 
 /// Convenience function to get a parser matching `mdbook::new_cmark_parser`.
 ///
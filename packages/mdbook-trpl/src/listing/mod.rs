@@ -1,3 +1,8 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
 use anyhow::anyhow;
 use html_parser::Dom;
 use mdbook::{
@@ -6,10 +11,14 @@ use mdbook::{
     preprocess::{Preprocessor, PreprocessorContext},
     BookItem,
 };
-use pulldown_cmark::{html, Event};
+use pulldown_cmark::{html, CodeBlockKind, Event, Tag, TagEnd};
 use pulldown_cmark_to_cmark::cmark;
 
-use crate::{config::Mode, CompositeError};
+use crate::{
+    config::Mode,
+    i18n::{self, Catalog, Message},
+    numbering, CompositeError,
+};
 
 /// A preprocessor for rendering listings more elegantly.
 ///
@@ -53,8 +62,137 @@ use crate::{config::Mode, CompositeError};
 ///
 /// Listing 1-2: Some *text*, yeah?
 /// ````
+///
+/// That includes listings inside block quotes, like the ones asides use, which
+/// keep their `>` markers.
+///
+/// Instead of writing out `number` and `file-name`, a listing can point to the
+/// file it shows with a `path` attribute, relative to the chapter’s Markdown
+/// file, just like the `{{#rustdoc_include}}` inside it:
+///
+/// ```markdown
+/// <Listing path="../listings/ch01-getting-started/listing-01-02/src/main.rs" caption="Some *text*, yeah?">
+/// ```
+///
+/// The preprocessor then takes the file name from where the file sits in its
+/// listing package (the nearest directory above it with a `Cargo.toml`), and
+/// the number from the name of that package’s directory, so `listing-01-02`
+/// becomes listing 1-2. If the file does not exist, the build fails. Explicit
+/// `number` and `file-name` attributes still take precedence when given.
+///
+/// When a listing builds on an earlier one, a `diff-from` attribute naming the
+/// earlier listing, like `diff-from="listing-17-38"` (or just `"17-38"`), adds
+/// a collapsed section after the code showing just the lines which changed
+/// since then, as a diff. The earlier listing must come first in the book, and
+/// lines hidden from the reader with `# ` are left out of the comparison. This
+/// only applies to the default output mode: the simple mode ignores it.
+///
+/// To keep boilerplate, like imports, from crowding out the point of a listing
+/// while the listing’s package still compiles, a line of its source can end
+/// with a `// hidden` comment. In Rust code blocks, the preprocessor turns such
+/// lines into rustdoc-style hidden lines, like `# use std::time::Duration;`,
+/// which the HTML version collapses until the reader expands the code block.
+///
+/// When the book is built in another language, captions and the “Listing” and
+/// “Filename:” labels come from its gettext catalog (`po/<language>.po`, as
+/// with `mdbook-i18n-helpers`). Each caption is looked up by the listing’s
+/// number, under a context like `trpl-listing:9-3:caption`, so a translation
+/// stays attached to its listing even as other listings come and go. The
+/// `mdbook-trpl-listing xgettext` command prints the template for all of
+/// these messages.
 pub struct TrplListing;
 
+/// The gettext context for the “Listing” label.
+const LISTING_LABEL: &str = "trpl-listing:label";
+
+/// The gettext context for the “Filename:” label.
+const FILE_NAME_LABEL: &str = "trpl-listing:file-name";
+
+/// The gettext context for a listing’s caption.
+fn caption_context(number: Option<&str>) -> String {
+    match number {
+        Some(number) => format!("trpl-listing:{number}:caption"),
+        None => String::from("trpl-listing:caption"),
+    }
+}
+
+impl TrplListing {
+    /// A gettext template with the labels this preprocessor writes out and
+    /// the caption of every listing in `book`, whose chapters are in `src`
+    /// within the book’s `root` directory.
+    ///
+    /// Listings with an `id` instead of a `number` are numbered first, the
+    /// same way the `trpl-numbering` preprocessor does it before this one
+    /// runs, so their messages match up with what the build sees. Code blocks
+    /// are skipped, so that examples of the syntax are not picked up.
+    pub fn xgettext(
+        &self,
+        book: &Book,
+        root: &Path,
+        src: &Path,
+    ) -> Result<String> {
+        let mut book = book.clone();
+        numbering::number_listings(&mut book, Mode::Simple)?;
+
+        let mut messages = vec![
+            Message::new(LISTING_LABEL, "Listing"),
+            Message::new(FILE_NAME_LABEL, "Filename:"),
+        ];
+        let mut errors = vec![];
+        for item in book.iter() {
+            let BookItem::Chapter(chapter) = item else {
+                continue;
+            };
+            let Some(path) = &chapter.source_path else {
+                continue;
+            };
+            let chapter_dir = root.join(src).join(path);
+            let chapter_dir = chapter_dir.parent();
+
+            for (event, range) in
+                crate::parser(&chapter.content).into_offset_iter()
+            {
+                let Event::Html(tag) = event else {
+                    continue;
+                };
+                if !tag.starts_with("<Listing") {
+                    continue;
+                }
+                let listing =
+                    match ListingBuilder::from_tag(&tag).and_then(|builder| {
+                        builder.build(Mode::Simple, chapter_dir, None)
+                    }) {
+                        Ok(listing) => listing,
+                        Err(reason) => {
+                            errors.push(anyhow!(reason));
+                            continue;
+                        }
+                    };
+                if let Some(caption) = listing.caption {
+                    let line =
+                        chapter.content[..range.start].matches('\n').count()
+                            + 1;
+                    let location =
+                        format!("{}:{line}", src.join(path).display());
+                    messages.push(
+                        Message::new(
+                            caption_context(listing.number.as_deref()),
+                            caption,
+                        )
+                        .at(location),
+                    );
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(i18n::pot(&messages))
+        } else {
+            Err(CompositeError(errors).into())
+        }
+    }
+}
+
 impl Preprocessor for TrplListing {
     fn name(&self) -> &str {
         "trpl-listing"
@@ -63,10 +201,24 @@ impl Preprocessor for TrplListing {
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
         let mode = Mode::from_context(ctx, self.name())?;
 
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+        let catalog = Catalog::from_context(ctx).map_err(|e| anyhow!(e))?;
+
         let mut errors = vec![];
+        let mut earlier = EarlierListings::new();
         book.for_each_mut(|item| {
             if let BookItem::Chapter(ref mut chapter) = item {
-                match rewrite_listing(&chapter.content, mode) {
+                let chapter_dir =
+                    chapter.source_path.as_ref().and_then(|path| {
+                        src_dir.join(path).parent().map(PathBuf::from)
+                    });
+                match rewrite_listing(
+                    &chapter.content,
+                    mode,
+                    chapter_dir.as_deref(),
+                    &mut earlier,
+                    catalog.as_ref(),
+                ) {
                     Ok(rewritten) => chapter.content = rewritten,
                     Err(reason) => errors.push(anyhow!(reason)),
                 }
@@ -81,30 +233,59 @@ impl Preprocessor for TrplListing {
     }
 
     fn supports_renderer(&self, renderer: &str) -> bool {
-        renderer == "html" || renderer == "markdown" || renderer == "test"
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
     }
 }
 
-fn rewrite_listing(src: &str, mode: Mode) -> Result<String, String> {
+/// The code of each numbered listing seen so far, by number, with hidden lines
+/// left out, for `diff-from` to compare against.
+type EarlierListings = HashMap<String, String>;
+
+/// Rewrite every `<Listing>` in `src`. The `chapter_dir` is the directory
+/// containing the chapter’s source file, which `path` attributes are resolved
+/// against; it is `None` for draft chapters, which have no source file. The
+/// `earlier` listings are the ones from the chapters before this one, and this
+/// chapter’s listings get added to them. Translations come from `catalog`, if
+/// the book is being built in another language.
+fn rewrite_listing(
+    src: &str,
+    mode: Mode,
+    chapter_dir: Option<&Path>,
+    earlier: &mut EarlierListings,
+    catalog: Option<&Catalog>,
+) -> Result<String, String> {
+    let src = &hide_marked_lines(src);
     match mode {
         Mode::Default => {
             let final_state = crate::parser(src).try_fold(
                 RewriteState {
                     current: None,
+                    in_code_block: false,
                     events: vec![],
                 },
                 |mut state, ev| -> Result<RewriteState, String> {
                     match ev {
                         Event::Html(tag) => {
                             if tag.starts_with("<Listing") {
-                                state.open_listing(tag, mode)?;
+                                state.open_listing(
+                                    tag,
+                                    mode,
+                                    chapter_dir,
+                                    catalog,
+                                )?;
                             } else if tag.starts_with("</Listing>") {
-                                state.close_listing(tag);
+                                state.close_listing(tag, earlier);
                             } else {
                                 state.events.push(Ok(Event::Html(tag)));
                             }
                         }
-                        ev => state.events.push(Ok(ev)),
+                        ev => {
+                            state.record_code(&ev);
+                            state.events.push(Ok(ev));
+                        }
                     };
                     Ok(state)
                 },
@@ -137,19 +318,25 @@ fn rewrite_listing(src: &str, mode: Mode) -> Result<String, String> {
             let mut rewritten = String::with_capacity(src.len());
             let mut current_closing = None;
             for line in src.lines() {
-                if line.starts_with("<Listing") && (line.ends_with(">")) {
-                    let listing =
-                        ListingBuilder::from_tag(&line)?.build(Mode::Simple);
-                    rewritten.push_str(&listing.opening_text());
+                // Listings in asides and other block quotes get rewritten just
+                // the same, keeping the `> ` markers on every line.
+                let (quote, tag) = split_block_quote(line);
+                if tag.starts_with("<Listing") && (tag.ends_with(">")) {
+                    let listing = ListingBuilder::from_tag(tag)?.build(
+                        Mode::Simple,
+                        chapter_dir,
+                        catalog,
+                    )?;
+                    push_quoted(&mut rewritten, quote, &listing.opening_text());
                     current_closing = Some(listing.closing_text("\n"));
-                } else if line == "</Listing>" {
+                } else if tag == "</Listing>" {
                     let closing =
                         current_closing.as_ref().ok_or_else(|| {
                             String::from(
                                 "Closing `</Listing>` without opening tag.",
                             )
                         })?;
-                    rewritten.push_str(closing);
+                    push_quoted(&mut rewritten, quote, closing);
                 } else {
                     rewritten.push_str(line);
                     rewritten.push('\n');
@@ -169,8 +356,75 @@ fn rewrite_listing(src: &str, mode: Mode) -> Result<String, String> {
     }
 }
 
+/// The comment which marks a line of a listing’s source as one to hide.
+const HIDDEN_MARKER: &str = "// hidden";
+
+/// Turn the lines of Rust code blocks in `src` which end with `// hidden` into
+/// rustdoc-style hidden lines, starting with `# `, without the marker.
+fn hide_marked_lines(src: &str) -> String {
+    let mut rewritten = String::with_capacity(src.len());
+    // The fence of the code block the line is in, if any, and whether the
+    // block is Rust.
+    let mut code_block: Option<(&str, bool)> = None;
+    for line in src.lines() {
+        let trimmed = line.trim_start();
+        let fence_len = trimmed.len() - trimmed.trim_start_matches('`').len();
+        match code_block {
+            None if fence_len >= 3 => {
+                let (fence, info) = trimmed.split_at(fence_len);
+                let language = info.split(',').next().unwrap_or_default();
+                code_block = Some((fence, language.trim() == "rust"));
+            }
+            Some((fence, _))
+                if fence_len >= fence.len()
+                    && trimmed[fence_len..].trim().is_empty() =>
+            {
+                code_block = None;
+            }
+            Some((_, true)) => {
+                if let Some(code) = line.trim_end().strip_suffix(HIDDEN_MARKER)
+                {
+                    rewritten.push_str("# ");
+                    rewritten.push_str(code.trim_end());
+                    rewritten.push('\n');
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        rewritten.push_str(line);
+        rewritten.push('\n');
+    }
+
+    // As in the simple mode, this keeps a missing final newline missing.
+    if !src.ends_with('\n') {
+        rewritten.pop();
+    }
+    rewritten
+}
+
+/// Split the `>` markers of any block quotes `line` is in from the rest of it.
+fn split_block_quote(line: &str) -> (&str, &str) {
+    let mut rest = line;
+    // Other preprocessors’ Markdown output can indent the markers, too.
+    while let Some(after) = rest.trim_start_matches(' ').strip_prefix('>') {
+        rest = after.strip_prefix(' ').unwrap_or(after);
+    }
+    line.split_at(line.len() - rest.len())
+}
+
+/// Push each line of `text` onto `rewritten`, inside the block `quote`.
+fn push_quoted(rewritten: &mut String, quote: &str, text: &str) {
+    for line in text.lines() {
+        let quoted = format!("{quote}{line}");
+        rewritten.push_str(quoted.trim_end());
+        rewritten.push('\n');
+    }
+}
+
 struct RewriteState<'e> {
     current: Option<Listing>,
+    in_code_block: bool,
     events: Vec<Result<Event<'e>, String>>,
 }
 
@@ -179,8 +433,14 @@ impl<'e> RewriteState<'e> {
         &mut self,
         tag: pulldown_cmark::CowStr<'_>,
         mode: Mode,
+        chapter_dir: Option<&Path>,
+        catalog: Option<&Catalog>,
     ) -> Result<(), String> {
-        let listing = ListingBuilder::from_tag(&tag)?.build(mode);
+        let listing = ListingBuilder::from_tag(&tag)?.build(
+            mode,
+            chapter_dir,
+            catalog,
+        )?;
         let opening_event = Event::Html(listing.opening_html().into());
 
         self.current = Some(listing);
@@ -188,19 +448,65 @@ impl<'e> RewriteState<'e> {
         Ok(())
     }
 
-    fn close_listing(&mut self, tag: pulldown_cmark::CowStr<'_>) {
+    /// Keep track of the code in the current listing.
+    fn record_code(&mut self, event: &Event<'_>) {
+        let Some(listing) = &mut self.current else {
+            return;
+        };
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                self.in_code_block = true;
+                if let CodeBlockKind::Fenced(info) = kind {
+                    listing.hides_lines |= info.starts_with("rust");
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => self.in_code_block = false,
+            Event::Text(text) if self.in_code_block => {
+                listing.code.push_str(text);
+            }
+            _ => {}
+        }
+    }
+
+    fn close_listing(
+        &mut self,
+        tag: pulldown_cmark::CowStr<'_>,
+        earlier: &mut EarlierListings,
+    ) {
         let trailing = if !tag.ends_with('>') {
             tag.replace("</Listing>", "")
         } else {
             String::from("")
         };
 
-        match &self.current {
+        match self.current.take() {
             Some(listing) => {
+                let code = listing.visible_code();
+                if let Some(from) = &listing.diff_from {
+                    match earlier.get(from) {
+                        Some(earlier_code) => {
+                            let changes = line_diff::unified(
+                                &line_diff::lines(earlier_code, &code),
+                                3,
+                            );
+                            if !changes.is_empty() {
+                                self.events.push(Ok(Event::Html(
+                                    diff_html(from, &changes).into(),
+                                )));
+                            }
+                        }
+                        None => self.events.push(Err(format!(
+                            "Listing {from} from `diff-from` must come before \
+                             the listing which uses it"
+                        ))),
+                    }
+                }
+                if let Some(number) = &listing.number {
+                    earlier.insert(number.clone(), code);
+                }
+
                 let closing_event =
                     Event::Html(listing.closing_html(&trailing).into());
-
-                self.current = None;
                 self.events.push(Ok(closing_event));
             }
             None => {
@@ -217,9 +523,35 @@ struct Listing {
     number: Option<String>,
     caption: Option<String>,
     file_name: Option<String>,
+    /// The number of the listing to show the changes from, if any.
+    diff_from: Option<String>,
+    /// The “Listing” label, translated if need be.
+    label: String,
+    /// The “Filename:” label, translated if need be.
+    file_name_label: String,
+    /// The contents of the listing’s code blocks, as they are in the source.
+    code: String,
+    /// Whether any of those are Rust code blocks, which can hide lines.
+    hides_lines: bool,
 }
 
 impl Listing {
+    /// The listing’s code as readers see it, without lines hidden by `# `.
+    fn visible_code(&self) -> String {
+        if !self.hides_lines {
+            return self.code.clone();
+        }
+
+        self.code
+            .lines()
+            .filter(|line| {
+                let line = line.trim_start();
+                line != "#" && !line.starts_with("# ")
+            })
+            .flat_map(|line| [line, "\n"])
+            .collect()
+    }
+
     fn opening_html(&self) -> String {
         let id_attribute = self
             .number
@@ -231,7 +563,8 @@ impl Listing {
 
         match self.file_name.as_ref() {
             Some(file_name) => format!(
-                "{figure}<span class=\"file-name\">Filename: {file_name}</span>\n",
+                "{figure}<span class=\"file-name\">{} {file_name}</span>\n",
+                self.file_name_label
             ),
             None => figure,
         }
@@ -245,7 +578,8 @@ impl Listing {
                     .map(|caption| format!(": {}", caption))
                     .unwrap_or_default();
                 let listing_a_tag = format!(
-                    "<a href=\"#listing-{number}\">Listing {number}</a>"
+                    "<a href=\"#listing-{number}\">{} {number}</a>",
+                    self.label
                 );
                 format!(
                     r#"<figcaption>{listing_a_tag}{caption_text}</figcaption>
@@ -270,15 +604,32 @@ impl Listing {
     fn closing_text(&self, trailing: &str) -> String {
         match (&self.number, &self.caption) {
             (Some(number), Some(caption)) => {
-                format!("Listing {number}: {caption}{trailing}")
+                format!("{} {number}: {caption}{trailing}", self.label)
             }
             (None, Some(caption)) => format!("{caption}{trailing}"),
-            (Some(number), None) => format!("Listing {number}{trailing}"),
+            (Some(number), None) => {
+                format!("{} {number}{trailing}", self.label)
+            }
             (None, None) => trailing.into(),
         }
     }
 }
 
+/// The collapsed section showing what changed since listing `from`.
+fn diff_html(from: &str, changes: &str) -> String {
+    let escaped = changes
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<details class="listing-diff">
+<summary>Changes from Listing {from}</summary>
+<pre><code class="language-diff">{escaped}</code></pre>
+</details>
+"#
+    )
+}
+
 /// Note: Although this has the same structure as [`Listing`], it does not have
 /// the same *semantics*. In particular, this has the *source* for the `caption`
 /// while `Listing` has the *rendered* version.
@@ -286,6 +637,8 @@ struct ListingBuilder {
     number: Option<String>,
     caption: Option<String>,
     file_name: Option<String>,
+    path: Option<String>,
+    diff_from: Option<String>,
 }
 
 impl ListingBuilder {
@@ -307,6 +660,8 @@ impl ListingBuilder {
                     number: None,
                     caption: None,
                     file_name: None,
+                    path: None,
+                    diff_from: None,
                 },
                 |builder, (key, maybe_value)| match (key.as_str(), maybe_value)
                 {
@@ -318,9 +673,17 @@ impl ListingBuilder {
                         Ok(builder.with_file_name(value))
                     }
 
+                    ("path", Some(value)) => Ok(builder.with_path(value)),
+
+                    ("diff-from", Some(value)) => {
+                        Ok(builder.with_diff_from(value))
+                    }
+
                     (attr @ "file-name", None)
                     | (attr @ "caption", None)
-                    | (attr @ "number", None) => {
+                    | (attr @ "number", None)
+                    | (attr @ "path", None)
+                    | (attr @ "diff-from", None) => {
                         Err(format!("Missing value for attribute: '{attr}'"))
                     }
 
@@ -346,9 +709,41 @@ impl ListingBuilder {
         self
     }
 
-    fn build(self, mode: Mode) -> Listing {
+    fn with_path(mut self, value: String) -> Self {
+        self.path = Some(value);
+        self
+    }
+
+    fn with_diff_from(mut self, value: String) -> Self {
+        let number = value.strip_prefix("listing-").unwrap_or(&value);
+        self.diff_from = Some(number.to_string());
+        self
+    }
+
+    fn build(
+        mut self,
+        mode: Mode,
+        chapter_dir: Option<&Path>,
+        catalog: Option<&Catalog>,
+    ) -> Result<Listing, String> {
+        if let Some(path) = &self.path {
+            let chapter_dir = chapter_dir.ok_or_else(|| {
+                format!("Cannot resolve listing path '{path}' without a chapter file")
+            })?;
+            let metadata = ListingMetadata::from_path(chapter_dir, path)?;
+            self.number = self.number.or(metadata.number);
+            self.file_name = self.file_name.or(Some(metadata.file_name));
+        }
+
+        // Translations are Markdown just like the English, so they go through
+        // the same rendering.
+        let context = caption_context(self.number.as_deref());
+        let caption = self.caption.map(|caption| {
+            Catalog::translate(catalog, &context, &caption).to_string()
+        });
+
         let caption = match mode {
-            Mode::Default => self.caption.map(|caption_source| {
+            Mode::Default => caption.map(|caption_source| {
                 let events = crate::parser(&caption_source);
                 let mut buf = String::with_capacity(caption_source.len() * 2);
                 html::push_html(&mut buf, events);
@@ -358,16 +753,88 @@ impl ListingBuilder {
                 // is “fine”.
                 buf.replace("<p>", "").replace("</p>", "").replace('\n', "")
             }),
-            Mode::Simple => self.caption,
+            Mode::Simple => caption,
         };
 
-        Listing {
-            number: self.number.map(String::from),
+        Ok(Listing {
+            number: self.number,
             caption,
-            file_name: self.file_name.map(String::from),
+            file_name: self.file_name,
+            diff_from: self.diff_from,
+            label: Catalog::translate(catalog, LISTING_LABEL, "Listing").into(),
+            file_name_label: Catalog::translate(
+                catalog,
+                FILE_NAME_LABEL,
+                "Filename:",
+            )
+            .into(),
+            code: String::new(),
+            hides_lines: false,
+        })
+    }
+}
+
+/// What a listing’s `path` attribute says about it.
+#[derive(Debug, PartialEq)]
+struct ListingMetadata {
+    /// The listing number, if the package directory is named like
+    /// `listing-01-02`.
+    number: Option<String>,
+    /// Where the file is within its listing package, like `src/main.rs`.
+    file_name: String,
+}
+
+impl ListingMetadata {
+    fn from_path(
+        chapter_dir: &Path,
+        path: &str,
+    ) -> Result<ListingMetadata, String> {
+        let full_path = chapter_dir
+            .join(path)
+            .canonicalize()
+            .map_err(|e| format!("Bad listing path '{path}': {e}"))?;
+        if !full_path.is_file() {
+            return Err(format!("Listing path '{path}' is not a file"));
         }
+
+        let package_dir = full_path
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join("Cargo.toml").is_file())
+            .ok_or_else(|| {
+                format!("Listing path '{path}' is not inside a Cargo package")
+            })?;
+
+        let file_name = full_path
+            .strip_prefix(package_dir)
+            .expect("the package directory is an ancestor of the file")
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let number = package_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(number_from_dir_name);
+
+        Ok(ListingMetadata { number, file_name })
     }
 }
 
+/// Turn a directory name like `listing-01-02` into a listing number like
+/// `1-2`. Anything else, like `no-listing-01-hello`, has no number.
+fn number_from_dir_name(name: &str) -> Option<String> {
+    let (chapter, listing) = name.strip_prefix("listing-")?.split_once('-')?;
+    let parse = |part: &str| {
+        if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse::<u32>().ok()
+        } else {
+            None
+        }
+    };
+    Some(format!("{}-{}", parse(chapter)?, parse(listing)?))
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,5 +1,20 @@
 use super::*;
 
+/// Rewrite a chapter on its own, with no listings before it.
+fn rewrite_listing(
+    src: &str,
+    mode: Mode,
+    chapter_dir: Option<&Path>,
+) -> Result<String, String> {
+    super::rewrite_listing(
+        src,
+        mode,
+        chapter_dir,
+        &mut EarlierListings::new(),
+        None,
+    )
+}
+
 /// Note: This inserts an additional backtick around the re-emitted code.
 /// It is not clear *why*, but that seems to be an artifact of the rendering
 /// done by the `pulldown_cmark_to_cmark` crate.
@@ -14,6 +29,7 @@ fn main() {}
 
 </Listing>"#,
         Mode::Default,
+        None,
     );
 
     assert_eq!(
@@ -45,6 +61,7 @@ fn main() {}
 
 Trailing text."#,
         Mode::Simple,
+        None,
     );
 
     assert_eq!(
@@ -63,6 +80,40 @@ Trailing text."#
     );
 }
 
+#[test]
+fn simple_mode_works_in_block_quotes() {
+    let result = rewrite_listing(
+        r#"> ### An Aside
+>
+> <Listing file-name="src/main.rs">
+>
+> ```rust
+> fn main() {}
+> ```
+>
+> </Listing>
+>
+> More of the aside."#,
+        Mode::Simple,
+        None,
+    );
+
+    assert_eq!(
+        &result.unwrap(),
+        r#"> ### An Aside
+>
+> src/main.rs
+>
+> ```rust
+> fn main() {}
+> ```
+>
+>
+>
+> More of the aside."#
+    );
+}
+
 #[test]
 fn listing_with_embedded_angle_brackets() {
     let result = rewrite_listing(
@@ -76,6 +127,7 @@ fn get_a_box_of<T>(t: T) -> Box<T> {
 
 </Listing>"#,
         Mode::Default,
+        None,
     );
 
     assert_eq!(
@@ -110,6 +162,7 @@ fn main() {
 
 Save the file and go back to your terminal window"#,
         Mode::Default,
+        None,
     );
 
     assert!(result.is_ok());
@@ -148,6 +201,7 @@ fn main() {}
 
 This is the closing."#,
         Mode::Default,
+        None,
     );
 
     assert!(result.is_ok());
@@ -179,6 +233,7 @@ fn main() {}
 
 </Listing>"#,
         Mode::Default,
+        None,
     );
 
     assert!(result.is_ok());
@@ -206,6 +261,7 @@ fn main() {}
 
 </Listing>",
         Mode::Default,
+        None,
     );
 
     assert_eq!(
@@ -225,6 +281,7 @@ fn main() {}
 
 </Listing>"#,
         Mode::Default,
+        None,
     );
 
     assert_eq!(
@@ -233,6 +290,50 @@ fn main() {}
     )
 }
 
+#[test]
+fn marked_lines_become_hidden_lines() {
+    let result = rewrite_listing(
+        r#"<Listing number="17-3" file-name="src/main.rs">
+
+```rust
+use std::time::Duration; // hidden
+
+fn main() {
+    #[allow(unused)] // hidden
+    let wait = Duration::from_millis(100);
+}
+```
+
+</Listing>
+
+```text
+Not Rust // hidden
+```"#,
+        Mode::Simple,
+        None,
+    );
+
+    assert_eq!(
+        &result.unwrap(),
+        r#"src/main.rs
+
+```rust
+# use std::time::Duration;
+
+fn main() {
+#     #[allow(unused)]
+    let wait = Duration::from_millis(100);
+}
+```
+
+Listing 17-3
+
+```text
+Not Rust // hidden
+```"#
+    );
+}
+
 #[cfg(test)]
 mod missing_value {
     use super::*;
@@ -248,6 +349,7 @@ fn main() {}
 
 </Listing>"#,
             Mode::Default,
+            None,
         );
 
         assert_eq!(
@@ -267,6 +369,7 @@ fn main() {}
 
 </Listing>"#,
             Mode::Default,
+            None,
         );
 
         assert_eq!(
@@ -286,6 +389,7 @@ fn main() {}
 
 </Listing>"#,
             Mode::Default,
+            None,
         );
 
         assert_eq!(
@@ -294,3 +398,406 @@ fn main() {}
         )
     }
 }
+
+mod from_path {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    /// Lay out a tiny book with one listing package, and produce the directory
+    /// the chapters live in.
+    fn book_fixture(name: &str) -> PathBuf {
+        let root = std::env::temp_dir()
+            .join(format!("mdbook-trpl-listing-{name}-{}", std::process::id()));
+        let package = root.join("listings/ch01-example/listing-01-02");
+        fs::create_dir_all(package.join("src")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(package.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(package.join("src/main.rs"), "fn main() {}\n").unwrap();
+        root.join("src")
+    }
+
+    #[test]
+    fn fills_in_number_and_file_name() {
+        let chapter_dir = book_fixture("fills-in");
+        let result = rewrite_listing(
+            r#"<Listing path="../listings/ch01-example/listing-01-02/src/main.rs" caption="Hello">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Default,
+            Some(&chapter_dir),
+        );
+
+        assert_eq!(
+            &result.unwrap(),
+            r##"<figure class="listing" id="listing-1-2">
+<span class="file-name">Filename: src/main.rs</span>
+
+````rust
+fn main() {}
+````
+
+<figcaption><a href="#listing-1-2">Listing 1-2</a>: Hello</figcaption>
+</figure>"##
+        );
+    }
+
+    #[test]
+    fn works_in_simple_mode() {
+        let chapter_dir = book_fixture("simple");
+        let result = rewrite_listing(
+            r#"<Listing path="../listings/ch01-example/listing-01-02/src/main.rs" caption="Hello">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Simple,
+            Some(&chapter_dir),
+        );
+
+        assert_eq!(
+            &result.unwrap(),
+            r#"src/main.rs
+
+```rust
+fn main() {}
+```
+
+Listing 1-2: Hello"#
+        );
+    }
+
+    #[test]
+    fn explicit_attributes_take_precedence() {
+        let chapter_dir = book_fixture("explicit");
+        let result = rewrite_listing(
+            r#"<Listing number="1-3" file-name="main.rs" path="../listings/ch01-example/listing-01-02/src/main.rs">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Simple,
+            Some(&chapter_dir),
+        );
+
+        assert_eq!(
+            &result.unwrap(),
+            r#"main.rs
+
+```rust
+fn main() {}
+```
+
+Listing 1-3"#
+        );
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let chapter_dir = book_fixture("missing");
+        let result = rewrite_listing(
+            r#"<Listing path="../listings/ch01-example/listing-01-02/src/lib.rs">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Default,
+            Some(&chapter_dir),
+        );
+
+        let error = result.unwrap_err();
+        assert!(
+            error.starts_with("Bad listing path '../listings/ch01-example/listing-01-02/src/lib.rs'"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn numbers_from_dir_names() {
+        assert_eq!(number_from_dir_name("listing-01-02"), Some("1-2".into()));
+        assert_eq!(number_from_dir_name("listing-17-10"), Some("17-10".into()));
+        assert_eq!(number_from_dir_name("listing-07-21-and-22"), None);
+        assert_eq!(number_from_dir_name("no-listing-01-hello"), None);
+        assert_eq!(number_from_dir_name("listing-12-23-reproduced"), None);
+    }
+}
+
+mod diff_from {
+    use super::*;
+
+    const FIRST: &str = r#"<Listing number="17-1" caption="First">
+
+```rust
+# use std::time::Duration;
+fn main() {
+    println!("one");
+}
+```
+
+</Listing>"#;
+
+    #[test]
+    fn shows_the_changed_lines() {
+        let src = format!(
+            r#"{FIRST}
+
+<Listing number="17-2" caption="Second" diff-from="listing-17-1">
+
+```rust
+# use std::time::Duration;
+# use std::thread;
+fn main() {{
+    println!("two");
+}}
+```
+
+</Listing>"#
+        );
+
+        let result = rewrite_listing(&src, Mode::Default, None).unwrap();
+
+        assert!(
+            result.contains(
+                r##"<details class="listing-diff">
+<summary>Changes from Listing 17-1</summary>
+<pre><code class="language-diff">@@ -1,3 +1,3 @@
+ fn main() {
+-    println!("one");
++    println!("two");
+ }</code></pre>
+</details>
+<figcaption><a href="#listing-17-2">Listing 17-2</a>: Second</figcaption>"##
+            ),
+            "unexpected output: {result}"
+        );
+    }
+
+    #[test]
+    fn works_across_chapters() {
+        let mut earlier = EarlierListings::new();
+        super::super::rewrite_listing(
+            FIRST,
+            Mode::Default,
+            None,
+            &mut earlier,
+            None,
+        )
+        .unwrap();
+
+        let result = super::super::rewrite_listing(
+            r#"<Listing number="17-2" diff-from="17-1">
+
+```rust
+fn main() {
+    println!("<two>");
+}
+```
+
+</Listing>"#,
+            Mode::Default,
+            None,
+            &mut earlier,
+            None,
+        )
+        .unwrap();
+
+        assert!(
+            result.contains(r#"+    println!("&lt;two&gt;");"#),
+            "unexpected output: {result}"
+        );
+    }
+
+    #[test]
+    fn unchanged_listing_has_no_diff() {
+        let src = FIRST.replace("17-1", "17-2").replace(
+            "caption=\"First\"",
+            "caption=\"Again\" diff-from=\"listing-17-1\"",
+        );
+        let result =
+            rewrite_listing(&format!("{FIRST}\n\n{src}"), Mode::Default, None)
+                .unwrap();
+        assert!(!result.contains("listing-diff"));
+    }
+
+    #[test]
+    fn later_listing_is_an_error() {
+        let result = rewrite_listing(
+            r#"<Listing number="17-2" diff-from="listing-17-3">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Default,
+            None,
+        );
+        assert_eq!(
+            result,
+            Err(
+                "Listing 17-3 from `diff-from` must come before the listing \
+                 which uses it"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn simple_mode_ignores_it() {
+        let result = rewrite_listing(
+            r#"<Listing number="17-2" diff-from="listing-17-1">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+            Mode::Simple,
+            None,
+        );
+        assert_eq!(
+            result.unwrap(),
+            "\n```rust\nfn main() {}\n```\n\nListing 17-2"
+        );
+    }
+}
+
+mod translated {
+    use mdbook::book::{Chapter, SectionNumber};
+
+    use super::*;
+
+    const SRC: &str = r#"<Listing number="9-3" file-name="src/main.rs" caption="Opening a file">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#;
+
+    fn catalog() -> Catalog {
+        Catalog::parse(
+            r#"msgctxt "trpl-listing:label"
+msgid "Listing"
+msgstr "Codeblock"
+
+msgctxt "trpl-listing:file-name"
+msgid "Filename:"
+msgstr "Dateiname:"
+
+msgctxt "trpl-listing:9-3:caption"
+msgid "Opening a file"
+msgstr "Eine Datei mit `File::open` öffnen"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn captions_and_labels_come_from_the_catalog() {
+        let catalog = catalog();
+        let result = super::super::rewrite_listing(
+            SRC,
+            Mode::Default,
+            None,
+            &mut EarlierListings::new(),
+            Some(&catalog),
+        )
+        .unwrap();
+
+        assert!(result.contains(
+            r#"<span class="file-name">Dateiname: src/main.rs</span>"#
+        ));
+        assert!(result.contains(
+            r##"<figcaption><a href="#listing-9-3">Codeblock 9-3</a>: Eine Datei mit <code>File::open</code> öffnen</figcaption>"##
+        ));
+    }
+
+    #[test]
+    fn simple_mode_keeps_the_translated_markdown() {
+        let catalog = catalog();
+        let result = super::super::rewrite_listing(
+            SRC,
+            Mode::Simple,
+            None,
+            &mut EarlierListings::new(),
+            Some(&catalog),
+        )
+        .unwrap();
+
+        assert!(result
+            .ends_with("Codeblock 9-3: Eine Datei mit `File::open` öffnen"));
+    }
+
+    #[test]
+    fn untranslated_captions_stay_in_english() {
+        let catalog = catalog();
+        let result = super::super::rewrite_listing(
+            &SRC.replace("9-3", "9-4"),
+            Mode::Simple,
+            None,
+            &mut EarlierListings::new(),
+            Some(&catalog),
+        )
+        .unwrap();
+
+        assert!(result.ends_with("Codeblock 9-4: Opening a file"));
+    }
+
+    #[test]
+    fn template_has_stable_contexts_and_skips_code() {
+        let mut chapter = Chapter::new(
+            "Recoverable Errors",
+            format!(
+                r#"Some text.
+
+{SRC}
+
+<Listing id="by-id" caption="Numbered for you">
+
+</Listing>
+
+````markdown
+<Listing number="1-1" caption="Only an example">
+````
+"#
+            ),
+            "ch09-02.md",
+            vec![],
+        );
+        chapter.number = Some(SectionNumber(vec![9, 2]));
+        let mut book = Book::new();
+        book.push_item(chapter);
+
+        let pot = TrplListing
+            .xgettext(&book, Path::new("/nowhere"), Path::new("src"))
+            .unwrap();
+
+        assert!(pot.contains(
+            r#"#: src/ch09-02.md:3
+msgctxt "trpl-listing:9-3:caption"
+msgid "Opening a file"
+"#
+        ));
+        assert!(pot.contains(
+            r#"#: src/ch09-02.md:11
+msgctxt "trpl-listing:9-4:caption"
+msgid "Numbered for you"
+"#
+        ));
+        assert!(
+            pot.contains("msgctxt \"trpl-listing:label\"\nmsgid \"Listing\"\n")
+        );
+        assert!(!pot.contains("Only an example"));
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use mdbook::{
     book::Book,
     errors::Result,
@@ -10,6 +11,11 @@ use pulldown_cmark::{
 };
 use pulldown_cmark_to_cmark::cmark;
 
+use crate::{
+    config::{self, Mode},
+    i18n::{self, Catalog, Message},
+};
+
 /// A simple preprocessor for semantic notes in _The Rust Programming Language_.
 ///
 /// Takes in Markdown like this:
@@ -27,28 +33,142 @@ use pulldown_cmark_to_cmark::cmark;
 ///
 /// </section>
 /// ```
+///
+/// Besides plain notes, it understands a few other kinds, each marked by how
+/// its first paragraph starts, and each getting an extra class so that it can
+/// be styled differently:
+///
+/// | Starts with       | Kind of note          | Class            |
+/// | ----------------- | --------------------- | ---------------- |
+/// | `Note: `          | A plain note          | `note`           |
+/// | `Warning: `       | Something to avoid    | `note warning`   |
+/// | `Tip: `           | A helpful suggestion  | `note tip`       |
+/// | `Edition note: `  | Differences between   | `note edition`   |
+/// |                   | Rust editions         |                  |
+/// | `Ferris: `        | An aside from Ferris  | `note ferris`    |
+///
+/// With `output-mode = "simple"` in the `trpl-note` configuration, as for the
+/// print version, notes stay blockquotes, and the label at the start of every
+/// kind other than a plain note is made bold instead, like
+/// `> **Warning:** This is a warning.`, so that the kinds stand apart there,
+/// too.
+///
+/// When the book is built in another language, the labels can be translated
+/// in its gettext catalog, under the `trpl-note:label` context, so that notes
+/// starting with the translated label are recognized too. The
+/// `mdbook-trpl-note xgettext` command prints the template for those messages.
+/// The catalog is `po/<language>.po` for the book’s `language`, as with
+/// `mdbook-i18n-helpers`.
 pub struct TrplNote;
 
+impl TrplNote {
+    /// A gettext template with the labels for each kind of note.
+    pub fn xgettext(&self) -> String {
+        let messages: Vec<Message> = Kind::ALL
+            .into_iter()
+            .map(|kind| Message::new(LABEL_CONTEXT, kind.label()))
+            .collect();
+        i18n::pot(&messages)
+    }
+}
+
+/// The gettext context for the labels which start notes.
+const LABEL_CONTEXT: &str = "trpl-note:label";
+
 impl Preprocessor for TrplNote {
     fn name(&self) -> &str {
         "simple-note-preprocessor"
     }
 
-    fn run(&self, _ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        // The configuration lives under the name the book uses for it, and
+        // having none at all just means the default behavior.
+        let mode = match Mode::from_context(ctx, "trpl-note") {
+            Ok(mode) => mode,
+            Err(config::Error::NoConfig(_)) => Mode::Default,
+            Err(error) => return Err(error.into()),
+        };
+        let catalog = Catalog::from_context(ctx).map_err(|e| anyhow!(e))?;
+
         book.for_each_mut(|item| {
             if let BookItem::Chapter(ref mut chapter) = item {
-                chapter.content = rewrite(&chapter.content);
+                chapter.content =
+                    rewrite(&chapter.content, mode, catalog.as_ref());
             }
         });
         Ok(book)
     }
 
     fn supports_renderer(&self, renderer: &str) -> bool {
-        renderer == "html" || renderer == "markdown" || renderer == "test"
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
+    }
+}
+
+/// The kinds of notes, by the label which starts them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Note,
+    Warning,
+    Tip,
+    Edition,
+    Ferris,
+}
+
+impl Kind {
+    const ALL: [Kind; 5] = [
+        Kind::Note,
+        Kind::Warning,
+        Kind::Tip,
+        Kind::Edition,
+        Kind::Ferris,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Note => "Note:",
+            Kind::Warning => "Warning:",
+            Kind::Tip => "Tip:",
+            Kind::Edition => "Edition note:",
+            Kind::Ferris => "Ferris:",
+        }
+    }
+
+    fn class(self) -> &'static str {
+        match self {
+            Kind::Note => "note",
+            Kind::Warning => "note warning",
+            Kind::Tip => "note tip",
+            Kind::Edition => "note edition",
+            Kind::Ferris => "note ferris",
+        }
+    }
+
+    /// The kind of note which `text` starts, if any, along with the length of
+    /// the label it starts with: either the label’s translation in `catalog`,
+    /// or the English label.
+    fn of(text: &str, catalog: Option<&Catalog>) -> Option<(Kind, usize)> {
+        Kind::ALL.into_iter().find_map(|kind| {
+            let translated =
+                Catalog::translate(catalog, LABEL_CONTEXT, kind.label());
+            [translated, kind.label()].into_iter().find_map(|label| {
+                text.strip_prefix(label)
+                    .is_some_and(|rest| rest.starts_with(' '))
+                    .then_some((kind, label.len()))
+            })
+        })
+    }
+
+    fn opening_html(self) -> String {
+        format!(r#"<section class="{}" aria-role="note">"#, self.class())
     }
 }
 
-pub fn rewrite(text: &str) -> String {
+/// Rewrite the notes in `text`, also recognizing the labels translated in
+/// `catalog`, if the book is being built in another language.
+pub fn rewrite(text: &str, mode: Mode, catalog: Option<&Catalog>) -> String {
     let parser = crate::parser(text);
 
     let mut events = Vec::new();
@@ -61,36 +181,60 @@ pub fn rewrite(text: &str) -> String {
             }
 
             (StartingBlockquote(blockquote_events), Text(content)) => {
-                if content.starts_with("Note: ") {
-                    // This needs the "extra" `SoftBreak`s so that when the final rendering pass
-                    // happens, it does not end up treating the internal content as inline *or*
-                    // treating the HTML tags as inline tags:
-                    //
-                    // - Content inside HTML blocks is only rendered as Markdown when it is
-                    //   separated from the block HTML elements: otherwise it gets treated as inline
-                    //   HTML and *not* rendered.
-                    // - Along the same lines, an HTML tag that happens to be directly adjacent to
-                    //   the end of a previous Markdown block will end up being rendered as part of
-                    //   that block.
-                    events.extend([
-                        SoftBreak,
-                        SoftBreak,
-                        Html(
-                            r#"<section class="note" aria-role="note">"#.into(),
-                        ),
-                        SoftBreak,
-                        SoftBreak,
-                        Start(Tag::Paragraph),
-                        Text(content),
-                    ]);
-                    state = InNote;
-                } else {
-                    events.append(blockquote_events);
-                    events.push(Text(content));
-                    state = Default;
+                match (Kind::of(&content, catalog), mode) {
+                    (Some((kind, _)), Mode::Default) => {
+                        // This needs the "extra" `SoftBreak`s so that when the final rendering pass
+                        // happens, it does not end up treating the internal content as inline *or*
+                        // treating the HTML tags as inline tags:
+                        //
+                        // - Content inside HTML blocks is only rendered as Markdown when it is
+                        //   separated from the block HTML elements: otherwise it gets treated as inline
+                        //   HTML and *not* rendered.
+                        // - Along the same lines, an HTML tag that happens to be directly adjacent to
+                        //   the end of a previous Markdown block will end up being rendered as part of
+                        //   that block.
+                        events.extend([
+                            SoftBreak,
+                            SoftBreak,
+                            Html(kind.opening_html().into()),
+                            SoftBreak,
+                            SoftBreak,
+                            Start(Tag::Paragraph),
+                            Text(content),
+                        ]);
+                        state = InNote;
+                    }
+                    (Some((kind, label_len)), Mode::Simple)
+                        if kind != Kind::Note =>
+                    {
+                        let label = content[..label_len].to_string();
+                        let rest = content[label_len..].to_string();
+                        events.append(blockquote_events);
+                        events.extend([
+                            Start(Tag::Strong),
+                            Text(label.into()),
+                            End(TagEnd::Strong),
+                            Text(rest.into()),
+                        ]);
+                        state = Default;
+                    }
+                    _ => {
+                        events.append(blockquote_events);
+                        events.push(Text(content));
+                        state = Default;
+                    }
                 }
             }
 
+            (
+                StartingBlockquote(blockquote_events),
+                heading @ Start(Tag::Heading { .. }),
+            ) if mode == Mode::Simple => {
+                events.append(blockquote_events);
+                events.push(heading);
+                state = Default;
+            }
+
             (
                 StartingBlockquote(_blockquote_events),
                 heading @ Start(Tag::Heading { .. }),
@@ -98,7 +242,7 @@ pub fn rewrite(text: &str) -> String {
                 events.extend([
                     SoftBreak,
                     SoftBreak,
-                    Html(r#"<section class="note" aria-role="note">"#.into()),
+                    Html(Kind::Note.opening_html().into()),
                     SoftBreak,
                     SoftBreak,
                     heading,
@@ -1,9 +1,14 @@
 use super::*;
 
+/// Rewrite notes with only the English labels.
+fn rewrite(text: &str, mode: Mode) -> String {
+    super::rewrite(text, mode, None)
+}
+
 #[test]
 fn no_note() {
     let text = "Hello, world.\n\nThis is some text.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<p>Hello, world.</p>\n<p>This is some text.</p>\n"
@@ -13,7 +18,7 @@ fn no_note() {
 #[test]
 fn with_note() {
     let text = "> Note: This is some text.\n> It keeps going.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<p>Note: This is some text.\nIt keeps going.</p>\n</section>"
@@ -23,7 +28,7 @@ fn with_note() {
 #[test]
 fn regular_blockquote() {
     let text = "> This is some text.\n> It keeps going.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<blockquote>\n<p>This is some text.\nIt keeps going.</p>\n</blockquote>\n"
@@ -33,7 +38,7 @@ fn regular_blockquote() {
 #[test]
 fn combined() {
     let text = "> Note: This is some text.\n> It keeps going.\n\nThis is regular text.\n\n> This is a blockquote.\n";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<p>Note: This is some text.\nIt keeps going.</p>\n</section>\n<p>This is regular text.</p>\n<blockquote>\n<p>This is a blockquote.</p>\n</blockquote>\n"
@@ -43,7 +48,7 @@ fn combined() {
 #[test]
 fn blockquote_then_note() {
     let text = "> This is quoted.\n\n> Note: This is noted.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<blockquote>\n<p>This is quoted.</p>\n</blockquote>\n<section class=\"note\" aria-role=\"note\">\n<p>Note: This is noted.</p>\n</section>"
@@ -53,7 +58,7 @@ fn blockquote_then_note() {
 #[test]
 fn note_then_blockquote() {
     let text = "> Note: This is noted.\n\n> This is quoted.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<p>Note: This is noted.</p>\n</section>\n<blockquote>\n<p>This is quoted.</p>\n</blockquote>\n"
@@ -63,7 +68,7 @@ fn note_then_blockquote() {
 #[test]
 fn with_h1_note() {
     let text = "> # Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h1>Header</h1>\n<p>And then some note content.</p>\n</section>"
@@ -73,7 +78,7 @@ fn with_h1_note() {
 #[test]
 fn with_h2_note() {
     let text = "> ## Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h2>Header</h2>\n<p>And then some note content.</p>\n</section>"
@@ -83,7 +88,7 @@ fn with_h2_note() {
 #[test]
 fn with_h3_note() {
     let text = "> ### Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h3>Header</h3>\n<p>And then some note content.</p>\n</section>"
@@ -93,7 +98,7 @@ fn with_h3_note() {
 #[test]
 fn with_h4_note() {
     let text = "> #### Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h4>Header</h4>\n<p>And then some note content.</p>\n</section>"
@@ -103,7 +108,7 @@ fn with_h4_note() {
 #[test]
 fn with_h5_note() {
     let text = "> ##### Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h5>Header</h5>\n<p>And then some note content.</p>\n</section>"
@@ -113,7 +118,7 @@ fn with_h5_note() {
 #[test]
 fn with_h6_note() {
     let text = "> ###### Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h6>Header</h6>\n<p>And then some note content.</p>\n</section>"
@@ -124,7 +129,7 @@ fn with_h6_note() {
 fn h1_then_blockquote() {
     let text =
         "> # Header\n > And then some note content.\n\n> This is quoted.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<section class=\"note\" aria-role=\"note\">\n<h1>Header</h1>\n<p>And then some note content.</p>\n</section>\n<blockquote>\n<p>This is quoted.</p>\n</blockquote>\n"
@@ -135,7 +140,7 @@ fn h1_then_blockquote() {
 fn blockquote_then_h1_note() {
     let text =
         "> This is quoted.\n\n> # Header\n > And then some note content.";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<blockquote>\n<p>This is quoted.</p>\n</blockquote>\n<section class=\"note\" aria-role=\"note\">\n<h1>Header</h1>\n<p>And then some note content.</p>\n</section>"
@@ -145,7 +150,7 @@ fn blockquote_then_h1_note() {
 #[test]
 fn blockquote_with_strong() {
     let text = "> **Bold text in a paragraph.**";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<blockquote>\n<p><strong>Bold text in a paragraph.</strong></p>\n</blockquote>\n"
@@ -155,7 +160,7 @@ fn blockquote_with_strong() {
 #[test]
 fn normal_table() {
     let text = "| Header 1 | Header 2 |\n| -------- | -------- |\n| Text 123 | More 456 |";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
 
     assert_eq!(
         processed,
@@ -167,7 +172,7 @@ fn normal_table() {
 #[test]
 fn table_in_note() {
     let text = "> Note: table stuff.\n\n| Header 1 | Header 2 |\n| -------- | -------- |\n| Text 123 | More 456 |";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
 
     assert_eq!(
         processed,
@@ -179,7 +184,7 @@ fn table_in_note() {
 #[test]
 fn table_in_quote() {
     let text = "> A table.\n\n| Header 1 | Header 2 |\n| -------- | -------- |\n| Text 123 | More 456 |";
-    let processed = rewrite(text);
+    let processed = rewrite(text, Mode::Default);
     assert_eq!(
         render_markdown(&processed),
         "<blockquote>\n<p>A table.</p>\n</blockquote>\n<table><thead><tr><th>Header 1</th><th>Header 2</th></tr></thead><tbody>\n<tr><td>Text 123</td><td>More 456</td></tr>\n</tbody></table>\n",
@@ -187,6 +192,141 @@ fn table_in_quote() {
     );
 }
 
+mod kinds {
+    use super::*;
+
+    #[test]
+    fn each_kind_gets_its_class() {
+        for (label, class) in [
+            ("Warning:", "note warning"),
+            ("Tip:", "note tip"),
+            ("Edition note:", "note edition"),
+            ("Ferris:", "note ferris"),
+        ] {
+            let text = format!("> {label} This is some text.");
+            let processed = rewrite(&text, Mode::Default);
+            assert_eq!(
+                render_markdown(&processed),
+                format!("<section class=\"{class}\" aria-role=\"note\">\n<p>{label} This is some text.</p>\n</section>")
+            );
+        }
+    }
+
+    #[test]
+    fn label_must_be_followed_by_a_space() {
+        let text = "> Tip:no space here.";
+        let processed = rewrite(text, Mode::Default);
+        assert_eq!(
+            render_markdown(&processed),
+            "<blockquote>\n<p>Tip:no space here.</p>\n</blockquote>\n"
+        );
+    }
+}
+
+mod simple_mode {
+    use super::*;
+
+    #[test]
+    fn plain_note_is_unchanged() {
+        let text = "> Note: This is some text.\n> It keeps going.";
+        let processed = rewrite(text, Mode::Simple);
+        assert_eq!(
+            render_markdown(&processed),
+            "<blockquote>\n<p>Note: This is some text.\nIt keeps going.</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn other_kinds_get_a_bold_label() {
+        let text = "> Warning: This is some text.\n> It keeps going.";
+        let processed = rewrite(text, Mode::Simple);
+        assert_eq!(
+            render_markdown(&processed),
+            "<blockquote>\n<p><strong>Warning:</strong> This is some text.\nIt keeps going.</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn heading_note_stays_a_blockquote() {
+        let text = "> ## Header\n > And then some note content.";
+        let processed = rewrite(text, Mode::Simple);
+        assert_eq!(
+            render_markdown(&processed),
+            "<blockquote>\n<h2>Header</h2>\n<p>And then some note content.</p>\n</blockquote>\n"
+        );
+    }
+}
+
+mod translated {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        Catalog::parse(
+            r#"msgctxt "trpl-note:label"
+msgid "Note:"
+msgstr "Remarque :"
+
+msgctxt "trpl-note:label"
+msgid "Warning:"
+msgstr "Attention :"
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn translated_labels_start_notes() {
+        let catalog = catalog();
+        let processed = super::super::rewrite(
+            "> Attention : Ceci est un avertissement.",
+            Mode::Default,
+            Some(&catalog),
+        );
+        assert_eq!(
+            render_markdown(&processed),
+            "<section class=\"note warning\" aria-role=\"note\">\n<p>Attention : Ceci est un avertissement.</p>\n</section>"
+        );
+    }
+
+    #[test]
+    fn english_labels_still_work() {
+        let catalog = catalog();
+        let processed = super::super::rewrite(
+            "> Note: Not translated yet.",
+            Mode::Default,
+            Some(&catalog),
+        );
+        assert!(
+            processed.contains(r#"<section class="note" aria-role="note">"#)
+        );
+    }
+
+    #[test]
+    fn simple_mode_bolds_the_translated_label() {
+        let catalog = catalog();
+        let processed = super::super::rewrite(
+            "> Attention : Ceci est un avertissement.",
+            Mode::Simple,
+            Some(&catalog),
+        );
+        assert_eq!(
+            render_markdown(&processed),
+            "<blockquote>\n<p><strong>Attention :</strong> Ceci est un avertissement.</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn template_has_every_label() {
+        let pot = TrplNote.xgettext();
+        for kind in Kind::ALL {
+            assert!(pot.contains(&format!(
+                "msgctxt \"trpl-note:label\"\nmsgid \"{}\"\n",
+                kind.label()
+            )));
+        }
+    }
+}
+
 fn render_markdown(text: &str) -> String {
     let parser = crate::parser(text);
     let mut buf = String::new();
@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::anyhow;
+use mdbook::{
+    book::Book,
+    errors::Result,
+    preprocess::{Preprocessor, PreprocessorContext},
+    BookItem,
+};
+use regex::Regex;
+
+use crate::{config::Mode, CompositeError};
+
+/// A preprocessor which numbers listings for you, and lets the text refer to
+/// them by name instead of by number.
+///
+/// Give a listing an `id` instead of a `number`:
+///
+/// ```markdown
+/// <Listing id="question-mark-in-main" file-name="src/main.rs" caption="…">
+/// ```
+///
+/// Listings are numbered in the order they appear, starting over with each
+/// chapter, and counting across all of its sections: the first listing with
+/// an `id` in `ch09-02-recoverable-errors-with-result.md` comes right after the
+/// last one in `ch09-01-unrecoverable-errors-with-panic.md`. A listing with an
+/// explicit `number` sets where the count picks up from, so listings with and
+/// without numbers can be mixed while moving a chapter over.
+///
+/// Anywhere in the book’s text, `{{listing-ref question-mark-in-main}}` then
+/// becomes a link to that listing, with the text “Listing 9-10”. With
+/// `output-mode = "simple"`, it becomes just the text. Referring to an `id`
+/// that no listing has, or using the same `id` twice, fails the build.
+///
+/// This hands each listing over with a `number` in place of its `id`, so it
+/// must run before the `trpl-listing` preprocessor:
+///
+/// ```toml
+/// [preprocessor.trpl-numbering]
+/// before = ["trpl-listing"]
+/// ```
+pub struct TrplNumbering;
+
+impl Preprocessor for TrplNumbering {
+    fn name(&self) -> &str {
+        "trpl-numbering"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let mode = Mode::from_context(ctx, self.name())?;
+        number_listings(&mut book, mode)?;
+        Ok(book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
+    }
+}
+
+/// Number every listing with an `id` in `book`, and resolve the references
+/// to them.
+pub(crate) fn number_listings(book: &mut Book, mode: Mode) -> Result<()> {
+    let numbering = number_book(book, Kind::Listing)?;
+    let mut errors = vec![];
+    book.for_each_mut(|item| {
+        if let BookItem::Chapter(ref mut chapter) = item {
+            match rewrite_listings(
+                &numbering,
+                &chapter.content,
+                chapter.source_path.as_deref(),
+                mode,
+            ) {
+                Ok(rewritten) => chapter.content = rewritten,
+                Err(reason) => errors.push(anyhow!(reason)),
+            }
+        }
+    });
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CompositeError(errors).into())
+    }
+}
+
+/// Replace each listing’s `id` with its `number`, and each `{{listing-ref}}`
+/// with a reference to the listing.
+fn rewrite_listings(
+    numbering: &Numbering,
+    src: &str,
+    source_path: Option<&Path>,
+    mode: Mode,
+) -> Result<String, String> {
+    static ID: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"\s+id="([^"]*)""#).unwrap());
+
+    numbering.rewrite(src, source_path, mode, |line| {
+        let attributes = attributes(line);
+        Ok(match attributes.get("id") {
+            Some(id) if !attributes.contains_key("number") => {
+                let number = &numbering.targets[id].number;
+                ID.replace(line, "").replacen(
+                    "<Listing",
+                    &format!("<Listing number=\"{number}\""),
+                    1,
+                )
+            }
+            Some(_) => ID.replace(line, "").into_owned(),
+            None => line.to_string(),
+        })
+    })
+}
+
+/// Something the book numbers chapter by chapter, and which the text can refer
+/// to by `id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// `<Listing>`s, referred to with `{{listing-ref}}`. Only listings with an
+    /// `id` or a `number` get one; the others stay unnumbered.
+    Listing,
+    /// `<Figure>`s, referred to with `{{figure-ref}}`. Every figure gets a
+    /// number, whether or not it has an `id`.
+    Figure,
+}
+
+impl Kind {
+    /// What the text calls one, as in “Listing 9-1”.
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Listing => "Listing",
+            Kind::Figure => "Figure",
+        }
+    }
+
+    /// The name in `{{listing-ref}}`s, in `#listing-9-1` anchors, and in
+    /// errors.
+    fn slug(self) -> &'static str {
+        match self {
+            Kind::Listing => "listing",
+            Kind::Figure => "figure",
+        }
+    }
+
+    /// Whether `line` is an opening tag for one, like `<Listing …>`.
+    pub(crate) fn is_tag(self, line: &str) -> bool {
+        line.trim_start()
+            .strip_prefix('<')
+            .is_some_and(|tag| tag.starts_with(self.name()))
+    }
+}
+
+/// Number everything of one `kind` in `book`, in the order it appears.
+pub(crate) fn number_book(book: &Book, kind: Kind) -> Result<Numbering> {
+    let mut numbering = Numbering::new(kind);
+    let mut errors = vec![];
+    for item in book.iter() {
+        if let BookItem::Chapter(chapter) = item {
+            let chapter_number =
+                chapter.number.as_ref().and_then(|n| n.first().copied());
+            if let Err(reason) = numbering.add_chapter(
+                chapter_number,
+                chapter.source_path.as_deref(),
+                &chapter.content,
+            ) {
+                errors.push(anyhow!(reason));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(numbering)
+    } else {
+        Err(CompositeError(errors).into())
+    }
+}
+
+/// The numbers given out so far to one kind of thing, and where each one with
+/// an `id` lives.
+#[derive(Debug)]
+pub(crate) struct Numbering {
+    kind: Kind,
+    /// The number of each numbered tag, in order, by the chapter file it is
+    /// in.
+    numbers: HashMap<PathBuf, Vec<String>>,
+    targets: HashMap<String, Target>,
+    /// The last number used in each chapter.
+    counts: HashMap<u32, u32>,
+}
+
+#[derive(Debug)]
+struct Target {
+    number: String,
+    /// The Markdown file the target is in, relative to the book’s source
+    /// directory, if any.
+    source_path: Option<PathBuf>,
+}
+
+impl Numbering {
+    pub(crate) fn new(kind: Kind) -> Numbering {
+        Numbering {
+            kind,
+            numbers: HashMap::new(),
+            targets: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Number the tags in one chapter (or section) of the book. Chapters must
+    /// be added in the order they appear in the book.
+    pub(crate) fn add_chapter(
+        &mut self,
+        chapter_number: Option<u32>,
+        source_path: Option<&Path>,
+        src: &str,
+    ) -> Result<(), String> {
+        let slug = self.kind.slug();
+        let mut numbers = vec![];
+        for tag in lines_with_code_state(src).filter_map(|(line, in_code)| {
+            (!in_code && self.kind.is_tag(line)).then_some(line)
+        }) {
+            let attributes = attributes(tag);
+            let id = attributes.get("id");
+            let number = match attributes.get("number") {
+                Some(number) => {
+                    // Keep counting from an explicit number, as long as it is
+                    // in this chapter.
+                    if let (Some(chapter), Some((prefix, count))) =
+                        (chapter_number, number.split_once('-'))
+                    {
+                        if prefix.parse() == Ok(chapter) {
+                            if let Ok(count) = count.parse() {
+                                self.counts.insert(chapter, count);
+                            }
+                        }
+                    }
+                    number.clone()
+                }
+                None if id.is_some() || self.kind == Kind::Figure => {
+                    let chapter = chapter_number.ok_or_else(|| match id {
+                        Some(id) => format!(
+                            "Cannot number {slug} '{id}' outside a numbered \
+                             chapter"
+                        ),
+                        None => format!(
+                            "Cannot number a {slug} outside a numbered chapter"
+                        ),
+                    })?;
+                    let count = self.counts.entry(chapter).or_default();
+                    *count += 1;
+                    format!("{chapter}-{count}")
+                }
+                None => continue,
+            };
+
+            if let Some(id) = id {
+                if self.targets.contains_key(id) {
+                    return Err(format!("Duplicate {slug} id '{id}'"));
+                }
+                self.targets.insert(
+                    id.clone(),
+                    Target {
+                        number: number.clone(),
+                        source_path: source_path.map(PathBuf::from),
+                    },
+                );
+            }
+            numbers.push(number);
+        }
+
+        if let Some(source_path) = source_path {
+            self.numbers.insert(source_path.to_path_buf(), numbers);
+        }
+        Ok(())
+    }
+
+    /// The number of the target with this `id`, if there is one.
+    #[cfg(test)]
+    pub(crate) fn number(&self, id: &str) -> Option<&str> {
+        self.targets.get(id).map(|target| target.number.as_str())
+    }
+
+    /// The numbers of the tags in the chapter at `source_path`, in order.
+    pub(crate) fn numbers_in(
+        &self,
+        source_path: Option<&Path>,
+    ) -> impl Iterator<Item = &str> {
+        source_path
+            .and_then(|path| self.numbers.get(path))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Rewrite the chapter at `source_path`: each tag with `rewrite_tag`, and
+    /// each reference, like `{{listing-ref some-id}}`, into a reference to
+    /// what it names. Code blocks are left alone.
+    pub(crate) fn rewrite(
+        &self,
+        src: &str,
+        source_path: Option<&Path>,
+        mode: Mode,
+        mut rewrite_tag: impl FnMut(&str) -> Result<String, String>,
+    ) -> Result<String, String> {
+        static REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"\{\{(\w+)-ref\s+([^}\s]+)\s*\}\}").unwrap()
+        });
+
+        let mut errors = vec![];
+        let mut rewritten = String::with_capacity(src.len());
+        for (line, in_code) in lines_with_code_state(src) {
+            if in_code {
+                rewritten.push_str(line);
+            } else if self.kind.is_tag(line) {
+                rewritten.push_str(&rewrite_tag(line)?);
+            } else {
+                let line = REFERENCE.replace_all(
+                    line,
+                    |captures: &regex::Captures| {
+                        // References to the other kind are for its own pass.
+                        if &captures[1] != self.kind.slug() {
+                            return captures[0].to_string();
+                        }
+                        let id = &captures[2];
+                        match self.targets.get(id) {
+                            Some(target) => {
+                                self.reference(target, source_path, mode)
+                            }
+                            None => {
+                                errors.push(format!(
+                                    "No {} with id '{id}' for `{}`",
+                                    self.kind.slug(),
+                                    &captures[0]
+                                ));
+                                captures[0].to_string()
+                            }
+                        }
+                    },
+                );
+                rewritten.push_str(&line);
+            }
+            rewritten.push('\n');
+        }
+
+        // As with the listing preprocessor, match the input’s trailing
+        // newline, or lack thereof.
+        if !src.ends_with('\n') {
+            rewritten.pop();
+        }
+
+        if errors.is_empty() {
+            Ok(rewritten)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// How to refer to `target` from the chapter at `from`.
+    fn reference(
+        &self,
+        target: &Target,
+        from: Option<&Path>,
+        mode: Mode,
+    ) -> String {
+        let text = format!("{} {}", self.kind.name(), target.number);
+        match mode {
+            Mode::Simple => text,
+            Mode::Default => {
+                let anchor = format!("#{}-{}", self.kind.slug(), target.number);
+                let link = match (&target.source_path, from) {
+                    (Some(to), Some(from)) if to != from => {
+                        format!("{}{anchor}", relative_path(from, to))
+                    }
+                    _ => anchor,
+                };
+                format!("[{text}]({link})")
+            }
+        }
+    }
+}
+
+/// A path from the Markdown file `from` to the one at `to`, where both are
+/// relative to the book’s source directory.
+pub(crate) fn relative_path(from: &Path, to: &Path) -> String {
+    let depth = from
+        .parent()
+        .map(|dir| {
+            dir.components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .count()
+        })
+        .unwrap_or(0);
+    let to = to
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{}{to}", "../".repeat(depth))
+}
+
+/// Each line of `src`, and whether it is inside a fenced code block (fences
+/// included), so that examples of the syntax are left alone.
+pub(crate) fn lines_with_code_state(
+    src: &str,
+) -> impl Iterator<Item = (&str, bool)> {
+    let mut fence: Option<&str> = None;
+    src.lines().map(move |line| {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(open) => {
+                if trimmed.starts_with(open) {
+                    fence = None;
+                }
+                (line, true)
+            }
+            None if trimmed.starts_with("```") => {
+                fence = Some("```");
+                (line, true)
+            }
+            None if trimmed.starts_with("~~~") => {
+                fence = Some("~~~");
+                (line, true)
+            }
+            None => (line, false),
+        }
+    })
+}
+
+/// The `key="value"` attributes on a tag like `<Listing>`.
+pub(crate) fn attributes(tag: &str) -> HashMap<String, String> {
+    static ATTRIBUTE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap());
+
+    ATTRIBUTE
+        .captures_iter(tag)
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
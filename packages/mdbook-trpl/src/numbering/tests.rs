@@ -0,0 +1,232 @@
+use super::*;
+
+fn numbered(chapters: &[(Option<u32>, &str, &str)]) -> Numbering {
+    let mut numbering = Numbering::new(Kind::Listing);
+    for (number, path, src) in chapters {
+        numbering
+            .add_chapter(*number, Some(Path::new(path)), src)
+            .unwrap();
+    }
+    numbering
+}
+
+#[test]
+fn numbers_listings_in_order_across_sections() {
+    let numbering = numbered(&[
+        (
+            Some(9),
+            "ch09-01.md",
+            r#"<Listing id="first" caption="One">
+"#,
+        ),
+        (
+            Some(9),
+            "ch09-02.md",
+            r#"<Listing id="second" caption="Two">
+
+<Listing id="third" file-name="src/main.rs">
+"#,
+        ),
+        (
+            Some(10),
+            "ch10-00.md",
+            r#"<Listing id="fourth">
+"#,
+        ),
+    ]);
+
+    let numbers: Vec<_> = ["first", "second", "third", "fourth"]
+        .into_iter()
+        .map(|id| numbering.targets[id].number.as_str())
+        .collect();
+    assert_eq!(numbers, ["9-1", "9-2", "9-3", "10-1"]);
+}
+
+#[test]
+fn explicit_numbers_set_where_counting_continues() {
+    let numbering = numbered(&[(
+        Some(4),
+        "ch04-01.md",
+        r#"<Listing number="4-5" caption="Old style">
+
+<Listing id="next" caption="New style">
+"#,
+    )]);
+
+    assert_eq!(numbering.targets["next"].number, "4-6");
+}
+
+#[test]
+fn listings_in_code_blocks_are_ignored() {
+    let numbering = numbered(&[(
+        Some(1),
+        "ch01-01.md",
+        r#"```markdown
+<Listing id="example">
+```
+
+<Listing id="real">
+"#,
+    )]);
+
+    assert!(!numbering.targets.contains_key("example"));
+    assert_eq!(numbering.targets["real"].number, "1-1");
+}
+
+#[test]
+fn duplicate_ids_are_an_error() {
+    let mut numbering = Numbering::new(Kind::Listing);
+    let result = numbering.add_chapter(
+        Some(2),
+        None,
+        r#"<Listing id="same">
+
+<Listing id="same">
+"#,
+    );
+    assert_eq!(result, Err("Duplicate listing id 'same'".into()));
+}
+
+#[test]
+fn ids_outside_numbered_chapters_are_an_error() {
+    let mut numbering = Numbering::new(Kind::Listing);
+    let result =
+        numbering.add_chapter(None, None, r#"<Listing id="foreword">"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn rewrites_ids_into_numbers() {
+    let numbering = numbered(&[(
+        Some(9),
+        "ch09-02.md",
+        r#"<Listing id="question-mark" file-name="src/main.rs" caption="Oops">
+"#,
+    )]);
+
+    let rewritten = rewrite_listings(
+        &numbering,
+        r#"<Listing id="question-mark" file-name="src/main.rs" caption="Oops">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#,
+        Some(Path::new("ch09-02.md")),
+        Mode::Default,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rewritten,
+        r#"<Listing number="9-1" file-name="src/main.rs" caption="Oops">
+
+```rust
+fn main() {}
+```
+
+</Listing>"#
+    );
+}
+
+mod references {
+    use super::*;
+
+    fn numbering() -> Numbering {
+        numbered(&[
+            (Some(9), "ch09-01.md", r#"<Listing id="panic">"#),
+            (Some(9), "nested/ch09-02.md", r#"<Listing id="result">"#),
+        ])
+    }
+
+    #[test]
+    fn link_within_the_same_chapter() {
+        let rewritten = rewrite_listings(
+            &numbering(),
+            "See {{listing-ref panic}}.",
+            Some(Path::new("ch09-01.md")),
+            Mode::Default,
+        )
+        .unwrap();
+        assert_eq!(rewritten, "See [Listing 9-1](#listing-9-1).");
+    }
+
+    #[test]
+    fn link_to_another_chapter() {
+        let numbering = numbering();
+
+        let rewritten = rewrite_listings(
+            &numbering,
+            "See {{listing-ref result}}.",
+            Some(Path::new("ch09-01.md")),
+            Mode::Default,
+        )
+        .unwrap();
+        assert_eq!(
+            rewritten,
+            "See [Listing 9-2](nested/ch09-02.md#listing-9-2)."
+        );
+
+        let rewritten = rewrite_listings(
+            &numbering,
+            "See {{listing-ref panic}}.",
+            Some(Path::new("nested/ch09-02.md")),
+            Mode::Default,
+        )
+        .unwrap();
+        assert_eq!(rewritten, "See [Listing 9-1](../ch09-01.md#listing-9-1).");
+    }
+
+    #[test]
+    fn plain_text_in_simple_mode() {
+        let rewritten = rewrite_listings(
+            &numbering(),
+            "In {{listing-ref panic}} and {{listing-ref result}}, we…",
+            Some(Path::new("ch09-01.md")),
+            Mode::Simple,
+        )
+        .unwrap();
+        assert_eq!(rewritten, "In Listing 9-1 and Listing 9-2, we…");
+    }
+
+    #[test]
+    fn code_blocks_are_left_alone() {
+        let src = "```markdown\n{{listing-ref panic}}\n```\n";
+        let rewritten = rewrite_listings(
+            &numbering(),
+            src,
+            Some(Path::new("ch09-01.md")),
+            Mode::Default,
+        )
+        .unwrap();
+        assert_eq!(rewritten, src);
+    }
+
+    #[test]
+    fn figure_references_are_left_for_the_figure_preprocessor() {
+        let src = "See {{figure-ref panic}}.";
+        let rewritten = rewrite_listings(
+            &numbering(),
+            src,
+            Some(Path::new("ch09-01.md")),
+            Mode::Default,
+        )
+        .unwrap();
+        assert_eq!(rewritten, src);
+    }
+
+    #[test]
+    fn unknown_ids_are_an_error() {
+        let result = rewrite_listings(
+            &numbering(),
+            "See {{listing-ref nope}}.",
+            Some(Path::new("ch09-01.md")),
+            Mode::Default,
+        );
+        assert_eq!(
+            result,
+            Err("No listing with id 'nope' for `{{listing-ref nope}}`".into())
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use mdbook::{
+    book::Book,
+    errors::Result,
+    preprocess::{Preprocessor, PreprocessorContext},
+    BookItem,
+};
+use regex::Regex;
+
+/// A preprocessor which makes the book’s async examples runnable on the Rust
+/// Playground.
+///
+/// mdBook gives every Rust code block a button for running it on the
+/// Playground, but the Playground does not have the `trpl` crate, so those
+/// buttons only produce errors for the examples which use it. This fixes that
+/// by adding a small stand-in for `trpl` to each such code block, built from
+/// crates the Playground does have (Tokio and `futures`), as hidden lines at
+/// the end of the code. Readers do not see it in the book, but it goes along
+/// to the Playground when they click the button. The `extern crate trpl;` line
+/// which `mdbook test` needs is removed, since the stand-in takes its place.
+///
+/// The stand-in only covers the parts of `trpl` which can work there, so code
+/// blocks which use anything else, like `trpl::get`, which needs network
+/// access the Playground does not allow, are marked `noplayground` instead, so
+/// that they have no button at all. Code blocks which are already
+/// `noplayground` are left alone.
+///
+/// This is for the HTML output only, and must run after mdBook’s own `links`
+/// preprocessor, so that the code from `{{#rustdoc_include}}` is in place:
+///
+/// ```toml
+/// [preprocessor.trpl-playground]
+/// after = ["links"]
+/// ```
+pub struct TrplPlayground;
+
+impl Preprocessor for TrplPlayground {
+    fn name(&self) -> &str {
+        "trpl-playground"
+    }
+
+    fn run(&self, _ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut chapter) = item {
+                chapter.content = rewrite_playground(&chapter.content);
+            }
+        });
+        Ok(book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html"
+    }
+}
+
+/// The stand-in for `trpl`, to be hidden at the end of each code block.
+const SHIM: &str = include_str!("trpl_shim.rs");
+
+/// The items from `trpl` which [`SHIM`] provides.
+const SHIM_ITEMS: &[&str] = &[
+    "block_on",
+    "channel",
+    "Either",
+    "join",
+    "join3",
+    "join_all",
+    "race",
+    "Receiver",
+    "run",
+    "select",
+    "Sender",
+    "sleep",
+    "spawn_task",
+    "Stream",
+    "StreamExt",
+    "stream_from_iter",
+    "yield_now",
+];
+
+/// Rewrite every Rust code block in `src` which uses `trpl`.
+fn rewrite_playground(src: &str) -> String {
+    let mut rewritten = String::with_capacity(src.len());
+    let mut lines = src.lines();
+    while let Some(line) = lines.next() {
+        let Some((fence, info)) = opening_fence(line) else {
+            rewritten.push_str(line);
+            rewritten.push('\n');
+            continue;
+        };
+
+        let mut code = vec![];
+        let mut closing = None;
+        for line in lines.by_ref() {
+            if line.trim() == fence {
+                closing = Some(line);
+                break;
+            }
+            code.push(line);
+        }
+
+        let indent = &line[..line.len() - line.trim_start().len()];
+        match rewrite_code_block(info, &code) {
+            Some((info, code)) => {
+                rewritten.push_str(&format!("{indent}{fence}{info}\n"));
+                for line in code {
+                    rewritten.push_str(&line);
+                    rewritten.push('\n');
+                }
+            }
+            None => {
+                rewritten.push_str(line);
+                rewritten.push('\n');
+                for line in code {
+                    rewritten.push_str(line);
+                    rewritten.push('\n');
+                }
+            }
+        }
+        if let Some(closing) = closing {
+            rewritten.push_str(closing);
+            rewritten.push('\n');
+        }
+    }
+
+    // As elsewhere, match the input’s trailing newline, or lack thereof.
+    if !src.ends_with('\n') {
+        rewritten.pop();
+    }
+    rewritten
+}
+
+/// The backticks and info string of a code block’s opening fence.
+fn opening_fence(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    let ticks = trimmed.len() - trimmed.trim_start_matches('`').len();
+    (ticks >= 3).then(|| trimmed.split_at(ticks))
+}
+
+/// The new info string and code for a code block, if it is one to change.
+fn rewrite_code_block(
+    info: &str,
+    code: &[&str],
+) -> Option<(String, Vec<String>)> {
+    let attributes: Vec<&str> = info.split(',').map(str::trim).collect();
+    if attributes.first() != Some(&"rust")
+        || attributes.contains(&"noplayground")
+    {
+        return None;
+    }
+
+    let used = trpl_items(code);
+    if used.is_empty() {
+        return None;
+    }
+
+    if !used.iter().all(|item| SHIM_ITEMS.contains(&item.as_str())) {
+        return Some((
+            format!("{info},noplayground"),
+            code.iter().map(|line| line.to_string()).collect(),
+        ));
+    }
+
+    let code = code
+        .iter()
+        .filter(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix("# ").unwrap_or(line);
+            !line.starts_with("extern crate trpl")
+        })
+        .map(|line| line.to_string())
+        .chain(SHIM.lines().map(|line| {
+            if line.is_empty() {
+                String::from("#")
+            } else {
+                format!("# {line}")
+            }
+        }))
+        .collect();
+    Some((info.to_string(), code))
+}
+
+/// The names of the items from `trpl` which `code` uses, from paths like
+/// `trpl::sleep` and `use trpl::{Either, Html};`.
+fn trpl_items(code: &[&str]) -> HashSet<String> {
+    static TRPL_PATH: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\btrpl::(?:\{([^}]*)\}|([A-Za-z_]\w*))").unwrap()
+    });
+
+    code.iter()
+        .flat_map(|line| TRPL_PATH.captures_iter(line))
+        .flat_map(|captures| match (captures.get(1), captures.get(2)) {
+            (Some(list), _) => list
+                .as_str()
+                .split(',')
+                .filter_map(|item| {
+                    let name = item.trim().split("::").next()?;
+                    let name = name.split_whitespace().next()?;
+                    Some(name.to_string())
+                })
+                .collect(),
+            (None, Some(name)) => vec![name.as_str().to_string()],
+            (None, None) => vec![],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
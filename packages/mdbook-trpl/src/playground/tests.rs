@@ -0,0 +1,105 @@
+use super::*;
+
+#[test]
+fn code_without_trpl_is_unchanged() {
+    let src = "Some text.
+
+```rust
+fn main() {
+    println!(\"Hello, world!\");
+}
+```
+";
+    assert_eq!(rewrite_playground(src), src);
+}
+
+#[test]
+fn adds_the_hidden_shim() {
+    let src = "```rust
+# extern crate trpl; // required for mdbook test
+fn main() {
+    trpl::block_on(async {
+        trpl::sleep(std::time::Duration::from_millis(1)).await;
+    });
+}
+```";
+
+    let rewritten = rewrite_playground(src);
+
+    assert!(!rewritten.contains("extern crate trpl"));
+    assert!(rewritten.starts_with(
+        "```rust
+fn main() {
+    trpl::block_on(async {"
+    ));
+    assert!(rewritten.contains("\n# mod trpl {\n"));
+    assert!(rewritten.ends_with("# }\n```"));
+
+    // Every line of the shim is hidden from readers.
+    let code_lines = rewritten.lines().skip(6).take_while(|l| *l != "```");
+    for line in code_lines {
+        assert!(line == "#" || line.starts_with("# "), "visible: {line:?}");
+    }
+}
+
+#[test]
+fn unsupported_items_get_no_playground() {
+    let src = "```rust
+use trpl::{Either, Html};
+
+fn main() {
+    trpl::block_on(async {
+        let page = trpl::get(\"https://www.rust-lang.org\").await;
+    });
+}
+```";
+
+    let rewritten = rewrite_playground(src);
+
+    assert_eq!(
+        rewritten,
+        src.replacen("```rust", "```rust,noplayground", 1)
+    );
+}
+
+#[test]
+fn noplayground_is_left_alone() {
+    let src = "```rust,should_panic,noplayground
+fn main() {
+    trpl::block_on(async { panic!() });
+}
+```";
+    assert_eq!(rewrite_playground(src), src);
+}
+
+#[test]
+fn other_languages_are_left_alone() {
+    let src = "```console
+$ cargo add trpl::sleep
+```";
+    assert_eq!(rewrite_playground(src), src);
+}
+
+#[test]
+fn longer_fences_work() {
+    let src = "````rust
+fn main() {
+    trpl::block_on(async {});
+}
+````";
+    let rewritten = rewrite_playground(src);
+    assert!(rewritten.starts_with("````rust\n"));
+    assert!(rewritten.ends_with("# }\n````"));
+}
+
+#[test]
+fn finds_items_in_paths_and_use_lists() {
+    let items = trpl_items(&[
+        "use trpl::{Either, StreamExt as _};",
+        "    trpl::join!(a, b);",
+        "let rx = trpl::channel::<u32>();",
+    ]);
+    let mut items: Vec<_> = items.into_iter().collect();
+    items.sort();
+    assert_eq!(items, ["Either", "StreamExt", "channel", "join"]);
+}
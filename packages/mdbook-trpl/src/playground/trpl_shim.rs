@@ -0,0 +1,69 @@
+// A stand-in for the `trpl` crate, which the Rust Playground does not have,
+// built from the crates it does have. It covers what the runnable listings
+// use, with the same names and behavior as the real crate.
+#[allow(dead_code, unused_imports)]
+mod trpl {
+    use std::{
+        future::Future,
+        pin::{pin, Pin},
+        task::{Context, Poll},
+    };
+
+    pub use futures::future::{join, join3, join_all, Either};
+    pub use futures::join;
+    pub use futures::stream::{Stream, StreamExt};
+    pub use tokio::sync::mpsc::UnboundedSender as Sender;
+    pub use tokio::task::{spawn as spawn_task, yield_now};
+    pub use tokio::time::sleep;
+
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    pub use block_on as run;
+
+    pub async fn select<A, B>(a: A, b: B) -> Either<A::Output, B::Output>
+    where
+        A: Future,
+        B: Future,
+    {
+        let a = pin!(a);
+        let b = pin!(b);
+        match futures::future::select(a, b).await {
+            Either::Left((a, _)) => Either::Left(a),
+            Either::Right((b, _)) => Either::Right(b),
+        }
+    }
+
+    pub use select as race;
+
+    pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (tx, Receiver(rx))
+    }
+
+    pub struct Receiver<T>(tokio::sync::mpsc::UnboundedReceiver<T>);
+
+    impl<T> Receiver<T> {
+        pub async fn recv(&mut self) -> Option<T> {
+            self.0.recv().await
+        }
+    }
+
+    impl<T> Stream for Receiver<T> {
+        type Item = T;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<T>> {
+            self.0.poll_recv(cx)
+        }
+    }
+
+    pub fn stream_from_iter<I: IntoIterator>(
+        iter: I,
+    ) -> impl Stream<Item = I::Item> {
+        futures::stream::iter(iter)
+    }
+}
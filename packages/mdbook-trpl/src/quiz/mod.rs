@@ -0,0 +1,319 @@
+use anyhow::anyhow;
+use mdbook::{
+    book::Book,
+    errors::Result,
+    preprocess::{Preprocessor, PreprocessorContext},
+    BookItem,
+};
+use pulldown_cmark::html;
+use serde::Deserialize;
+
+use crate::{config::Mode, CompositeError};
+
+/// A preprocessor for quizzes which check readers’ understanding of a section.
+///
+/// A quiz is a code block marked `quiz`, holding TOML with a list of
+/// questions. Each question is either multiple choice, with the `answer` being
+/// the text of the right choice, or short answer, with a `solution` to show:
+///
+/// ````markdown
+/// ```quiz
+/// title = "Check your understanding"  # optional; the default is “Quiz”
+///
+/// [[question]]
+/// type = "multiple-choice"
+/// prompt = "What does `.await` do to a future?"
+/// choices = ["Runs it on a new thread", "Polls it until it is ready"]
+/// answer = "Polls it until it is ready"
+/// explanation = "Futures are lazy: nothing happens until they are awaited."
+///
+/// [[question]]
+/// type = "short-answer"
+/// prompt = "Why can a stream not be iterated with a plain `for` loop?"
+/// solution = "Getting each item may need to wait, which `Iterator` cannot do."
+/// ```
+/// ````
+///
+/// Prompts, choices, answers, and explanations are all Markdown. In the HTML
+/// output, each quiz becomes a `<section class="quiz">`, with the answers in
+/// collapsed `<details>` so that readers think about the question before
+/// looking; `theme/quiz.css` has styles for it. With `output-mode = "simple"`,
+/// as for print, the quiz is plain Markdown instead, with the answers after
+/// all the questions.
+///
+/// A quiz which is not valid TOML, has no questions, or has a multiple choice
+/// `answer` which is not one of its `choices` fails the build.
+///
+/// ```toml
+/// [preprocessor.trpl-quiz]
+/// ```
+pub struct TrplQuiz;
+
+impl Preprocessor for TrplQuiz {
+    fn name(&self) -> &str {
+        "trpl-quiz"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let mode = Mode::from_context(ctx, self.name())?;
+
+        let mut errors = vec![];
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut chapter) = item {
+                match rewrite_quizzes(&chapter.content, mode) {
+                    Ok(rewritten) => chapter.content = rewritten,
+                    Err(reason) => errors.push(anyhow!(
+                        "Bad quiz in '{}': {reason}",
+                        chapter.name
+                    )),
+                }
+            }
+        });
+
+        if errors.is_empty() {
+            Ok(book)
+        } else {
+            Err(CompositeError(errors).into())
+        }
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html"
+            || renderer == "markdown"
+            || renderer == "test"
+            || renderer == "trpl-backend"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Quiz {
+    title: Option<String>,
+    #[serde(rename = "question", default)]
+    questions: Vec<Question>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+enum Question {
+    MultipleChoice {
+        prompt: String,
+        choices: Vec<String>,
+        answer: String,
+        explanation: Option<String>,
+    },
+    ShortAnswer {
+        prompt: String,
+        solution: String,
+    },
+}
+
+impl Quiz {
+    fn parse(toml_source: &str) -> Result<Quiz, String> {
+        let quiz: Quiz =
+            toml::from_str(toml_source).map_err(|e| e.message().to_string())?;
+        if quiz.questions.is_empty() {
+            return Err(String::from("a quiz needs at least one question"));
+        }
+        for question in &quiz.questions {
+            if let Question::MultipleChoice {
+                prompt,
+                choices,
+                answer,
+                ..
+            } = question
+            {
+                if !choices.contains(answer) {
+                    return Err(format!(
+                        "the answer to \"{prompt}\" is not one of its choices"
+                    ));
+                }
+            }
+        }
+        Ok(quiz)
+    }
+
+    fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or("Quiz")
+    }
+
+    /// The quiz as HTML, with Markdown for the prompts and such in between, so
+    /// that the rest of the build renders them like any other Markdown. The
+    /// HTML has to be separated from the Markdown by blank lines for that.
+    fn html(&self) -> String {
+        let mut html = format!(
+            "<section class=\"quiz\">\n<p class=\"quiz-title\">{}</p>\n\
+             <ol class=\"quiz-questions\">\n",
+            inline_html(self.title())
+        );
+        for question in &self.questions {
+            match question {
+                Question::MultipleChoice {
+                    prompt,
+                    choices,
+                    answer,
+                    explanation,
+                } => {
+                    html.push_str(&format!(
+                        "<li class=\"quiz-question multiple-choice\">\n\n\
+                         {prompt}\n\n<ol class=\"quiz-choices\" type=\"A\">\n"
+                    ));
+                    for choice in choices {
+                        html.push_str(&format!(
+                            "<li>{}</li>\n",
+                            inline_html(choice)
+                        ));
+                    }
+                    html.push_str(&format!(
+                        "</ol>\n<details class=\"quiz-answer\">\n\
+                         <summary>Answer</summary>\n\
+                         <p><strong>{}.</strong> {}</p>\n",
+                        letter(choices, answer),
+                        inline_html(answer)
+                    ));
+                    if let Some(explanation) = explanation {
+                        html.push_str(&format!("\n{explanation}\n\n"));
+                    }
+                    html.push_str("</details>\n</li>\n");
+                }
+                Question::ShortAnswer { prompt, solution } => {
+                    html.push_str(&format!(
+                        "<li class=\"quiz-question short-answer\">\n\n\
+                         {prompt}\n\n<details class=\"quiz-answer\">\n\
+                         <summary>Solution</summary>\n\n{solution}\n\n\
+                         </details>\n</li>\n"
+                    ));
+                }
+            }
+        }
+        html.push_str("</ol>\n</section>\n");
+        html
+    }
+
+    /// The quiz as plain Markdown, with the questions first and then the
+    /// answers, like the end of a chapter in a textbook.
+    fn markdown(&self) -> String {
+        let mut questions = format!("**{}**\n\n", self.title());
+        let mut answers = String::from("**Answers**\n\n");
+        for (index, question) in self.questions.iter().enumerate() {
+            let number = index + 1;
+            match question {
+                Question::MultipleChoice {
+                    prompt,
+                    choices,
+                    answer,
+                    explanation,
+                } => {
+                    questions
+                        .push_str(&format!("{number}. {}\n\n", indent(prompt)));
+                    for (choice, letter) in choices.iter().zip('A'..='Z') {
+                        questions.push_str(&format!("   {letter}. {choice}\n"));
+                    }
+                    questions.push('\n');
+
+                    answers.push_str(&format!(
+                        "{number}. {}. {answer}",
+                        letter(choices, answer)
+                    ));
+                    if let Some(explanation) = explanation {
+                        answers.push_str(&format!(
+                            "\n\n   {}",
+                            indent(explanation)
+                        ));
+                    }
+                    answers.push_str("\n\n");
+                }
+                Question::ShortAnswer { prompt, solution } => {
+                    questions
+                        .push_str(&format!("{number}. {}\n\n", indent(prompt)));
+                    answers.push_str(&format!(
+                        "{number}. {}\n\n",
+                        indent(solution)
+                    ));
+                }
+            }
+        }
+        questions + answers.trim_end() + "\n"
+    }
+}
+
+/// Replace each `quiz` code block in `src` with the quiz it describes.
+fn rewrite_quizzes(src: &str, mode: Mode) -> Result<String, String> {
+    let mut rewritten = String::with_capacity(src.len());
+    let mut lines = src.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let ticks = trimmed.len() - trimmed.trim_start_matches('`').len();
+        let (fence, info) = trimmed.split_at(ticks);
+        if ticks < 3 || info.trim() != "quiz" {
+            // Skip over other code blocks whole, so that a quiz shown as an
+            // example inside a longer fence stays as it is.
+            rewritten.push_str(line);
+            rewritten.push('\n');
+            if ticks >= 3 {
+                for line in lines.by_ref() {
+                    rewritten.push_str(line);
+                    rewritten.push('\n');
+                    if line.trim() == fence {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+
+        let mut toml_source = String::new();
+        let mut closed = false;
+        for line in lines.by_ref() {
+            if line.trim() == fence {
+                closed = true;
+                break;
+            }
+            toml_source.push_str(line);
+            toml_source.push('\n');
+        }
+        if !closed {
+            return Err(String::from("unclosed quiz block"));
+        }
+
+        let quiz = Quiz::parse(&toml_source)?;
+        rewritten.push_str(&match mode {
+            Mode::Default => quiz.html(),
+            Mode::Simple => quiz.markdown(),
+        });
+    }
+
+    // As with the other preprocessors, match the input’s trailing newline, or
+    // lack thereof.
+    if !src.ends_with('\n') {
+        rewritten.pop();
+    }
+    Ok(rewritten)
+}
+
+/// The letter for `answer` among `choices`, which it is known to be one of.
+fn letter(choices: &[String], answer: &str) -> char {
+    choices
+        .iter()
+        .zip('A'..='Z')
+        .find_map(|(choice, letter)| (choice == answer).then_some(letter))
+        .unwrap_or('?')
+}
+
+/// Markdown which is only one line, rendered as inline HTML.
+fn inline_html(markdown: &str) -> String {
+    let mut buf = String::with_capacity(markdown.len() * 2);
+    html::push_html(&mut buf, crate::parser(markdown));
+    buf.trim()
+        .trim_start_matches("<p>")
+        .trim_end_matches("</p>")
+        .to_string()
+}
+
+/// Indent all but the first line of `markdown` to sit inside a list item.
+fn indent(markdown: &str) -> String {
+    markdown.trim().replace('\n', "\n   ")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,155 @@
+use super::*;
+
+const QUIZ: &str = r#"Some text.
+
+```quiz
+[[question]]
+type = "multiple-choice"
+prompt = "What does `.await` do?"
+choices = ["Spawns a thread", "Polls the future"]
+answer = "Polls the future"
+explanation = "Futures are *lazy*."
+
+[[question]]
+type = "short-answer"
+prompt = "Why not use `for`?"
+solution = "Each item may need to wait."
+```
+
+More text.
+"#;
+
+#[test]
+fn default_mode_renders_html_with_hidden_answers() {
+    let rewritten = rewrite_quizzes(QUIZ, Mode::Default).unwrap();
+    assert_eq!(
+        rewritten,
+        r#"Some text.
+
+<section class="quiz">
+<p class="quiz-title">Quiz</p>
+<ol class="quiz-questions">
+<li class="quiz-question multiple-choice">
+
+What does `.await` do?
+
+<ol class="quiz-choices" type="A">
+<li>Spawns a thread</li>
+<li>Polls the future</li>
+</ol>
+<details class="quiz-answer">
+<summary>Answer</summary>
+<p><strong>B.</strong> Polls the future</p>
+
+Futures are *lazy*.
+
+</details>
+</li>
+<li class="quiz-question short-answer">
+
+Why not use `for`?
+
+<details class="quiz-answer">
+<summary>Solution</summary>
+
+Each item may need to wait.
+
+</details>
+</li>
+</ol>
+</section>
+
+More text.
+"#
+    );
+}
+
+#[test]
+fn simple_mode_puts_the_answers_last() {
+    let src = QUIZ.replace("```quiz\n", "```quiz\ntitle = \"Check\"\n");
+    let rewritten = rewrite_quizzes(&src, Mode::Simple).unwrap();
+    assert_eq!(
+        rewritten,
+        r#"Some text.
+
+**Check**
+
+1. What does `.await` do?
+
+   A. Spawns a thread
+   B. Polls the future
+
+2. Why not use `for`?
+
+**Answers**
+
+1. B. Polls the future
+
+   Futures are *lazy*.
+
+2. Each item may need to wait.
+
+More text.
+"#
+    );
+}
+
+#[test]
+fn quizzes_inside_other_code_blocks_are_left_alone() {
+    let src = "````markdown
+```quiz
+not = \"parsed\"
+```
+````";
+    assert_eq!(rewrite_quizzes(src, Mode::Default).unwrap(), src);
+}
+
+mod errors {
+    use super::*;
+
+    #[test]
+    fn answer_must_be_a_choice() {
+        let src = r#"```quiz
+[[question]]
+type = "multiple-choice"
+prompt = "Pick"
+choices = ["a", "b"]
+answer = "c"
+```"#;
+        assert_eq!(
+            rewrite_quizzes(src, Mode::Default).unwrap_err(),
+            "the answer to \"Pick\" is not one of its choices"
+        );
+    }
+
+    #[test]
+    fn no_questions() {
+        let src = "```quiz\ntitle = \"Empty\"\n```";
+        assert_eq!(
+            rewrite_quizzes(src, Mode::Default).unwrap_err(),
+            "a quiz needs at least one question"
+        );
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let src = r#"```quiz
+[[question]]
+type = "short-answer"
+prompt = "Why?"
+answer = "Because."
+```"#;
+        assert!(rewrite_quizzes(src, Mode::Default)
+            .unwrap_err()
+            .contains("unknown field `answer`"));
+    }
+
+    #[test]
+    fn unclosed() {
+        let src = "```quiz\n[[question]]\n";
+        assert_eq!(
+            rewrite_quizzes(src, Mode::Default).unwrap_err(),
+            "unclosed quiz block"
+        );
+    }
+}
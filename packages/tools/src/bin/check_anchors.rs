@@ -0,0 +1,308 @@
+// Checks that the anchors the chapters include, like the `here` in
+// `{{#rustdoc_include ../listings/ch01/listing-01-01/src/main.rs:here}}`, match
+// up with the `// ANCHOR: here` and `// ANCHOR_END: here` comments in the
+// listings' files.
+//
+// mdBook includes the whole file when an anchor it is asked for is missing, so
+// a typo in either place does not otherwise show up until someone reads the
+// rendered book closely. This reports:
+//
+// - anchors which the chapters use but the file does not have
+// - anchors which a file starts but never ends, or ends without starting
+// - anchors which a file has more than one part for, where a chapter uses them
+//   with `{{#include}}`, which only shows the first part (unlike
+//   `{{#rustdoc_include}}`, which shows all of them)
+//
+// Run this from the root of the repo.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+const USAGE: &str = "
+Check the anchors used in includes against the ANCHOR comments in listings.
+Usage:
+  check_anchors [<src-dir>]
+  check_anchors (-h | --help)
+Options:
+  -h --help  Show this screen.
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src_dir: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let src_dir = PathBuf::from(args.arg_src_dir.as_deref().unwrap_or("src"));
+
+    let mut problems = 0;
+
+    // First, every file with anchors has to have them well-formed.
+    let mut anchored_files = BTreeMap::new();
+    for entry in walkdir::WalkDir::new("listings").sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        // Skip binary files and the like; they have no anchors.
+        let Ok(contents) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let anchors = anchors(&contents);
+        for problem in &anchors.problems {
+            problems += 1;
+            eprintln!("{}:{problem}", entry.path().display());
+        }
+        anchored_files.insert(fs::canonicalize(entry.path())?, anchors);
+    }
+
+    // Then every anchor the chapters use has to exist.
+    let mut uses = 0;
+    for entry in walkdir::WalkDir::new(&src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(&src_dir);
+        for include in anchor_includes(&contents) {
+            uses += 1;
+            // Missing files are for `mdbook build` (and `orphan_listings`)
+            // to complain about.
+            let Ok(file) = fs::canonicalize(dir.join(&include.path)) else {
+                continue;
+            };
+            let starts = anchored_files
+                .get(&file)
+                .and_then(|anchors| anchors.starts.get(&include.anchor));
+            let problem = match starts {
+                None => "has no anchor",
+                Some(starts) if starts.len() > 1 && !include.rustdoc => {
+                    "only shows the first part of anchor"
+                }
+                Some(_) => continue,
+            };
+            problems += 1;
+            eprintln!(
+                "{}:{}: '{}' {problem} '{}'",
+                path.display(),
+                include.line,
+                include.path,
+                include.anchor
+            );
+        }
+    }
+
+    println!("Checked {uses} uses of anchors, found {problems} problems");
+    if problems > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// An include of one anchor in a file.
+#[derive(Debug, PartialEq, Eq)]
+struct AnchorInclude {
+    /// The 1-based line of the Markdown file it is on.
+    line: usize,
+    /// The path to the file, relative to the Markdown file.
+    path: String,
+    anchor: String,
+    /// Whether it is a `{{#rustdoc_include}}`.
+    rustdoc: bool,
+}
+
+lazy_static! {
+    static ref INCLUDE: Regex =
+        Regex::new(r"\{\{#(rustdoc_)?include\s+([^\s:}]+):([^\s:}]+)\s*\}\}")
+            .unwrap();
+    static ref ANCHOR_START: Regex =
+        Regex::new(r"ANCHOR:\s*([\w_-]+)").unwrap();
+    static ref ANCHOR_END: Regex =
+        Regex::new(r"ANCHOR_END:\s*([\w_-]+)").unwrap();
+}
+
+/// The includes in `markdown` which name an anchor, as opposed to including
+/// the whole file or a range of lines, like `:5` or `:2:10`.
+fn anchor_includes(markdown: &str) -> Vec<AnchorInclude> {
+    let mut includes = vec![];
+    for (index, line) in markdown.lines().enumerate() {
+        for captures in INCLUDE.captures_iter(line) {
+            let anchor = &captures[3];
+            if anchor.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            includes.push(AnchorInclude {
+                line: index + 1,
+                path: captures[2].to_string(),
+                anchor: anchor.to_string(),
+                rustdoc: captures.get(1).is_some(),
+            });
+        }
+    }
+    includes
+}
+
+/// The anchors in one file.
+#[derive(Debug, Default)]
+struct Anchors {
+    /// Each anchor, with the 1-based lines each of its parts starts on.
+    starts: BTreeMap<String, Vec<usize>>,
+    /// What is wrong with them, each starting with the line it is on.
+    problems: Vec<String>,
+}
+
+fn anchors(contents: &str) -> Anchors {
+    let mut anchors = Anchors::default();
+    // The anchors which have started and not yet ended, by name.
+    let mut open: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        // `ANCHOR_END:` contains `ANCHOR:` too, so check for it first.
+        if let Some(captures) = ANCHOR_END.captures(line) {
+            let name = &captures[1];
+            if open.remove(name).is_none() {
+                anchors.problems.push(format!(
+                    "{line_number}: anchor '{name}' ends without starting"
+                ));
+            }
+        } else if let Some(captures) = ANCHOR_START.captures(line) {
+            let name = captures[1].to_string();
+            if let Some(start) = open.get(&name) {
+                anchors.problems.push(format!(
+                    "{line_number}: anchor '{name}' starts again before its \
+                     part from line {start} ends"
+                ));
+            }
+            anchors
+                .starts
+                .entry(name.clone())
+                .or_default()
+                .push(line_number);
+            open.insert(name, line_number);
+        }
+    }
+
+    for (name, line_number) in open {
+        anchors
+            .problems
+            .push(format!("{line_number}: anchor '{name}' never ends"));
+    }
+    anchors
+        .problems
+        .sort_by_key(|problem| leading_number(problem));
+    anchors
+}
+
+fn leading_number(problem: &str) -> usize {
+    problem
+        .split(':')
+        .next()
+        .and_then(|number| number.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_named_anchors_only() {
+        let markdown = "\
+```rust
+{{#rustdoc_include ../listings/ch02/listing-02-01/src/main.rs:print}}
+```
+
+```rust
+{{#include ../listings/ch02/listing-02-01/src/main.rs}}
+{{#include ../listings/ch02/listing-02-01/src/main.rs:3}}
+{{#include ../listings/ch02/listing-02-01/src/main.rs:3:7}}
+```
+
+```toml
+{{#include ../listings/ch02/listing-02-02/Cargo.toml:8:}}
+{{#include ../listings/ch02/listing-02-02/Cargo.toml:dependencies}}
+```
+";
+        assert_eq!(
+            anchor_includes(markdown),
+            vec![
+                AnchorInclude {
+                    line: 2,
+                    path: "../listings/ch02/listing-02-01/src/main.rs".into(),
+                    anchor: "print".into(),
+                    rustdoc: true,
+                },
+                AnchorInclude {
+                    line: 13,
+                    path: "../listings/ch02/listing-02-02/Cargo.toml".into(),
+                    anchor: "dependencies".into(),
+                    rustdoc: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn well_formed_anchors() {
+        let anchors = anchors(
+            "// ANCHOR: all
+fn main() {
+    // ANCHOR: print
+    println!(\"Hi!\");
+    // ANCHOR_END: print
+}
+// ANCHOR_END: all
+// ANCHOR: print
+println!(\"Bye!\");
+// ANCHOR_END: print
+",
+        );
+        assert!(anchors.problems.is_empty());
+        assert_eq!(
+            anchors.starts.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("all".to_string(), vec![1]),
+                ("print".to_string(), vec![3, 8]),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_anchors() {
+        let anchors = anchors(
+            "// ANCHOR: here
+fn main() {}
+// ANCHOR: here
+// ANCHOR_END: here
+// ANCHOR: there
+// ANCHOR_END: elsewhere
+",
+        );
+        assert_eq!(
+            anchors.problems,
+            vec![
+                "3: anchor 'here' starts again before its part from line 1 \
+                 ends",
+                "5: anchor 'there' never ends",
+                "6: anchor 'elsewhere' ends without starting",
+            ]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+// Checks that the Ferris annotations on the book's code blocks still match
+// what the listings actually do:
+//
+// - `does_not_compile` listings should fail to build
+// - `panics` listings should build, then panic when run (or tested, for
+//   library listings)
+// - `not_desired_behavior` listings, and listings with no annotation at all,
+//   should build
+//
+// Each listing gets its own target directory in `tmp/check-annotations`, which
+// is deleted once the listing has been checked, so run this from the root of
+// the repo.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_book_tools::listings::{self, TargetDir};
+use serde::Deserialize;
+
+const USAGE: &str = "
+Check the does_not_compile, panics, and not_desired_behavior annotations.
+Usage:
+  check_annotations [--only=<pattern>] [--verbose] [<src-dir>]
+  check_annotations (-h | --help)
+Options:
+  -h --help          Show this screen.
+  --only=<pattern>   Only check listings whose path contains <pattern>.
+  --verbose          Show the cargo output for each mismatch.
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src_dir: Option<String>,
+    flag_only: Option<String>,
+    flag_verbose: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let src_dir = PathBuf::from(args.arg_src_dir.as_deref().unwrap_or("src"));
+    let tmp_dir = fs::canonicalize(".")?.join("tmp/check-annotations");
+
+    let mut listings = find_listings(&src_dir)?;
+    listings.retain(|listing, _| {
+        listings::is_selected(listing, args.flag_only.as_deref())
+    });
+
+    let mut mismatches = 0;
+    for (listing, uses) in &listings {
+        let expected = expected_outcome(uses);
+        let target_dir = TargetDir::under(&tmp_dir, listing);
+        let (actual, output) =
+            run_listing(listing, expected, target_dir.path())?;
+        drop(target_dir);
+        if actual == expected {
+            continue;
+        }
+
+        mismatches += 1;
+        // Point at the block which set the expectation, or else the first
+        // place the listing shows up.
+        let block = uses
+            .iter()
+            .find(|block| Outcome::from(block.annotation) == expected)
+            .unwrap_or(&uses[0]);
+        eprintln!(
+            "{}:{}: '{}' is expected to {}, but it {}",
+            block.source.display(),
+            block.line,
+            listing.display(),
+            expected.expected(),
+            actual.actual(expected),
+        );
+        if args.flag_verbose {
+            for line in output.lines() {
+                eprintln!("    {line}");
+            }
+        }
+    }
+
+    fs::remove_dir_all(&tmp_dir).ok();
+
+    println!(
+        "Checked {} listings, found {mismatches} mismatches",
+        listings.len()
+    );
+    if mismatches > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Which Ferris annotation a code block has, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Annotation {
+    DoesNotCompile,
+    Panics,
+    NotDesiredBehavior,
+    None,
+}
+
+impl Annotation {
+    fn from_info(info: &str) -> Annotation {
+        let attributes: Vec<&str> = info.split(',').map(str::trim).collect();
+        if attributes.contains(&"does_not_compile") {
+            Annotation::DoesNotCompile
+        } else if attributes.contains(&"panics") {
+            Annotation::Panics
+        } else if attributes.contains(&"not_desired_behavior") {
+            Annotation::NotDesiredBehavior
+        } else {
+            Annotation::None
+        }
+    }
+}
+
+/// What happens when building, and if need be running, a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    DoesNotCompile,
+    Panics,
+    Compiles,
+}
+
+impl From<Annotation> for Outcome {
+    fn from(annotation: Annotation) -> Outcome {
+        match annotation {
+            Annotation::DoesNotCompile => Outcome::DoesNotCompile,
+            Annotation::Panics => Outcome::Panics,
+            Annotation::NotDesiredBehavior | Annotation::None => {
+                Outcome::Compiles
+            }
+        }
+    }
+}
+
+impl Outcome {
+    fn expected(self) -> &'static str {
+        match self {
+            Outcome::DoesNotCompile => "fail to compile",
+            Outcome::Panics => "panic",
+            Outcome::Compiles => "compile",
+        }
+    }
+
+    fn actual(self, expected: Outcome) -> &'static str {
+        match self {
+            Outcome::DoesNotCompile => "does not compile",
+            Outcome::Panics => "panics",
+            Outcome::Compiles if expected == Outcome::Panics => {
+                "runs without panicking"
+            }
+            Outcome::Compiles => "compiles",
+        }
+    }
+}
+
+/// A Rust code block in the book which shows (part of) a listing.
+#[derive(Debug, PartialEq, Eq)]
+struct Block {
+    /// The Markdown file the block is in.
+    source: PathBuf,
+    /// The 1-based line of the block's opening fence.
+    line: usize,
+    annotation: Annotation,
+}
+
+/// A Rust code block's annotation and the files it includes, as written.
+#[derive(Debug, PartialEq, Eq)]
+struct CodeBlock {
+    line: usize,
+    annotation: Annotation,
+    includes: Vec<String>,
+}
+
+/// Every listing the Markdown files in `src_dir` include, along with each of
+/// the blocks which show it.
+fn find_listings(
+    src_dir: &Path,
+) -> Result<BTreeMap<PathBuf, Vec<Block>>, Box<dyn Error>> {
+    let mut listings: BTreeMap<PathBuf, Vec<Block>> = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(src_dir);
+        for block in code_blocks(&contents) {
+            for include in &block.includes {
+                let Some(listing) = listing_dir(&dir.join(include)) else {
+                    continue;
+                };
+                let uses = listings.entry(listing).or_default();
+                // A block which includes several files from one listing is
+                // still only one use of it.
+                if uses.last().is_some_and(|last| {
+                    last.source == path && last.line == block.line
+                }) {
+                    continue;
+                }
+                uses.push(Block {
+                    source: path.to_path_buf(),
+                    line: block.line,
+                    annotation: block.annotation,
+                });
+            }
+        }
+    }
+
+    Ok(listings)
+}
+
+lazy_static! {
+    static ref INCLUDE: Regex =
+        Regex::new(r"\{\{#(?:rustdoc_)?include\s+([^\s:}]+)").unwrap();
+}
+
+/// The Rust code blocks in `markdown` which include files, with the files each
+/// one includes.
+fn code_blocks(markdown: &str) -> Vec<CodeBlock> {
+    enum State {
+        Outside,
+        InRust(CodeBlock),
+        InOther,
+    }
+
+    let mut blocks = vec![];
+    let mut state = State::Outside;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            state = match state {
+                State::Outside if info.starts_with("rust") => {
+                    State::InRust(CodeBlock {
+                        line: index + 1,
+                        annotation: Annotation::from_info(info),
+                        includes: vec![],
+                    })
+                }
+                State::Outside => State::InOther,
+                State::InRust(block) => {
+                    if !block.includes.is_empty() {
+                        blocks.push(block);
+                    }
+                    State::Outside
+                }
+                State::InOther => State::Outside,
+            };
+            continue;
+        }
+
+        if let State::InRust(block) = &mut state {
+            block.includes.extend(
+                INCLUDE
+                    .captures_iter(trimmed)
+                    .map(|captures| captures[1].to_string()),
+            );
+        }
+    }
+
+    blocks
+}
+
+/// The package directory of the listing which contains `file`: the nearest
+/// directory above it with a `Cargo.toml`, as long as it is in `listings`.
+fn listing_dir(file: &Path) -> Option<PathBuf> {
+    let file = fs::canonicalize(file).ok()?;
+    let package = file
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())?;
+    if !package.components().any(|c| c.as_os_str() == "listings") {
+        return None;
+    }
+
+    let cwd = std::env::current_dir().ok()?;
+    Some(package.strip_prefix(&cwd).unwrap_or(package).to_path_buf())
+}
+
+/// What the book says should happen with a listing, from all the blocks which
+/// show it. A listing which is marked `does_not_compile` anywhere is expected
+/// not to compile, even where the book shows only a part of it which is fine
+/// on its own.
+fn expected_outcome(uses: &[Block]) -> Outcome {
+    let outcomes: Vec<Outcome> =
+        uses.iter().map(|block| block.annotation.into()).collect();
+    if outcomes.contains(&Outcome::DoesNotCompile) {
+        Outcome::DoesNotCompile
+    } else if outcomes.contains(&Outcome::Panics) {
+        Outcome::Panics
+    } else {
+        Outcome::Compiles
+    }
+}
+
+/// Build the listing, and run it too if it is expected to panic, producing
+/// what happened along with cargo's output.
+fn run_listing(
+    listing: &Path,
+    expected: Outcome,
+    target_dir: &Path,
+) -> Result<(Outcome, String), Box<dyn Error>> {
+    let (built, output) =
+        cargo(listing, &["build", "--all-targets"], target_dir)?;
+    if !built {
+        return Ok((Outcome::DoesNotCompile, output));
+    }
+    if expected != Outcome::Panics {
+        return Ok((Outcome::Compiles, output));
+    }
+
+    // Library listings show their panics by way of their tests.
+    let command = if listing.join("src/main.rs").is_file() {
+        "run"
+    } else {
+        "test"
+    };
+    let (succeeded, output) = cargo(listing, &[command], target_dir)?;
+    if !succeeded && output.contains("panicked") {
+        Ok((Outcome::Panics, output))
+    } else {
+        Ok((Outcome::Compiles, output))
+    }
+}
+
+/// Run cargo in `dir`, producing whether it succeeded and everything it
+/// printed.
+fn cargo(
+    dir: &Path,
+    args: &[&str],
+    target_dir: &Path,
+) -> Result<(bool, String), Box<dyn Error>> {
+    let output = Command::new("cargo")
+        .args(args)
+        .current_dir(dir)
+        .env("CARGO_TARGET_DIR", target_dir)
+        .env("RUST_TEST_THREADS", "1")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!("Could not run cargo in '{}': {e}", dir.display())
+        })?;
+
+    let mut printed = String::from_utf8_lossy(&output.stderr).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stdout));
+    Ok((output.status.success(), printed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_annotations_and_includes() {
+        let markdown = "\
+Some text.
+
+```rust,ignore,does_not_compile
+{{#rustdoc_include ../listings/ch04/listing-04-01/src/main.rs:here}}
+```
+
+```console
+{{#include ../listings/ch04/listing-04-01/output.txt}}
+```
+
+```rust,should_panic,panics
+{{#rustdoc_include ../listings/ch09/listing-09-01/src/main.rs}}
+```
+
+```rust
+fn inline_only() {}
+```
+
+```rust,noplayground,not_desired_behavior
+{{#include ../listings/ch11/no-listing-03/src/lib.rs}}
+```
+";
+
+        assert_eq!(
+            code_blocks(markdown),
+            vec![
+                CodeBlock {
+                    line: 3,
+                    annotation: Annotation::DoesNotCompile,
+                    includes: vec![
+                        "../listings/ch04/listing-04-01/src/main.rs".into()
+                    ],
+                },
+                CodeBlock {
+                    line: 11,
+                    annotation: Annotation::Panics,
+                    includes: vec![
+                        "../listings/ch09/listing-09-01/src/main.rs".into()
+                    ],
+                },
+                CodeBlock {
+                    line: 19,
+                    annotation: Annotation::NotDesiredBehavior,
+                    includes: vec![
+                        "../listings/ch11/no-listing-03/src/lib.rs".into()
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_compile_anywhere_wins() {
+        let block = |annotation| Block {
+            source: PathBuf::from("src/ch.md"),
+            line: 1,
+            annotation,
+        };
+
+        assert_eq!(
+            expected_outcome(&[
+                block(Annotation::None),
+                block(Annotation::DoesNotCompile),
+            ]),
+            Outcome::DoesNotCompile
+        );
+        assert_eq!(
+            expected_outcome(&[
+                block(Annotation::NotDesiredBehavior),
+                block(Annotation::Panics),
+            ]),
+            Outcome::Panics
+        );
+        assert_eq!(
+            expected_outcome(&[block(Annotation::NotDesiredBehavior)]),
+            Outcome::Compiles
+        );
+    }
+}
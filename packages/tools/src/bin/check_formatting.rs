@@ -0,0 +1,240 @@
+// Checks that every listing is formatted the way the book wants, and prints
+// the diff for any which is not. That is `rustfmt` with the `rustfmt.toml` at
+// the root of the repo, plus a few options which are unstable, so this needs a
+// nightly toolchain:
+//
+// - Lines `rustfmt` cannot make fit in the `max_width` suited to print are
+//   errors instead of being left alone. Since it skips over some, like long
+//   comments and strings, this checks every line's width itself, too.
+// - Every listing gets the same style, whatever its edition.
+// - Imports are sorted within the groups each listing has them in, but not
+//   regrouped: that would move them across the `// ANCHOR:` comments which
+//   pick out the part of a file a chapter shows.
+//
+// Listings with a `rustfmt-ignore` file are skipped, as when formatting them.
+// Run this from the root of the repo.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+const USAGE: &str = "
+Check that every listing is formatted in the book's style.
+Usage:
+  check_formatting [--only=<pattern>] [--toolchain=<toolchain>]
+  check_formatting (-h | --help)
+Options:
+  -h --help                Show this screen.
+  --only=<pattern>         Only check listings whose path contains <pattern>.
+  --toolchain=<toolchain>  Run rustfmt with this toolchain instead of `nightly`.
+";
+
+/// The options on top of `rustfmt.toml`, as described above.
+const BOOK_STYLE: [(&str, &str); 5] = [
+    ("error_on_line_overflow", "true"),
+    ("style_edition", "2024"),
+    ("reorder_imports", "true"),
+    ("group_imports", "Preserve"),
+    ("imports_granularity", "Preserve"),
+];
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_only: Option<String>,
+    flag_toolchain: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let toolchain = args.flag_toolchain.as_deref().unwrap_or("nightly");
+    let max_width = max_width(&fs::read_to_string("rustfmt.toml")?)
+        .ok_or("No `max_width` in 'rustfmt.toml'")?;
+
+    let mut packages = vec![];
+    for entry in walkdir::WalkDir::new("listings")
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target")
+    {
+        let entry = entry?;
+        if entry.file_name() == "Cargo.toml" {
+            packages.push(entry.path().parent().unwrap().to_path_buf());
+        }
+    }
+    if let Some(pattern) = &args.flag_only {
+        packages.retain(|path| path.to_string_lossy().contains(pattern));
+    }
+
+    let mut checked = 0;
+    let mut unformatted = vec![];
+    for package in &packages {
+        if package.join("rustfmt-ignore").is_file() {
+            continue;
+        }
+        let manifest = fs::read_to_string(package.join("Cargo.toml"))?;
+        // Workspaces have their members checked on their own.
+        let Some(edition) = edition(&manifest) else {
+            continue;
+        };
+
+        checked += 1;
+        let files = rust_files(package)?;
+        let mut problems = check(package, &files, edition, toolchain)?;
+        for file in &files {
+            for (line, width) in too_wide(&fs::read_to_string(file)?, max_width)
+            {
+                problems.push_str(&format!(
+                    "{}:{line}: {width} columns is wider than the {max_width} \
+                     which fit in print\n",
+                    file.display()
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            unformatted.push((package, problems));
+        }
+    }
+
+    for (package, diff) in &unformatted {
+        println!("{}:\n{diff}", package.display());
+    }
+    println!(
+        "Checked {checked} listings, found {} not in the book's style",
+        unformatted.len()
+    );
+    if !unformatted.is_empty() {
+        println!(
+            "Run `cargo fmt` in them, and reword or rewrap what it cannot fit."
+        );
+        process::exit(1);
+    }
+    Ok(())
+}
+
+lazy_static! {
+    static ref EDITION: Regex =
+        Regex::new(r#"(?m)^edition\s*=\s*"(\d+)""#).unwrap();
+    static ref MAX_WIDTH: Regex =
+        Regex::new(r"(?m)^max_width\s*=\s*(\d+)").unwrap();
+}
+
+fn max_width(rustfmt_toml: &str) -> Option<usize> {
+    MAX_WIDTH.captures(rustfmt_toml)?[1].parse().ok()
+}
+
+/// The 1-based numbers and widths of the lines in `contents` wider than
+/// `max_width`.
+fn too_wide(contents: &str, max_width: usize) -> Vec<(usize, usize)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.chars().count()))
+        .filter(|&(_, width)| width > max_width)
+        .collect()
+}
+
+/// The edition of the package with the `manifest`, or `None` if it is just a
+/// workspace.
+fn edition(manifest: &str) -> Option<&str> {
+    if !manifest.lines().any(|line| line.trim() == "[package]") {
+        return None;
+    }
+    Some(
+        EDITION
+            .captures(manifest)
+            .and_then(|captures| captures.get(1))
+            .map_or("2015", |edition| edition.as_str()),
+    )
+}
+
+/// Run `rustfmt --check` on the `files` of the `package`, returning what it
+/// printed if anything needs to change.
+fn check(
+    package: &Path,
+    files: &[PathBuf],
+    edition: &str,
+    toolchain: &str,
+) -> Result<String, Box<dyn Error>> {
+    if files.is_empty() {
+        return Ok(String::new());
+    }
+
+    // Each file gets checked itself, so modules it declares can be skipped.
+    let config = BOOK_STYLE
+        .iter()
+        .chain(&[("skip_children", "true")])
+        .map(|(option, value)| format!("{option}={value}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    let output = Command::new("rustfmt")
+        .args(["--check", "--color", "never", "--edition", edition])
+        .args(["--config", &config])
+        .args(files)
+        .env("RUSTUP_TOOLCHAIN", toolchain)
+        .output()
+        .map_err(|e| {
+            format!("Could not run rustfmt in '{}': {e}", package.display())
+        })?;
+    if output.status.success() {
+        return Ok(String::new());
+    }
+
+    let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(printed)
+}
+
+/// The Rust files in `package`, leaving out any packages nested in it and
+/// build output.
+fn rust_files(package: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = vec![];
+    for entry in walkdir::WalkDir::new(package)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !(entry.file_name() == "target"
+                    || entry.path().join("Cargo.toml").is_file())
+        })
+    {
+        let entry = entry?;
+        if entry.path().extension().is_some_and(|ext| ext == "rs") {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editions_come_from_packages_only() {
+        let package = "[package]\nname = \"adder\"\nedition = \"2024\"\n";
+        assert_eq!(edition(package), Some("2024"));
+        assert_eq!(edition("[package]\nname = \"old\"\n"), Some("2015"));
+        assert_eq!(
+            edition("[workspace]\nresolver = \"3\"\nmembers = [\"adder\"]\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_lines_too_wide_for_print() {
+        assert_eq!(max_width("edition = \"2024\"\nmax_width = 80\n"), Some(80));
+        let contents = "fn main() {\n    // Fits.\n    \
+                        // This comment is much wider than the few columns \
+                        which fit.\n}\n";
+        assert_eq!(too_wide(contents, 20), vec![(3, 65)]);
+        assert_eq!(too_wide(contents, 80), vec![]);
+    }
+}
@@ -0,0 +1,332 @@
+// Builds every listing which uses `trpl` twice: once against the copy in
+// `packages/trpl`, the way the book's own tests build them, and once against
+// the latest version of `trpl` published to crates.io, the way readers who
+// follow the printed book with `cargo add trpl` build them. Any difference
+// between the two, like a listing which only compiles with one of them or
+// which fails with different errors, means the published crate has drifted
+// from the book, so run this before publishing a new release of `trpl`, and
+// again after.
+//
+// Each listing gets copied into `tmp/check-trpl` for each build, so the
+// listings themselves, and their `Cargo.lock`s, are left alone. The copy for
+// the published crate drops the `Cargo.lock`, just like a reader's new
+// project would not have one.
+//
+// Run this from the root of the repo, with network access to crates.io.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_book_tools::listings::{self, TargetDir};
+use serde::Deserialize;
+
+const USAGE: &str = "
+Build the listings which use trpl against both the local and the published trpl.
+Usage:
+  check_trpl [--published=<version>] [--only=<pattern>]
+  check_trpl (-h | --help)
+Options:
+  -h --help              Show this screen.
+  --published=<version>  Build against this version requirement for the
+                         published trpl instead of the latest version, `*`.
+  --only=<pattern>       Only build listings whose path contains <pattern>.
+";
+
+/// The path the listings use for `trpl`, relative to their own directory.
+const LOCAL_TRPL: &str = "../../../packages/trpl";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    flag_published: Option<String>,
+    flag_only: Option<String>,
+}
+
+lazy_static! {
+    static ref TRPL_DEPENDENCY: Regex =
+        Regex::new(r#"(?m)^trpl\s*=\s*\{\s*path\s*=\s*"[^"]*"\s*\}"#).unwrap();
+    static ref LOCKED_TRPL: Regex =
+        Regex::new(r#"name = "trpl"\nversion = "([^"]+)"\nsource = "registry"#)
+            .unwrap();
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let requirement = args.flag_published.as_deref().unwrap_or("*");
+    let local_trpl = fs::canonicalize("packages/trpl")
+        .map_err(|e| format!("Could not find 'packages/trpl': {e}"))?;
+
+    let mut listings = vec![];
+    for entry in walkdir::WalkDir::new("listings").min_depth(2).max_depth(2) {
+        let path = entry?.into_path();
+        let manifest = path.join("Cargo.toml");
+        if manifest.is_file()
+            && fs::read_to_string(&manifest)?.contains(LOCAL_TRPL)
+        {
+            listings.push(path);
+        }
+    }
+    listings.sort();
+    listings
+        .retain(|path| listings::is_selected(path, args.flag_only.as_deref()));
+
+    let scratch = Path::new("tmp/check-trpl");
+    let local_dependency =
+        format!("trpl = {{ path = {:?} }}", local_trpl.display());
+    let published_dependency = format!("trpl = {requirement:?}");
+    let mut published_versions = BTreeSet::new();
+    let mut mismatches = vec![];
+    for listing in &listings {
+        println!("Building '{}'...", listing.display());
+        let (local, _) = build_copy(listing, &scratch.join("local"), |dir| {
+            rewrite_dependency(dir, &local_dependency)
+        })?;
+        let (published, version) =
+            build_copy(listing, &scratch.join("published"), |dir| {
+                fs::remove_file(dir.join("Cargo.lock")).ok();
+                rewrite_dependency(dir, &published_dependency)
+            })?;
+        published_versions.extend(version);
+
+        if let Some(difference) = difference(&local, &published) {
+            mismatches.push((listing, difference));
+        }
+    }
+    fs::remove_dir_all(scratch).ok();
+
+    let published = if published_versions.is_empty() {
+        format!("the published trpl ({requirement})")
+    } else {
+        let versions: Vec<_> = published_versions.into_iter().collect();
+        format!("trpl {} from crates.io", versions.join(", "))
+    };
+    println!(
+        "\nBuilt {} listings against the local trpl and {published}: {} \
+         mismatched.",
+        listings.len(),
+        mismatches.len()
+    );
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\nThese listings build differently against the published trpl, so \
+         readers following the book with `cargo add trpl` will not get what \
+         the book says:"
+    );
+    for (listing, difference) in &mismatches {
+        println!("\n{}", listing.display());
+        for line in difference {
+            println!("  {line}");
+        }
+    }
+    process::exit(1);
+}
+
+fn listing_name(listing: &Path) -> Result<&OsStr, Box<dyn Error>> {
+    listing.file_name().ok_or_else(|| {
+        format!("No directory name for '{}'", listing.display()).into()
+    })
+}
+
+/// Copy `listing` into `scratch`, without its `target` directory, let
+/// `prepare` change the copy, and then build all of its targets. Along with
+/// how that went, return the version of `trpl` Cargo picked from crates.io,
+/// if it did.
+///
+/// Each copy gets built in a `TargetDir` inside it, deleted along with it.
+fn build_copy(
+    listing: &Path,
+    scratch: &Path,
+    prepare: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>,
+) -> Result<(Build, Option<String>), Box<dyn Error>> {
+    let dir = scratch.join(listing_name(listing)?);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    for entry in walkdir::WalkDir::new(listing)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "target")
+    {
+        let entry = entry?;
+        let to = dir.join(entry.path().strip_prefix(listing)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&to)?;
+        } else {
+            fs::copy(entry.path(), &to)?;
+        }
+    }
+    prepare(&dir)?;
+
+    let target_dir = TargetDir::in_listing(&dir);
+    let output = Command::new("cargo")
+        .args(["check", "--all-targets", "--quiet"])
+        .current_dir(&dir)
+        .env("RUSTFLAGS", "-A unused_variables -A dead_code")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!("Could not run cargo in '{}': {e}", dir.display())
+        })?;
+
+    let version =
+        fs::read_to_string(dir.join("Cargo.lock"))
+            .ok()
+            .and_then(|lock| {
+                LOCKED_TRPL
+                    .captures(&lock)
+                    .map(|captures| captures[1].to_string())
+            });
+    drop(target_dir);
+    fs::remove_dir_all(&dir)?;
+
+    let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((build(output.status.success(), &printed), version))
+}
+
+/// Point the `trpl` dependency in the manifest in `dir` somewhere else.
+fn rewrite_dependency(
+    dir: &Path,
+    dependency: &str,
+) -> Result<(), Box<dyn Error>> {
+    let manifest = dir.join("Cargo.toml");
+    let contents = fs::read_to_string(&manifest)?;
+    if !TRPL_DEPENDENCY.is_match(&contents) {
+        return Err(format!(
+            "No `trpl` path dependency in '{}'",
+            manifest.display()
+        )
+        .into());
+    }
+    let contents =
+        TRPL_DEPENDENCY.replace(&contents, regex::NoExpand(dependency));
+    fs::write(&manifest, contents.as_ref())?;
+    Ok(())
+}
+
+/// How building a listing went: whether it compiled, and if not, the errors,
+/// which many listings have on purpose.
+#[derive(Debug, PartialEq, Eq)]
+struct Build {
+    compiles: bool,
+    errors: BTreeSet<String>,
+}
+
+fn build(success: bool, output: &str) -> Build {
+    // Cargo's own errors just count the compiler's, and so do the compiler's
+    // summaries at the end.
+    let errors = output
+        .lines()
+        .filter(|line| line.starts_with("error"))
+        .filter(|line| {
+            !["error: could not compile", "error: aborting due to"]
+                .iter()
+                .any(|summary| line.starts_with(summary))
+        })
+        .map(String::from)
+        .collect();
+    Build {
+        compiles: success,
+        errors,
+    }
+}
+
+/// What differs between building a listing against the local `trpl` and the
+/// published one, if anything.
+fn difference(local: &Build, published: &Build) -> Option<Vec<String>> {
+    if local == published {
+        return None;
+    }
+
+    let mut difference = vec![];
+    match (local.compiles, published.compiles) {
+        (true, false) => difference.push(String::from(
+            "compiles with the local trpl, but not the published one",
+        )),
+        (false, true) => difference.push(String::from(
+            "compiles with the published trpl, but not the local one",
+        )),
+        _ => {}
+    }
+    for error in published.errors.difference(&local.errors) {
+        difference.push(format!("only with the published trpl: {error}"));
+    }
+    for error in local.errors.difference(&published.errors) {
+        difference.push(format!("only with the local trpl: {error}"));
+    }
+    Some(difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_compiler_errors() {
+        let output = "\
+error[E0599]: no method named `race` found for struct `Select` in the current scope
+ --> src/main.rs:7:35
+error[E0425]: cannot find function `sleep` in crate `trpl`
+error: aborting due to 2 previous errors
+error: could not compile `async_await` (bin \"async_await\") due to 2 previous errors
+";
+        assert_eq!(
+            build(false, output),
+            Build {
+                compiles: false,
+                errors: BTreeSet::from([
+                    String::from(
+                        "error[E0425]: cannot find function `sleep` in crate \
+                         `trpl`"
+                    ),
+                    String::from(
+                        "error[E0599]: no method named `race` found for \
+                         struct `Select` in the current scope"
+                    ),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn reports_only_differences() {
+        let moved = "error[E0382]: borrow of moved value: `tx`\n";
+        let missing = "error[E0425]: cannot find function `sleep` in crate \
+                       `trpl`\n";
+        assert_eq!(difference(&build(true, ""), &build(true, "")), None);
+        assert_eq!(
+            difference(&build(false, moved), &build(false, moved)),
+            None
+        );
+        assert_eq!(
+            difference(&build(true, ""), &build(false, missing)),
+            Some(vec![
+                String::from(
+                    "compiles with the local trpl, but not the published one"
+                ),
+                format!("only with the published trpl: {}", missing.trim()),
+            ])
+        );
+        assert_eq!(
+            difference(
+                &build(false, moved),
+                &build(false, &format!("{moved}{missing}"))
+            ),
+            Some(vec![format!(
+                "only with the published trpl: {}",
+                missing.trim()
+            )])
+        );
+    }
+}
@@ -1,3 +1,12 @@
+// Concatenates the Markdown files for each chapter into one file, like
+// `nostarch/chapter17.md`, and writes a `manifest.json` next to them listing
+// which files went into each chapter and which listings each one has.
+//
+// The chapters are copied as they are, so any listing `id`s the `trpl-listing`
+// preprocessor wrote stay exactly the same. No `id`s get added, though: the
+// plain-text output for No Starch has none, and should not gain HTML just for
+// this, so tools which need a listing's anchor get it from the manifest.
+
 use std::collections::BTreeMap;
 use std::env;
 use std::fs::{File, create_dir, read_dir};
@@ -8,6 +17,7 @@ use std::process::exit;
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::Serialize;
 
 static PATTERNS: &[(&str, &str)] = &[
     (r"ch(\d\d)-\d\d-.*\.md", "chapter$1.md"),
@@ -37,9 +47,49 @@ fn main() {
     let mut matched_files = match_files(source_dir, target_dir);
     matched_files.sort();
 
+    let mut manifest = Manifest::default();
     for (target_path, source_paths) in group_by_target(matched_files) {
-        concat_files(source_paths, target_path).unwrap();
+        manifest
+            .chapters
+            .push(concat_files(source_paths, target_path).unwrap());
     }
+
+    // Tools which work on the concatenated chapters can find the listings in
+    // them from this, rather than parsing the Markdown again.
+    let manifest_path = target_dir.join("manifest.json");
+    println!("Writing manifest to {}", manifest_path.to_string_lossy());
+    let manifest_file = File::create(manifest_path).unwrap();
+    serde_json::to_writer_pretty(manifest_file, &manifest).unwrap();
+}
+
+/// Which source files went into each concatenated chapter, and what listings
+/// each chapter has.
+#[derive(Debug, Default, Serialize)]
+struct Manifest {
+    chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Serialize)]
+struct Chapter {
+    /// The concatenated file, like `chapter17.md`.
+    file: String,
+    /// The files it is made from, in order.
+    sources: Vec<String>,
+    listings: Vec<Listing>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Listing {
+    /// Like `17-1`.
+    number: String,
+    /// The `id` the listing has in the HTML book, like `listing-17-1`, which
+    /// only depends on its number, so it stays the same wherever the listing
+    /// ends up.
+    anchor: String,
+    caption: Option<String>,
+    file_name: Option<String>,
+    /// The 1-based line of the concatenated file with the listing’s caption.
+    line: usize,
 }
 
 fn match_files(
@@ -86,12 +136,10 @@ fn group_by_target(
 fn concat_files(
     source_paths: Vec<PathBuf>,
     target_path: PathBuf,
-) -> io::Result<()> {
+) -> io::Result<Chapter> {
     println!("Concatenating into {}:", target_path.to_string_lossy());
-    let mut target = File::create(target_path)?;
-
-    write!(
-        target,
+    let mut target = File::create(&target_path)?;
+    let mut concatenated = String::from(
         "\
 <!-- DO NOT EDIT THIS FILE.
 
@@ -100,20 +148,128 @@ directory, so all fixes need to be made in `/src/`.
 -->
 
 [TOC]
-"
-    )?;
+",
+    );
 
+    // `link2print` numbers the footnotes in each file from 1, so keep
+    // counting across the files instead, or the chapter would have several
+    // footnotes with the same number.
+    let mut footnotes = 0;
+    let mut sources = vec![];
     for path in source_paths {
         println!("  {}", path.to_string_lossy());
-        let mut source = File::open(path)?;
-        let mut contents: Vec<u8> = Vec::new();
-        source.read_to_end(&mut contents)?;
+        let mut source = File::open(&path)?;
+        let mut contents = String::new();
+        source.read_to_string(&mut contents)?;
+        let (contents, count) = renumber_footnotes(&contents, footnotes);
+        footnotes += count;
+
+        concatenated.push('\n');
+        concatenated.push_str(&contents);
+        concatenated.push('\n');
+        sources.push(file_name(&path));
+    }
+    target.write_all(concatenated.as_bytes())?;
+
+    Ok(Chapter {
+        file: file_name(&target_path),
+        sources,
+        listings: listings(&concatenated),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+lazy_static! {
+    static ref CAPTION: Regex = Regex::new(
+        r#"^(?:<figcaption>(?:<a href="[^"]*">)?)?Listing (\d+-\d+)(?:</a>)?(?:: (.*?))?(?:</figcaption>)?$"#
+    )
+    .unwrap();
+    static ref FIGURE_ID: Regex =
+        Regex::new(r#"^<figure class="listing" id="([^"]+)">$"#).unwrap();
+    static ref FILE_NAME: Regex = Regex::new(
+        r#"^(?:<span class="file-name">)?(?:Filename: )?([\w-]+(?:[./][\w-]+)+)(?:</span>)?$"#
+    )
+    .unwrap();
+}
+
+/// The numbered listings in a concatenated chapter, from the text the
+/// `trpl-listing` preprocessor writes around them, either as HTML or, with
+/// `output-mode = "simple"`, as plain text. Listings in block quotes count
+/// too.
+fn listings(chapter: &str) -> Vec<Listing> {
+    let mut listings = vec![];
+    let mut in_code_block = false;
+    let mut anchor = None;
+    // A listing’s file name is on the line before its code, if it has one.
+    let mut line_before_code = None;
+    let mut file_name = None;
+    for (index, line) in chapter.lines().enumerate() {
+        let line = line.trim_start_matches(['>', ' ']);
+        if line.starts_with("```") {
+            if !in_code_block {
+                file_name = line_before_code
+                    .take()
+                    .and_then(|line| FILE_NAME.captures(line))
+                    .map(|caps| caps[1].to_string());
+            }
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.is_empty() {
+            continue;
+        }
+
+        if let Some(caps) = FIGURE_ID.captures(line) {
+            anchor = Some(caps[1].to_string());
+        } else if let Some(caps) = CAPTION.captures(line) {
+            let number = caps[1].to_string();
+            listings.push(Listing {
+                anchor: anchor
+                    .take()
+                    .unwrap_or_else(|| format!("listing-{number}")),
+                number,
+                caption: caps.get(2).map(|caption| caption.as_str().into()),
+                file_name: file_name.take(),
+                line: index + 1,
+            });
+        } else {
+            line_before_code = Some(line);
+        }
+    }
+    listings
+}
+
+lazy_static! {
+    static ref FOOTNOTE: Regex = Regex::new(r"\[\^(\d+)\]").unwrap();
+}
 
-        target.write_all(b"\n")?;
-        target.write_all(&contents)?;
-        target.write_all(b"\n")?;
+/// Add `offset` to the number of every footnote in `contents`, outside code
+/// blocks, and count how many footnotes it has.
+fn renumber_footnotes(contents: &str, offset: usize) -> (String, usize) {
+    let mut count = 0;
+    let mut in_code_block = false;
+    let mut renumbered = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            renumbered.push_str(line);
+            continue;
+        }
+        let line = FOOTNOTE.replace_all(line, |caps: &regex::Captures<'_>| {
+            let number: usize = caps[1].parse().unwrap();
+            count = count.max(number);
+            format!("[^{}]", number + offset)
+        });
+        renumbered.push_str(&line);
     }
-    Ok(())
+    (renumbered, count)
 }
 
 fn ensure_dir_exists(dir_string: &str) -> io::Result<&Path> {
@@ -123,3 +279,117 @@ fn ensure_dir_exists(dir_string: &str) -> io::Result<&Path> {
     }
     Ok(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The HTML listings here are as the `trpl-listing` preprocessor writes
+    /// them, in `opening_html` and `closing_html`.
+    #[test]
+    fn finds_listings_in_either_output_mode() {
+        let chapter = r##"Some text, then Listing 17-1: not a caption.
+
+src/main.rs
+
+```rust
+// Listing 17-9: not a caption either
+fn main() {}
+```
+
+Listing 17-1: Using `trpl` to run a future
+
+src/lib.rs
+
+This text means the file name is not for this code.
+
+```rust
+```
+
+> Listing 17-2
+>
+> <figure class="listing" id="listing-17-3">
+> <span class="file-name">Filename: src/main.rs</span>
+>
+> ````rust
+> fn main() {}
+> ````
+>
+> <figcaption><a href="#listing-17-3">Listing 17-3</a>: In <em>HTML</em></figcaption>
+> </figure>
+
+<figure class="listing" id="listing-17-4">
+
+````rust
+````
+
+<figcaption><a href="#listing-17-4">Listing 17-4</a></figcaption>
+</figure>
+"##;
+        assert_eq!(
+            listings(chapter),
+            vec![
+                Listing {
+                    number: "17-1".into(),
+                    anchor: "listing-17-1".into(),
+                    caption: Some("Using `trpl` to run a future".into()),
+                    file_name: Some("src/main.rs".into()),
+                    line: 10,
+                },
+                Listing {
+                    number: "17-2".into(),
+                    anchor: "listing-17-2".into(),
+                    caption: None,
+                    file_name: None,
+                    line: 19,
+                },
+                Listing {
+                    number: "17-3".into(),
+                    anchor: "listing-17-3".into(),
+                    caption: Some("In <em>HTML</em>".into()),
+                    file_name: Some("src/main.rs".into()),
+                    line: 28,
+                },
+                Listing {
+                    number: "17-4".into(),
+                    anchor: "listing-17-4".into(),
+                    caption: None,
+                    file_name: None,
+                    line: 36,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn renumbers_footnotes_after_earlier_files() {
+        let contents = "\
+A link[^1] and another[^2].
+
+```rust
+let s = \"[^1]\";
+```
+
+[^1]: https://example.com
+[^2]: https://example.org
+";
+        assert_eq!(
+            renumber_footnotes(contents, 3),
+            (
+                String::from(
+                    "\
+A link[^4] and another[^5].
+
+```rust
+let s = \"[^1]\";
+```
+
+[^4]: https://example.com
+[^5]: https://example.org
+"
+                ),
+                2
+            )
+        );
+    }
+}
@@ -2,16 +2,25 @@ use std::io;
 use std::io::Read;
 
 fn main() {
-    let mut is_in_code_block = false;
-    let mut is_in_inline_code = false;
-    let mut is_in_html_tag = false;
-
     let mut buffer = String::new();
     if let Err(e) = io::stdin().read_to_string(&mut buffer) {
         panic!("{e}");
     }
 
-    for line in buffer.lines() {
+    print!("{}", convert_quotes(&buffer));
+}
+
+fn convert_quotes(input: &str) -> String {
+    let mut is_in_code_block = false;
+    let mut is_in_inline_code = false;
+    let mut is_in_html_tag = false;
+    // The book’s own elements, like `<Listing>`, have attributes with text for
+    // readers in them, like `caption`, so their quotes get converted too.
+    let mut is_in_custom_tag = false;
+    let mut is_in_attribute_value = false;
+
+    let mut output = String::with_capacity(input.len());
+    for line in input.lines() {
         if line.is_empty() {
             is_in_inline_code = false;
         }
@@ -21,29 +30,45 @@ fn main() {
         if is_in_code_block {
             is_in_inline_code = false;
             is_in_html_tag = false;
-            println!("{line}");
+            is_in_custom_tag = false;
+            is_in_attribute_value = false;
+            output.push_str(line);
         } else {
-            let modified_line = &mut String::new();
             let mut previous_char = std::char::REPLACEMENT_CHARACTER;
-            let chars_in_line = line.chars();
+            let mut chars_in_line = line.chars().peekable();
 
-            for possible_match in chars_in_line {
+            while let Some(possible_match) = chars_in_line.next() {
                 // Check if inside inline code.
                 if possible_match == '`' {
                     is_in_inline_code = !is_in_inline_code;
                 }
-                // Check if inside HTML tag.
-                if possible_match == '<' && !is_in_inline_code {
+                // Check if inside HTML tag. Angle brackets inside a custom
+                // tag’s attributes are part of the text, as in `Vec<T>`.
+                if possible_match == '<'
+                    && !is_in_inline_code
+                    && !is_in_attribute_value
+                {
                     is_in_html_tag = true;
+                    is_in_custom_tag = chars_in_line
+                        .peek()
+                        .is_some_and(|next| next.is_ascii_uppercase());
                 }
-                if possible_match == '>' && !is_in_inline_code {
+                if possible_match == '>'
+                    && !is_in_inline_code
+                    && !is_in_attribute_value
+                {
                     is_in_html_tag = false;
+                    is_in_custom_tag = false;
+                }
+                let is_in_tag_markup = is_in_html_tag && !is_in_attribute_value;
+                if possible_match == '"' && is_in_custom_tag {
+                    is_in_attribute_value = !is_in_attribute_value;
                 }
 
                 // Replace with right/left apostrophe/quote.
                 let char_to_push = if possible_match == '\''
                     && !is_in_inline_code
-                    && !is_in_html_tag
+                    && (!is_in_html_tag || is_in_attribute_value)
                 {
                     if (previous_char != std::char::REPLACEMENT_CHARACTER
                         && !previous_char.is_whitespace())
@@ -69,10 +94,56 @@ fn main() {
                     // Leave untouched.
                     possible_match
                 };
-                modified_line.push(char_to_push);
-                previous_char = char_to_push;
+                output.push(char_to_push);
+                // The start of an attribute value is the start of its text.
+                previous_char = if is_in_tag_markup && possible_match == '"' {
+                    std::char::REPLACEMENT_CHARACTER
+                } else {
+                    char_to_push
+                };
             }
-            println!("{modified_line}");
         }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_quotes_in_text() {
+        assert_eq!(
+            convert_quotes("It's \"fine\", but not `'a` or\n```\n'b'\n```\n"),
+            "It’s “fine”, but not `'a` or\n```\n'b'\n```\n"
+        );
+    }
+
+    #[test]
+    fn converts_quotes_in_custom_element_attributes() {
+        let input = r#"<Listing number="10-17" file-name="src/main.rs" caption="Rust's 'lifetimes' of `'a` and `Vec<T>`">
+
+```rust
+let x = 'x';
+```
+
+</Listing>
+
+<span class="filename">it's</span> and that's it
+"#;
+        assert_eq!(
+            convert_quotes(input),
+            r#"<Listing number="10-17" file-name="src/main.rs" caption="Rust’s ‘lifetimes’ of `'a` and `Vec<T>`">
+
+```rust
+let x = 'x';
+```
+
+</Listing>
+
+<span class="filename">it’s</span> and that’s it
+"#
+        );
     }
 }
@@ -2,13 +2,42 @@
 // ignore-tidy-linelength
 
 use std::collections::HashMap;
+use std::fs;
 use std::io;
 use std::io::Read;
+use std::path::PathBuf;
 
+use docopt::Docopt;
 use regex::{Captures, Regex};
+use serde::Deserialize;
+
+const USAGE: &str = "
+Turn links in Markdown from stdin into text for print.
+Usage:
+  link2print [<src-dir>]
+  link2print (-h | --help)
+Options:
+  -h --help  Show this screen.
+
+Links to other places in the book become references to their chapter and
+section, using the headings of the chapters in <src-dir> (by default, `src`).
+Links to other sites become numbered footnotes.
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src_dir: Option<String>,
+}
 
 fn main() {
-    write_md(parse_links(parse_references(read_md())));
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let src_dir = PathBuf::from(args.arg_src_dir.as_deref().unwrap_or("src"));
+
+    let read_chapter =
+        |file_name: &str| fs::read_to_string(src_dir.join(file_name)).ok();
+    write_md(parse_links(parse_references(read_md()), &read_chapter));
 }
 
 fn read_md() -> String {
@@ -42,7 +71,13 @@ fn parse_references(buffer: String) -> (String, HashMap<String, String>) {
     (output, ref_map)
 }
 
-fn parse_links((buffer, ref_map): (String, HashMap<String, String>)) -> String {
+/// Turn each link into text for print. `read_chapter` gets the Markdown of
+/// the chapter file with the given name, for links to other chapters.
+fn parse_links(
+    (buffer, ref_map): (String, HashMap<String, String>),
+    read_chapter: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    let mut footnotes: Vec<String> = vec![];
     // FIXME: check which punctuation is allowed by spec.
     let re = Regex::new(r###"(?:(?P<pre>(?:```(?:[^`]|`[^`])*`?\n```\n)|(?:[^\[]`[^`\n]+[\n]?[^`\n]*`))|(?:\[(?P<name>[^]]+)\](?:(?:\([[:blank:]]*(?P<val>[^")]*[^ ])(?:[[:blank:]]*"[^"]*")?\))|(?:\[(?P<key>[^]]*)\]))?))"###).expect("could not create regex");
     let output = re.replace_all(&buffer, |caps: &Captures<'_>| {
@@ -71,17 +106,193 @@ fn parse_links((buffer, ref_map): (String, HashMap<String, String>)) -> String {
                         }
                     }
                 };
-                format!("{name} at *{val}*")
+                if is_external(&val) {
+                    let number = match footnotes.iter().position(|url| *url == val) {
+                        Some(index) => index + 1,
+                        None => {
+                            footnotes.push(val);
+                            footnotes.len()
+                        }
+                    };
+                    format!("{name}[^{number}]")
+                } else if let Some(link) = BookLink::parse(&val) {
+                    link.to_print(name, &buffer, read_chapter)
+                } else {
+                    format!("{name} at *{val}*")
+                }
             }
         }
     });
-    output.to_string()
+
+    let mut output = output.to_string();
+    if !footnotes.is_empty() {
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push('\n');
+        for (index, url) in footnotes.iter().enumerate() {
+            output.push_str(&format!("[^{}]: {url}\n", index + 1));
+        }
+    }
+    output
+}
+
+fn is_external(url: &str) -> bool {
+    ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+/// A link to a chapter of the book, or to a section within one.
+#[derive(Debug, PartialEq)]
+struct BookLink<'a> {
+    /// The chapter’s file, like `ch17-01-futures-and-syntax.md`, or `None`
+    /// for a section in the same file as the link.
+    file_name: Option<String>,
+    /// Where the chapter is in the book, like `Chapter 17` or `Appendix E`.
+    location: Option<String>,
+    /// Whether the file is where its chapter starts, rather than one of its
+    /// sections.
+    chapter_start: bool,
+    anchor: Option<&'a str>,
+}
+
+impl<'a> BookLink<'a> {
+    fn parse(url: &'a str) -> Option<BookLink<'a>> {
+        let book_link = Regex::new(
+            r"^(?:(?:ch(?P<chapter>\d\d)-(?P<section>\d\d)|appendix-(?P<appendix>\d\d))[\w-]*\.(?:html|md))?(?:#(?P<anchor>[\w-]+))?$",
+        )
+        .unwrap();
+        let caps = book_link.captures(url)?;
+        let anchor = caps.name("anchor").map(|anchor| anchor.as_str());
+
+        // Neither the introduction nor the start of the appendix has a number
+        // or a letter of its own.
+        let (location, chapter_start) = if let Some(chapter) =
+            caps.name("chapter")
+        {
+            let number: u32 = chapter.as_str().parse().ok()?;
+            let location = (number > 0).then(|| format!("Chapter {number}"));
+            (location, &caps["section"] == "00")
+        } else if let Some(appendix) = caps.name("appendix") {
+            let number: u8 = appendix.as_str().parse().ok()?;
+            let location = (number > 0)
+                .then(|| format!("Appendix {}", char::from(b'A' + number - 1)));
+            (location, true)
+        } else if anchor.is_some() {
+            (None, false)
+        } else {
+            return None;
+        };
+
+        let file_name = url
+            .split_once('#')
+            .map_or(url, |(file_name, _)| file_name)
+            .replace(".html", ".md");
+        Some(BookLink {
+            file_name: (!file_name.is_empty()).then_some(file_name),
+            location,
+            chapter_start,
+            anchor,
+        })
+    }
+
+    /// The link as text, like “the text (see Chapter 17, “A Section”)”.
+    /// `current` is the Markdown the link is in, for links within it.
+    fn to_print(
+        &self,
+        name: &str,
+        current: &str,
+        read_chapter: &dyn Fn(&str) -> Option<String>,
+    ) -> String {
+        let markdown = match &self.file_name {
+            Some(file_name) => read_chapter(file_name).unwrap_or_else(|| {
+                panic!("could not read the chapter `{file_name}`")
+            }),
+            None => current.to_string(),
+        };
+        let headings = headings(&markdown);
+        let (index, title) = match self.anchor {
+            Some(anchor) => headings
+                .iter()
+                .position(|(id, _)| id == anchor)
+                .map(|index| (index, &headings[index].1)),
+            None => headings.first().map(|(_, title)| (0, title)),
+        }
+        .unwrap_or_else(|| {
+            panic!(
+                "could not find the section for the link to `{}#{}`",
+                self.file_name.as_deref().unwrap_or_default(),
+                self.anchor.unwrap_or_default()
+            )
+        });
+
+        let names_title = name.trim_matches(['“', '”', '"', ',', '.']) == title;
+        let is_whole_chapter = index == 0 && self.chapter_start;
+        match &self.location {
+            Some(location) if names_title || is_whole_chapter => {
+                format!("{name} (see {location})")
+            }
+            Some(location) => format!("{name} (see {location}, “{title}”)"),
+            None if names_title => name.to_string(),
+            None => format!("{name} (see “{title}”)"),
+        }
+    }
+}
+
+/// The `id` and text of each heading in `markdown`, in order, with the `id`s
+/// mdBook gives them.
+fn headings(markdown: &str) -> Vec<(String, String)> {
+    let mut headings = vec![];
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            continue;
+        }
+        let text = line.trim_start_matches('#');
+        if text.len() == line.len() || !text.starts_with(' ') {
+            continue;
+        }
+        let text = text.trim();
+        let id = text
+            .chars()
+            .filter(|c| *c != '`' && *c != '*')
+            .filter_map(|c| {
+                if c.is_alphanumeric() || c == '_' || c == '-' {
+                    Some(c.to_ascii_lowercase())
+                } else if c.is_whitespace() {
+                    Some('-')
+                } else {
+                    None
+                }
+            })
+            .collect();
+        headings.push((id, text.to_string()));
+    }
+    headings
 }
 
 #[cfg(test)]
 mod tests {
     fn parse(source: String) -> String {
-        super::parse_links(super::parse_references(source))
+        super::parse_links(super::parse_references(source), &read_chapter)
+    }
+
+    fn read_chapter(file_name: &str) -> Option<String> {
+        let markdown = match file_name {
+            "ch03-00-common-programming-concepts.md" => {
+                "# Common Programming Concepts\n\nSome text.\n"
+            }
+            "ch17-01-futures-and-syntax.md" => {
+                "## Futures and the Async Syntax\n\n```rust\n# hidden line\n```\n\n### Our First Async Program\n\n### Using `Box<T>`, Finally\n"
+            }
+            "appendix-05-editions.md" => "## Appendix E: Editions\n",
+            _ => return None,
+        };
+        Some(markdown.to_string())
     }
 
     #[test]
@@ -89,9 +300,11 @@ mod tests {
         let source =
             r"This is a [link](http://google.com) that should be expanded"
                 .to_string();
-        let target =
-            r"This is a link at *http://google.com* that should be expanded"
-                .to_string();
+        let target = r"This is a link[^1] that should be expanded
+
+[^1]: http://google.com
+"
+        .to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -100,9 +313,13 @@ mod tests {
         let source = r"This is a [link](http://google.com) that
 should appear expanded. Another [location](/here/) and [another](http://gogogo)"
             .to_string();
-        let target = r"This is a link at *http://google.com* that
-should appear expanded. Another location at */here/* and another at *http://gogogo*"
-            .to_string();
+        let target = r"This is a link[^1] that
+should appear expanded. Another location at */here/* and another[^2]
+
+[^1]: http://google.com
+[^2]: http://gogogo
+"
+        .to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -112,9 +329,12 @@ should appear expanded. Another location at */here/* and another at *http://gogo
 [theref]: http://example.com/foo
 more text"
             .to_string();
-        let target = r"This is a link at *http://example.com/foo*.
-more text"
-            .to_string();
+        let target = r"This is a link[^1].
+more text
+
+[^1]: http://example.com/foo
+"
+        .to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -168,8 +388,8 @@ more text"
         let source =
             r###"This is a titled [link](http://example.com "My title")."###
                 .to_string();
-        let target =
-            r"This is a titled link at *http://example.com*.".to_string();
+        let target = "This is a titled link[^1].\n\n[^1]: http://example.com\n"
+            .to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -177,7 +397,7 @@ more text"
     fn parses_title_with_puctuation() {
         let source =
             r###"[link](http://example.com "It's Title")"###.to_string();
-        let target = r"link at *http://example.com*".to_string();
+        let target = "link[^1]\n\n[^1]: http://example.com\n".to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -199,7 +419,8 @@ more text"
         let source = r###"[link][the ref-ref]
 [the ref-ref]:http://example.com/ref-ref"###
             .to_string();
-        let target = r###"link at *http://example.com/ref-ref*"###.to_string();
+        let target =
+            "link[^1]\n\n[^1]: http://example.com/ref-ref\n".to_string();
         assert_eq!(parse(source), target);
     }
 
@@ -291,8 +512,10 @@ version = "0.1.0"
 
 [dependencies]
 ```
-Another link at *http://gohere*
+Another link[^1]
 more text
+
+[^1]: http://gohere
 "###
         .to_string();
         assert_eq!(parse(source), target);
@@ -381,25 +604,79 @@ Some text to show that the reference links can follow later.
 [link text itself]: http://www.reddit.com"###
             .to_string();
 
-        let target = r###"I'm an inline-style link at *https://www.google.com*
+        let target = r###"I'm an inline-style link[^1]
 
-I'm an inline-style link with title at *https://www.google.com*
+I'm an inline-style link with title[^1]
 
-I'm a reference-style link at *https://www.mozilla.org*
+I'm a reference-style link[^2]
 
 I'm a relative reference to a repository file at *../blob/HEAD/LICENSE*
 
-You can use numbers for reference-style link definitions at *http://slashdot.org*
+You can use numbers for reference-style link definitions[^3]
 
-Or leave it empty and use the link text itself at *http://www.reddit.com*.
+Or leave it empty and use the link text itself[^4].
 
 URLs and URLs in angle brackets will automatically get turned into links.
 http://www.example.com or <http://www.example.com> and sometimes
 example.com (but not on Github, for example).
 
 Some text to show that the reference links can follow later.
+
+[^1]: https://www.google.com
+[^2]: https://www.mozilla.org
+[^3]: http://slashdot.org
+[^4]: http://www.reddit.com
 "###
+        .to_string();
+        assert_eq!(parse(source), target);
+    }
+
+    #[test]
+    fn parses_links_to_sections_in_other_chapters() {
+        let source = r"See [the first program][program] and
+[“Our First Async Program”][program], or [Boxes](ch17-01-futures-and-syntax.html#using-boxt-finally).
+
+[program]: ch17-01-futures-and-syntax.html#our-first-async-program"
+            .to_string();
+        let target = r"See the first program (see Chapter 17, “Our First Async Program”) and
+“Our First Async Program” (see Chapter 17), or Boxes (see Chapter 17, “Using `Box<T>`, Finally”).
+"
+        .to_string();
+        assert_eq!(parse(source), target);
+    }
+
+    #[test]
+    fn parses_links_to_whole_chapters() {
+        let source =
+            r"In [a later chapter](ch03-00-common-programming-concepts.md),
+[the first section](ch17-01-futures-and-syntax.html), and [the editions
+appendix](appendix-05-editions.html)."
+                .to_string();
+        let target = r"In a later chapter (see Chapter 3),
+the first section (see Chapter 17, “Futures and the Async Syntax”), and the editions
+appendix (see Appendix E)."
+            .to_string();
+        assert_eq!(parse(source), target);
+    }
+
+    #[test]
+    fn parses_links_within_the_same_chapter() {
+        let source = r"## Comparing the Guess
+
+Back in [“Comparing the Guess”](#comparing-the-guess) and [up there](#comparing-the-guess)."
+            .to_string();
+        let target = r"## Comparing the Guess
+
+Back in “Comparing the Guess” and up there (see “Comparing the Guess”)."
             .to_string();
         assert_eq!(parse(source), target);
     }
+
+    #[test]
+    #[should_panic]
+    fn rejects_links_to_missing_sections() {
+        parse(
+            "[Nope](ch17-01-futures-and-syntax.html#not-a-section)".to_string(),
+        );
+    }
 }
@@ -0,0 +1,265 @@
+// Runs the listings in the chapters most likely to have undefined behavior in
+// them, about unsafe code and concurrency, under Miri, so that the book's code
+// gets checked for more than whether it compiles.
+//
+// Listings which do not compile or which panic are fine, since so many of them
+// do that on purpose. A listing which Miri cannot or should not run, like one
+// which shows undefined behavior on purpose or which never exits, can opt out
+// with a `miri-ignore` file in its directory giving the reason, just like
+// `rustfmt-ignore`.
+//
+// Run this from the root of the repo, with Miri installed for the toolchain:
+// `rustup +nightly component add miri`.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+
+use docopt::Docopt;
+use rust_book_tools::listings::{self, TargetDir};
+use serde::Deserialize;
+
+const USAGE: &str = "
+Run the listings with the most potential for undefined behavior under Miri.
+Usage:
+  miri_listings [--only=<pattern>] [--toolchain=<toolchain>] [<chapter>...]
+  miri_listings (-h | --help)
+Options:
+  -h --help                Show this screen.
+  --only=<pattern>         Only run listings whose path contains <pattern>.
+  --toolchain=<toolchain>  Run Miri with this toolchain instead of `nightly`.
+";
+
+/// The chapters to run when none are given: unsafe Rust is in “Advanced
+/// Features”, and the concurrency chapters use threads and async runtimes.
+const CHAPTERS: [&str; 3] = [
+    "ch16-fearless-concurrency",
+    "ch17-async-await",
+    "ch20-advanced-features",
+];
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_chapter: Vec<String>,
+    flag_only: Option<String>,
+    flag_toolchain: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let toolchain = args.flag_toolchain.as_deref().unwrap_or("nightly");
+    let installed = Command::new("cargo")
+        .args(["miri", "--version"])
+        .env("RUSTUP_TOOLCHAIN", toolchain)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !installed {
+        return Err(format!(
+            "Miri is not installed for the '{0}' toolchain; run \
+             `rustup +{0} component add miri` first",
+            toolchain
+        )
+        .into());
+    }
+
+    let chapters = if args.arg_chapter.is_empty() {
+        CHAPTERS.iter().map(|chapter| chapter.to_string()).collect()
+    } else {
+        args.arg_chapter
+    };
+    let mut listings = vec![];
+    for chapter in &chapters {
+        let dir = Path::new("listings").join(chapter);
+        let entries = fs::read_dir(&dir)
+            .map_err(|e| format!("Could not read '{}': {e}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.join("Cargo.toml").is_file() {
+                listings.push(path);
+            }
+        }
+    }
+    listings.sort();
+    listings
+        .retain(|path| listings::is_selected(path, args.flag_only.as_deref()));
+
+    let mut tally = Tally::default();
+    let mut failures = vec![];
+    for listing in &listings {
+        if let Ok(reason) = fs::read_to_string(listing.join("miri-ignore")) {
+            println!("Skipping '{}': {}", listing.display(), reason.trim());
+            tally.skipped += 1;
+            continue;
+        }
+
+        println!("Running '{}' under Miri...", listing.display());
+        let (success, output) = run_miri(listing, toolchain)?;
+        match outcome(success, &output) {
+            Outcome::Passed => tally.passed += 1,
+            Outcome::Panicked => tally.panicked += 1,
+            Outcome::DoesNotCompile => tally.not_compiling += 1,
+            Outcome::Failed(errors) => failures.push((listing, errors)),
+        }
+    }
+
+    println!(
+        "\nRan {} listings under Miri: {} passed, {} panicked, {} did not \
+         compile, {} skipped, {} failed.",
+        listings.len(),
+        tally.passed,
+        tally.panicked,
+        tally.not_compiling,
+        tally.skipped,
+        failures.len()
+    );
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\nFix these listings, or add a `miri-ignore` file saying why not:"
+    );
+    for (listing, errors) in &failures {
+        println!("\n{}", listing.display());
+        for error in errors {
+            println!("  {error}");
+        }
+    }
+    process::exit(1);
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    passed: usize,
+    panicked: usize,
+    not_compiling: usize,
+    skipped: usize,
+}
+
+/// Run the listing's binary under Miri, or its tests if it is a library, and
+/// return whether that succeeded along with everything it printed.
+fn run_miri(
+    listing: &Path,
+    toolchain: &str,
+) -> Result<(bool, String), Box<dyn Error>> {
+    let subcommand = if listing.join("src/main.rs").is_file() {
+        "run"
+    } else {
+        "test"
+    };
+    let _target_dir = TargetDir::in_listing(listing);
+    let output = Command::new("cargo")
+        .args(["miri", subcommand, "--quiet"])
+        .current_dir(listing)
+        .env("RUSTUP_TOOLCHAIN", toolchain)
+        .env("RUSTFLAGS", "-A unused_variables -A dead_code")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            format!("Could not run Miri in '{}': {e}", listing.display())
+        })?;
+
+    let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.success(), printed))
+}
+
+/// How a run under Miri went.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Panicked,
+    DoesNotCompile,
+    /// Miri found a problem, like undefined behavior, a memory leak, or an
+    /// operation it does not support, with its error messages.
+    Failed(Vec<String>),
+}
+
+fn outcome(success: bool, output: &str) -> Outcome {
+    if success {
+        return Outcome::Passed;
+    }
+    if output.contains("error: could not compile") {
+        return Outcome::DoesNotCompile;
+    }
+
+    // Cargo's own errors just say that the program or tests failed.
+    let errors: Vec<String> = output
+        .lines()
+        .filter(|line| line.starts_with("error: "))
+        .filter(|line| {
+            !["aborting due to", "process didn't exit", "test failed"]
+                .iter()
+                .any(|cargo| line["error: ".len()..].starts_with(cargo))
+        })
+        .map(String::from)
+        .collect();
+    if !errors.is_empty() {
+        Outcome::Failed(errors)
+    } else if output.contains("panicked at") {
+        Outcome::Panicked
+    } else {
+        Outcome::Failed(vec![String::from("exited unsuccessfully")])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undefined_behavior_fails() {
+        let output = "\
+error: Undefined Behavior: pointer not dereferenceable: pointer must point to some allocation, but got 0x1234[noalloc] which is a dangling pointer (it has no provenance)
+ --> src/main.rs:7:35
+  |
+7 |     let values: &[i32] = unsafe { slice::from_raw_parts_mut(r, 10000) };
+  |                                   ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ Undefined Behavior occurred here
+
+note: some details are omitted, run with `MIRIFLAGS=-Zmiri-backtrace=full` for a verbose backtrace
+
+error: aborting due to 1 previous error; 1 warning emitted
+
+error: process didn't exit successfully: `cargo-miri runner target/miri/debug/unsafe-example` (exit status: 1)
+";
+        assert_eq!(
+            outcome(false, output),
+            Outcome::Failed(vec![String::from(
+                "error: Undefined Behavior: pointer not dereferenceable: \
+                 pointer must point to some allocation, but got \
+                 0x1234[noalloc] which is a dangling pointer (it has no \
+                 provenance)"
+            )])
+        );
+    }
+
+    #[test]
+    fn intentional_failures_are_fine() {
+        assert_eq!(outcome(true, ""), Outcome::Passed);
+        assert_eq!(
+            outcome(
+                false,
+                "error[E0382]: borrow of moved value: `v`\n\
+                 error: could not compile `threads` (bin \"threads\") due to \
+                 1 previous error\n"
+            ),
+            Outcome::DoesNotCompile
+        );
+        assert_eq!(
+            outcome(
+                false,
+                "thread 'main' panicked at src/main.rs:4:6:\n\
+                 index out of bounds: the len is 1 but the index is 1\n\
+                 error: process didn't exit successfully: `cargo-miri runner \
+                 target/miri/debug/async-await` (exit status: 101)\n"
+            ),
+            Outcome::Panicked
+        );
+    }
+}
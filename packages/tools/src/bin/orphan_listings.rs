@@ -0,0 +1,294 @@
+// Finds listing packages which no chapter uses any more, and places where the
+// chapters use listing files which do not exist.
+//
+// A listing counts as used when any Markdown file in the source directory
+// points into it, with `{{#include}}`, `{{#rustdoc_include}}`, or the `path`
+// of a `<Listing>`, or when a `manual-regeneration` comment mentions it (with a
+// path from the root of the repo, like `cd listings/ch14-more-about-cargo/…`).
+//
+// A listing which should stay even though no chapter uses it, for example
+// because translations of the book still do, can say so with an
+// `orphan-ignore` file in its directory giving the reason, just like
+// `rustfmt-ignore`. Once a chapter uses the listing again, the file has to go.
+//
+// Run this from the root of the repo.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+const USAGE: &str = "
+Find listings no chapter uses, and uses of listing files which do not exist.
+Usage:
+  orphan_listings [<src-dir>]
+  orphan_listings (-h | --help)
+Options:
+  -h --help  Show this screen.
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src_dir: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let src_dir = PathBuf::from(args.arg_src_dir.as_deref().unwrap_or("src"));
+    let listings_dir = Path::new("listings");
+
+    let mut used = BTreeSet::new();
+    let mut problems = 0;
+    for entry in walkdir::WalkDir::new(&src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(&src_dir);
+        for reference in references(&contents) {
+            let base = if reference.from_root {
+                Path::new("")
+            } else {
+                dir
+            };
+            let target = normalize(&base.join(&reference.path));
+            let Some(listing) = listing_of(&target, listings_dir) else {
+                continue;
+            };
+            if !target.exists() {
+                problems += 1;
+                eprintln!(
+                    "{}:{}: '{}' does not exist",
+                    path.display(),
+                    reference.line,
+                    reference.path
+                );
+            }
+            used.insert(listing);
+        }
+    }
+
+    let mut listings = 0;
+    let mut kept = 0;
+    for chapter in sorted_dirs(listings_dir)? {
+        for listing in sorted_dirs(&chapter)? {
+            listings += 1;
+            let ignored = listing.join("orphan-ignore").is_file();
+            match (used.contains(&listing), ignored) {
+                (true, false) => {}
+                (false, true) => kept += 1,
+                (false, false) => {
+                    problems += 1;
+                    eprintln!(
+                        "'{}' is not used by any chapter",
+                        listing.display()
+                    );
+                }
+                (true, true) => {
+                    problems += 1;
+                    eprintln!(
+                        "'{}' is used by a chapter, so it does not need its \
+                         `orphan-ignore` file",
+                        listing.display()
+                    );
+                }
+            }
+        }
+    }
+
+    println!(
+        "Checked {listings} listings ({kept} unused but kept), found \
+         {problems} problems"
+    );
+    if problems > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// A path to a file which a chapter uses.
+#[derive(Debug, PartialEq, Eq)]
+struct Reference {
+    /// The 1-based line it is on.
+    line: usize,
+    /// The path as written.
+    path: String,
+    /// Whether the path is from the root of the repo, as in comments, rather
+    /// than from the chapter.
+    from_root: bool,
+}
+
+lazy_static! {
+    static ref INCLUDE: Regex =
+        Regex::new(r"\{\{#(?:rustdoc_)?include\s+([^\s:}]+)").unwrap();
+    static ref LISTING_PATH: Regex =
+        Regex::new(r#"<Listing\b[^>]*\spath="([^"]+)""#).unwrap();
+    static ref ROOT_PATH: Regex =
+        Regex::new(r"(?:^|[\s(])(listings/[^\s)`]+)").unwrap();
+}
+
+/// Every file `markdown` includes, points a `<Listing>` at, or mentions in a
+/// comment, in order.
+fn references(markdown: &str) -> Vec<Reference> {
+    let mut references = vec![];
+    let mut in_comment = false;
+    for (index, line) in markdown.lines().enumerate() {
+        if line.contains("<!--") {
+            in_comment = true;
+        }
+
+        for captures in INCLUDE
+            .captures_iter(line)
+            .chain(LISTING_PATH.captures_iter(line))
+        {
+            references.push(Reference {
+                line: index + 1,
+                path: captures[1].to_string(),
+                from_root: false,
+            });
+        }
+        if in_comment {
+            for captures in ROOT_PATH.captures_iter(line) {
+                references.push(Reference {
+                    line: index + 1,
+                    path: captures[1].to_string(),
+                    from_root: true,
+                });
+            }
+        }
+
+        if line.contains("-->") {
+            in_comment = false;
+        }
+    }
+    references
+}
+
+/// Resolve the `.` and `..` in `path` without touching the file system, since
+/// the file may not exist.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// The listing `path` is in, like `listings/ch01-getting-started/listing-01-01`
+/// for anything within that directory.
+fn listing_of(path: &Path, listings_dir: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(listings_dir).ok()?;
+    let mut components = rest.components();
+    let chapter = components.next()?;
+    let listing = components.next()?;
+    Some(listings_dir.join(chapter).join(listing))
+}
+
+/// The directories directly inside `dir`, sorted by name.
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut dirs = vec![];
+    for entry in fs::read_dir(dir)
+        .map_err(|e| format!("Could not read '{}': {e}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_includes_and_listing_paths() {
+        let markdown = r#"Some text.
+
+<Listing number="1-1" path="../listings/ch01/listing-01-01/src/main.rs" caption="Hi">
+
+```rust
+{{#rustdoc_include ../listings/ch01/listing-01-01/src/main.rs:here}}
+```
+
+</Listing>
+
+```console
+{{#include ../listings/ch01/output-only-01/output.txt}}
+```
+
+<!-- manual-regeneration
+cd listings/ch01/output-only-02/add
+cargo run
+-->
+
+Not a comment: listings/ch01/no-listing-01
+"#;
+
+        assert_eq!(
+            references(markdown),
+            vec![
+                Reference {
+                    line: 3,
+                    path: "../listings/ch01/listing-01-01/src/main.rs".into(),
+                    from_root: false,
+                },
+                Reference {
+                    line: 6,
+                    path: "../listings/ch01/listing-01-01/src/main.rs".into(),
+                    from_root: false,
+                },
+                Reference {
+                    line: 12,
+                    path: "../listings/ch01/output-only-01/output.txt".into(),
+                    from_root: false,
+                },
+                Reference {
+                    line: 16,
+                    path: "listings/ch01/output-only-02/add".into(),
+                    from_root: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn files_belong_to_their_top_level_listing() {
+        let listings = Path::new("listings");
+        let file = normalize(Path::new(
+            "src/../listings/ch14/listing-14-07/add/adder/src/main.rs",
+        ));
+        assert_eq!(
+            listing_of(&file, listings),
+            Some(PathBuf::from("listings/ch14/listing-14-07"))
+        );
+        assert_eq!(listing_of(Path::new("src/ch01.md"), listings), None);
+        assert_eq!(listing_of(Path::new("listings/ch01"), listings), None);
+    }
+}
@@ -7,6 +7,7 @@ use std::path::{Path, PathBuf};
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use zip_writer::{Method, Writer};
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Get all listings from the `listings` directory
@@ -94,13 +95,229 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut archive = tar::Builder::new(encoder);
     archive.append_dir_all("listings", "tmp/listings")?;
 
+    // Create a zip of each chapter's listings too, for readers who want the
+    // code for just one chapter
+    let chapters_dir = Path::new("tmp/chapters");
+    if chapters_dir.is_dir() {
+        fs::remove_dir_all(chapters_dir)?;
+    }
+    fs::create_dir(chapters_dir)?;
+    for chapter in sorted_dirs(out_dir)? {
+        let chapter_name = chapter
+            .file_name()
+            .expect("Chapter should've had a name")
+            .to_string_lossy()
+            .into_owned();
+        let zip_path = chapters_dir.join(format!("{chapter_name}.zip"));
+        write_chapter_archive(&chapter, &chapter_name, &zip_path)?;
+    }
+
     // Assure whoever is running this that the script exiting successfully, and remind them
-    // where the generated file ends up
+    // where the generated files end up
     println!("Release tarball of listings in tmp/listings.tar.gz");
+    println!("Archives of each chapter's listings in tmp/chapters");
+
+    Ok(())
+}
+
+// Each chapter's archive is a workspace with every listing in the chapter as a
+// member, so readers can run any of them from one place. Listings in the same
+// chapter often share a package name, which a workspace does not allow, so
+// each package is renamed after its listing's directory. The listings which use
+// `trpl` get a copy of it in the archive to depend on.
+fn write_chapter_archive(
+    chapter: &Path,
+    chapter_name: &str,
+    zip_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut members = vec![];
+    let mut excluded = vec![];
+    let mut manifests = vec![];
+    let mut uses_trpl = false;
+    for listing in sorted_dirs(chapter)? {
+        let listing_name = listing
+            .file_name()
+            .expect("Listing should've had a name")
+            .to_string_lossy()
+            .into_owned();
+        let manifest_path = listing.join("Cargo.toml");
+        // Listings which are workspaces of their own, or hold more than one
+        // package, have to stay out of the chapter's workspace.
+        let manifest = fs::read_to_string(&manifest_path).unwrap_or_default();
+        if !manifest.contains("[package]") || manifest.contains("[workspace]") {
+            excluded.push(listing_name);
+            continue;
+        }
+        uses_trpl |= TRPL_DEPENDENCY.is_match(&manifest);
+        let has_lib = listing.join("src/lib.rs").is_file();
+        manifests.push((
+            manifest_path,
+            member_manifest(&manifest, &listing_name, has_lib),
+        ));
+        members.push(listing_name);
+    }
+
+    if uses_trpl {
+        excluded.push(String::from("trpl"));
+    }
+
+    let mut archive = Writer::new(BufWriter::new(File::create(zip_path)?));
+    archive.add(
+        &format!("{chapter_name}/Cargo.toml"),
+        workspace_manifest(chapter_name, &members, &excluded).as_bytes(),
+        Method::Deflated,
+    )?;
+
+    // Start the workspace from the versions the listings were checked with.
+    if let Some(lock) = members
+        .iter()
+        .map(|member| chapter.join(member).join("Cargo.lock"))
+        .find(|lock| lock.is_file())
+    {
+        archive.add(
+            &format!("{chapter_name}/Cargo.lock"),
+            &fs::read(lock)?,
+            Method::Deflated,
+        )?;
+    }
+
+    for entry in walkdir::WalkDir::new(chapter).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let name = zip_name(chapter_name, path.strip_prefix(chapter)?);
+        match manifests
+            .iter()
+            .find(|(manifest_path, _)| manifest_path == path)
+        {
+            Some((_, manifest)) => {
+                archive.add(&name, manifest.as_bytes(), Method::Deflated)?
+            }
+            None => archive.add(&name, &fs::read(path)?, Method::Deflated)?,
+        }
+    }
+
+    if uses_trpl {
+        let trpl = Path::new("packages/trpl");
+        for entry in walkdir::WalkDir::new(trpl)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != "target")
+        {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let path = entry.path();
+                let name = zip_name(
+                    chapter_name,
+                    &Path::new("trpl").join(path.strip_prefix(trpl)?),
+                );
+                archive.add(&name, &fs::read(path)?, Method::Deflated)?;
+            }
+        }
+    }
 
+    archive.finish()?;
     Ok(())
 }
 
+lazy_static! {
+    static ref PACKAGE_NAME: Regex =
+        Regex::new(r#"(?m)^name = "([^"]+)""#).unwrap();
+    static ref TRPL_DEPENDENCY: Regex =
+        Regex::new(r#"trpl = \{ path = "(?:\.\./)+packages/trpl" \}"#).unwrap();
+}
+
+// A listing's manifest as a member of its chapter's workspace: named after
+// the listing, but keeping its old name for its library, if it has one, so the
+// code which uses the library still works.
+fn member_manifest(
+    manifest: &str,
+    listing_name: &str,
+    has_lib: bool,
+) -> String {
+    let old_name = PACKAGE_NAME
+        .captures(manifest)
+        .map(|captures| captures[1].to_string());
+    let mut manifest = PACKAGE_NAME
+        .replace(manifest, format!(r#"name = "{listing_name}""#))
+        .into_owned();
+    manifest = TRPL_DEPENDENCY
+        .replace(&manifest, r#"trpl = { path = "../trpl" }"#)
+        .into_owned();
+    if let Some(old_name) = old_name
+        && has_lib
+        && old_name != listing_name
+        && !manifest.contains("[lib]")
+    {
+        if !manifest.ends_with('\n') {
+            manifest.push('\n');
+        }
+        manifest.push_str(&format!(
+            "\n[lib]\nname = \"{}\"\n",
+            old_name.replace('-', "_")
+        ));
+    }
+    manifest
+}
+
+fn workspace_manifest(
+    chapter_name: &str,
+    members: &[String],
+    excluded: &[String],
+) -> String {
+    let list = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| format!("    \"{name}\",\n"))
+            .collect::<String>()
+    };
+    format!(
+        "# The listings from `{chapter_name}` in The Rust Programming Language.
+#
+# Each listing is a package named after its directory, so run one with, for
+# example, `cargo run -p {example}`. Some listings do not compile on purpose,
+# so build them one at a time rather than all at once.
+[workspace]
+resolver = \"3\"
+members = [
+{members}]
+exclude = [
+{excluded}]
+",
+        example = members.first().map(String::as_str).unwrap_or("listing"),
+        members = list(members),
+        excluded = list(excluded),
+    )
+}
+
+// The name of a file in a chapter's archive, with `/` separators whatever the
+// platform uses.
+fn zip_name(chapter_name: &str, relative: &Path) -> String {
+    let mut name = String::from(chapter_name);
+    for component in relative.components() {
+        name.push('/');
+        name.push_str(&component.as_os_str().to_string_lossy());
+    }
+    name
+}
+
+// The directories directly inside `dir`, sorted by name.
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut dirs = vec![];
+    for entry in fs::read_dir(dir)
+        .map_err(|e| format!("Could not read '{}': {e}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
 // Cleaned listings will not contain:
 //
 // - `target` directories
@@ -108,6 +325,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 // - `rustfmt-ignore` files used to signal to update-rustc.sh the listing shouldn't be formatted
 // - anchor comments or snip comments
 // - empty `main` functions in `lib.rs` files used to trick rustdoc
+// - the `// hidden` markers on lines the book hides
 fn copy_cleaned_listing_files(
     from: PathBuf,
     to: PathBuf,
@@ -179,10 +397,15 @@ lazy_static! {
     static ref EMPTY_MAIN: Regex = Regex::new(r"fn main\(\) \{}").unwrap();
 }
 
+lazy_static! {
+    static ref HIDDEN_MARKER: Regex = Regex::new(r"\s*// hidden$").unwrap();
+}
+
 // Cleaned Rust files will not contain:
 //
 // - anchor comments or snip comments
 // - empty `main` functions in `lib.rs` files used to trick rustdoc
+// - the `// hidden` markers on lines the book hides
 fn copy_cleaned_rust_file(
     item_name: &std::ffi::OsStr,
     from: &PathBuf,
@@ -196,6 +419,7 @@ fn copy_cleaned_rust_file(
         if !ANCHOR_OR_SNIP_COMMENTS.is_match(&line)
             && (item_name != "lib.rs" || !EMPTY_MAIN.is_match(&line))
         {
+            let line = HIDDEN_MARKER.replace(&line, "");
             writeln!(&mut to_buf, "{line}")?;
         }
     }
@@ -204,3 +428,40 @@ fn copy_cleaned_rust_file(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_members_and_keeps_their_library_names() {
+        let manifest = r#"[package]
+name = "traits-example"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+trpl = { path = "../../../packages/trpl" }
+"#;
+        assert_eq!(
+            member_manifest(manifest, "listing-10-13", true),
+            r#"[package]
+name = "listing-10-13"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+trpl = { path = "../trpl" }
+
+[lib]
+name = "traits_example"
+"#
+        );
+        assert_eq!(
+            member_manifest(manifest, "listing-10-14", false),
+            manifest
+                .replace("traits-example", "listing-10-14")
+                .replace("../../../packages/trpl", "../trpl")
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use std::io;
 use std::io::prelude::*;
 
+use rust_book_tools::split_block_quote;
+
 fn main() {
     write_md(remove_hidden_lines(&read_md()));
 }
@@ -22,11 +24,13 @@ fn remove_hidden_lines(input: &str) -> String {
     let mut within_codeblock = false;
 
     for line in input.lines() {
-        if line.starts_with("```") {
+        // Code blocks in asides and other block quotes hide lines, too.
+        let (_, code) = split_block_quote(line);
+        if code.starts_with("```") {
             within_codeblock = !within_codeblock;
         }
 
-        if !within_codeblock || (!line.starts_with("# ") && line != "#") {
+        if !within_codeblock || (!code.starts_with("# ") && code != "#") {
             resulting_lines.push(line)
         }
     }
@@ -80,4 +84,13 @@ you can see that...
 
         assert_eq!(output, desired_output);
     }
+
+    #[test]
+    fn hidden_line_in_block_quote_is_removed() {
+        let input =
+            "> ```\n> # use std::io;\n> #\n> fn main() {}\n> ```\n> # Heading";
+        let output = remove_hidden_lines(input);
+
+        assert_eq!(output, "> ```\n> fn main() {}\n> ```\n> # Heading");
+    }
 }
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::io;
 use std::io::Read;
 
 use regex::{Captures, Regex};
+use rust_book_tools::split_block_quote;
 
 fn main() {
     write_md(remove_markup(read_md()));
@@ -28,12 +30,41 @@ fn remove_markup(input: String) -> String {
     let caption_end_regex = Regex::new(r#"(.*)</span>\z"#).unwrap();
     let regexen = [filename_regex, caption_start_regex, caption_end_regex];
 
+    // What to put in place of the `</Listing>` for the listing we are in.
+    let mut listing_closing = None;
     let lines: Vec<_> = input
         .lines()
         .flat_map(|line| {
-            // Remove our syntax highlighting and rustdoc markers.
-            if line.starts_with("```") {
-                Some(String::from("```"))
+            // Remove our syntax highlighting and rustdoc markers, including in
+            // code blocks inside asides and other block quotes. The Ferris
+            // markers go to the publisher as the same art the HTML version
+            // shows next to the code.
+            let (quote, rest) = split_block_quote(line);
+            if let Some(info) = rest.strip_prefix("```") {
+                let fence = format!("{quote}```");
+                match ferris(info) {
+                    Some(img) => vec![
+                        format!("{quote}{img}"),
+                        quote.trim_end().to_string(),
+                        fence,
+                    ],
+                    None => vec![fence],
+                }
+            // Turn any `<Listing>` the preprocessors have not already handled
+            // into the same text `trpl-listing` would, and drop the `<Note>`
+            // tags around their contents.
+            } else if rest.starts_with("<Listing") && rest.ends_with('>') {
+                let attributes = attributes(rest);
+                listing_closing = Some(listing_caption(&attributes));
+                let file_name = attributes
+                    .get("file-name")
+                    .map(|file_name| format!("Filename: {file_name}"));
+                vec![quoted(quote, file_name.as_deref().unwrap_or_default())]
+            } else if rest == "</Listing>" {
+                let caption = listing_closing.take().flatten();
+                vec![quoted(quote, caption.as_deref().unwrap_or_default())]
+            } else if is_note_tag(rest) {
+                vec![quote.trim_end().to_string()]
             // Remove the span around filenames and captions.
             } else {
                 let result =
@@ -44,9 +75,180 @@ fn remove_markup(input: String) -> String {
                             })
                             .to_string()
                     });
-                Some(result)
+                vec![result]
             }
         })
         .collect();
     lines.join("\n")
 }
+
+/// `text` inside the block quotes marked by `quote`, without any trailing
+/// spaces when `text` is empty.
+fn quoted(quote: &str, text: &str) -> String {
+    if text.is_empty() {
+        quote.trim_end().to_string()
+    } else {
+        format!("{quote}{text}")
+    }
+}
+
+/// The `key="value"` attributes on a tag like `<Listing>`.
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let attribute = Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap();
+    attribute
+        .captures_iter(tag)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+/// The “Listing 1-2: …” text which goes after a listing, if it has a number
+/// or a caption. Listings which only have a `path` need the `trpl-listing`
+/// preprocessor to work out their number.
+fn listing_caption(attributes: &HashMap<String, String>) -> Option<String> {
+    match (attributes.get("number"), attributes.get("caption")) {
+        (Some(number), Some(caption)) => {
+            Some(format!("Listing {number}: {caption}"))
+        }
+        (None, Some(caption)) => Some(caption.clone()),
+        (Some(number), None) => Some(format!("Listing {number}")),
+        (None, None) => None,
+    }
+}
+
+fn is_note_tag(text: &str) -> bool {
+    text == "</Note>"
+        || text.strip_prefix("<Note").is_some_and(|rest| {
+            rest.ends_with('>') && rest.starts_with(['>', ' '])
+        })
+}
+
+/// The Ferris image for a code block with the `info` string, if it has one of
+/// the annotations `ferris.js` looks for.
+fn ferris(info: &str) -> Option<String> {
+    const FERRIS_TYPES: [(&str, &str); 3] = [
+        ("does_not_compile", "This code does not compile!"),
+        ("panics", "This code panics!"),
+        (
+            "not_desired_behavior",
+            "This code does not produce the desired behavior.",
+        ),
+    ];
+
+    let annotations: Vec<_> = info
+        .trim_start_matches('`')
+        .split(',')
+        .map(str::trim)
+        .collect();
+    FERRIS_TYPES
+        .iter()
+        .find(|(attr, _)| annotations.contains(attr))
+        .map(|(attr, title)| {
+            format!(
+                r#"<img src="img/ferris/{attr}.svg" class="ferris" alt="{title}" />"#
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_code_block_markers() {
+        let input = "```rust,ignore\nfn main() {}\n```".to_string();
+        assert_eq!(remove_markup(input), "```\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn marks_ferris_code_blocks() {
+        let input =
+            "````rust,ignore,does_not_compile\nlet x;\n````".to_string();
+        assert_eq!(
+            remove_markup(input),
+            r#"<img src="img/ferris/does_not_compile.svg" class="ferris" alt="This code does not compile!" />
+
+```
+let x;
+```"#
+        );
+    }
+
+    #[test]
+    fn converts_listings_and_notes() {
+        let input = r#"<Listing number="1-2" file-name="src/main.rs" caption="Some *text*, “yeah”?">
+
+```rust
+fn main() {}
+```
+
+</Listing>
+
+<Listing>
+
+```text
+no caption
+```
+
+</Listing>
+
+<Note kind="tip">
+
+Notes keep their contents.
+
+</Note>
+
+<Notebook>"#
+            .to_string();
+        assert_eq!(
+            remove_markup(input),
+            r#"Filename: src/main.rs
+
+```
+fn main() {}
+```
+
+Listing 1-2: Some *text*, “yeah”?
+
+
+
+```
+no caption
+```
+
+
+
+
+
+Notes keep their contents.
+
+
+
+<Notebook>"#
+        );
+    }
+
+    #[test]
+    fn converts_listings_in_block_quotes() {
+        let input = "> <Listing caption=\"Quoted\">\n>\n> ```rust\n> x\n> ```\n>\n> </Listing>".to_string();
+        assert_eq!(
+            remove_markup(input),
+            ">\n>\n> ```\n> x\n> ```\n>\n> Quoted"
+        );
+    }
+
+    #[test]
+    fn handles_code_blocks_in_block_quotes() {
+        let input =
+            "> Aside:\n>\n> ```rust,panics\n> panic!();\n> ```".to_string();
+        assert_eq!(
+            remove_markup(input),
+            r#"> Aside:
+>
+> <img src="img/ferris/panics.svg" class="ferris" alt="This code panics!" />
+>
+> ```
+> panic!();
+> ```"#
+        );
+    }
+}
@@ -0,0 +1,390 @@
+// Regenerates the `output.txt` of every listing which has one, by running the
+// command on its first line in the copy of the listing in `tmp/listings` (made
+// by `cargo run --bin release_listings`, so line numbers match what readers
+// see), and then reports which chapters show output which changed, so their
+// prose can be checked against it.
+//
+// The output is normalized so that it does not depend on who ran the update or
+// when: paths to the listing and to the toolchain are replaced with stand-ins,
+// and the build and test times and test binary hashes from the previous output
+// are kept to minimize churn in the diff.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use docopt::Docopt;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use rust_book_tools::listings::{self, TargetDir};
+use serde::Deserialize;
+
+const USAGE: &str = "
+Regenerate the output.txt files of the listings.
+Usage:
+  update_output [--only=<pattern>] [--toolchain=<toolchain>] [<src-dir>]
+  update_output (-h | --help)
+Options:
+  -h --help                Show this screen.
+  --only=<pattern>         Only update listings whose path contains <pattern>.
+  --toolchain=<toolchain>  Build with this toolchain, like `stable`, instead of
+                           the one `rust-toolchain` picks.
+";
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_src_dir: Option<String>,
+    flag_only: Option<String>,
+    flag_toolchain: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    let src_dir = PathBuf::from(args.arg_src_dir.as_deref().unwrap_or("src"));
+    let tmp_dir = Path::new("tmp");
+    if !tmp_dir.join("listings").is_dir() {
+        return Err("No listings in 'tmp/listings'; run \
+                    `cargo run --bin release_listings` first"
+            .into());
+    }
+
+    let mut output_files = vec![];
+    for entry in walkdir::WalkDir::new("listings").sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_name() == "output.txt" {
+            output_files.push(entry.into_path());
+        }
+    }
+    output_files
+        .retain(|path| listings::is_selected(path, args.flag_only.as_deref()));
+
+    let mut changes = BTreeMap::new();
+    for path in &output_files {
+        println!("Regenerating '{}'...", path.display());
+        let previous = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        let listing_dir = tmp_dir.join(path.parent().unwrap());
+        let updated = regenerate(
+            &previous,
+            &listing_dir,
+            args.flag_toolchain.as_deref(),
+        )?;
+        if updated != previous {
+            fs::write(path, &updated).map_err(|e| {
+                format!("Could not write '{}': {e}", path.display())
+            })?;
+            changes.insert(path.clone(), (previous, updated));
+        }
+    }
+
+    println!(
+        "\nUpdated {} of {} output files.",
+        changes.len(),
+        output_files.len()
+    );
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    // Group the changes by the chapters showing them, since the text around
+    // the output is what needs checking.
+    let shown_in = chapters_showing(&src_dir)?;
+    let mut by_chapter: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+    for path in changes.keys() {
+        match shown_in.get(path) {
+            Some(chapters) => {
+                for chapter in chapters {
+                    by_chapter
+                        .entry(chapter.display().to_string())
+                        .or_default()
+                        .push(path);
+                }
+            }
+            None => by_chapter
+                .entry(String::from("(not shown in the book)"))
+                .or_default()
+                .push(path),
+        }
+    }
+
+    println!("Check the text around the changed output in these chapters:");
+    for (chapter, paths) in by_chapter {
+        println!("\n{chapter}");
+        for path in paths {
+            println!("  {}", path.display());
+            let (previous, updated) = &changes[path];
+            for line in changed_lines(previous, updated) {
+                println!("    {line}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the command at the top of the `previous` output in `listing_dir`, and
+/// produce the new output.
+fn regenerate(
+    previous: &str,
+    listing_dir: &Path,
+    toolchain: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    let command = previous
+        .lines()
+        .find_map(|line| line.strip_prefix("$ "))
+        .ok_or("No `$ ` command in the output")?;
+
+    // Act like this is the first time this listing has been built.
+    let _target_dir = TargetDir::in_listing(listing_dir);
+
+    // Turn some warnings off to reduce output noise, and use one test thread
+    // to get consistent ordering of tests in the output when the command is
+    // `cargo test`. Many listings fail on purpose, so the exit status is not
+    // interesting; only the output is.
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(format!("{command} 2>&1"))
+        .current_dir(listing_dir)
+        .env("RUSTFLAGS", "-A unused_variables -A dead_code")
+        .env("RUST_TEST_THREADS", "1")
+        // The book shows the default panic message, not a backtrace.
+        .env_remove("RUST_BACKTRACE")
+        .stdin(Stdio::null());
+    if let Some(toolchain) = toolchain {
+        shell.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+    let output = shell.output().map_err(|e| {
+        format!(
+            "Could not run `{command}` in '{}': {e}",
+            listing_dir.display()
+        )
+    })?;
+
+    let printed = String::from_utf8_lossy(&output.stdout);
+    Ok(format!("$ {command}\n{}", normalize(&printed, previous)))
+}
+
+lazy_static! {
+    static ref PROJECT_PATH: Regex =
+        Regex::new(r"(Compiling|Checking) (\S+) v0\.1\.0 .*").unwrap();
+    static ref TOOLCHAIN_PATH: Regex = Regex::new(
+        r"(?m)^(\s*-->\s+).*(\.rustup/toolchains/(?:\d+\.\d+|stable|beta|nightly))[^/]*"
+    )
+    .unwrap();
+    static ref MIRI_PATH: Regex = Regex::new(
+        r"Running `.*\.rustup/toolchains/nightly[^/]*/bin/cargo-miri runner target/miri/[^/]*/debug/"
+    )
+    .unwrap();
+    static ref COMPILE_TIME: Regex = Regex::new(
+        r"Finished `(dev|test)` profile \[unoptimized \+ debuginfo\] target\(s\) in ([0-9.]+)"
+    )
+    .unwrap();
+    static ref TEST_TIME: Regex =
+        Regex::new(r"; finished in ([0-9.]+)s").unwrap();
+    static ref TEST_BINARY: Regex = Regex::new(
+        r"Running (\S+)( [^\s()]+)? \(target/debug/deps/([^-\s]*)-([0-9a-f]+)\)"
+    )
+    .unwrap();
+}
+
+/// Replace everything in freshly generated `output` which depends on the
+/// machine or the moment it ran on, keeping the build and test times and test
+/// binary hash from the `previous` output, if it had them.
+fn normalize(output: &str, previous: &str) -> String {
+    let output =
+        PROJECT_PATH.replace_all(output, "$1 $2 v0.1.0 (file:///projects/$2)");
+
+    // Use a "default" installation directory for rustup's install location,
+    // and just the version or channel instead of it and the architecture
+    // triple, so e.g. `1.82-aarch64-apple-darwin` becomes `1.82`, and
+    // `stable-x86_64-unknown-linux-gnu` becomes `stable`.
+    let output = TOOLCHAIN_PATH.replace_all(&output, "${1}file:///home/$2");
+    let output = MIRI_PATH.replace_all(
+        &output,
+        "Running `file:///home/.rustup/toolchains/nightly/bin/cargo-miri runner target/miri/debug/",
+    );
+
+    let output = match COMPILE_TIME.captures(previous) {
+        Some(previous) => {
+            COMPILE_TIME.replace_all(&output, |captures: &Captures| {
+                format!(
+                    "Finished `{}` profile [unoptimized + debuginfo] target(s) in {}",
+                    &captures[1], &previous[2]
+                )
+            })
+        }
+        None => output,
+    };
+
+    // Each test run reports how long it took, so keep those in order.
+    let previous_test_times: Vec<&str> = TEST_TIME
+        .captures_iter(previous)
+        .map(|captures| captures.get(1).unwrap().as_str())
+        .collect();
+    let mut run = 0;
+    let output = TEST_TIME.replace_all(&output, |captures: &Captures| {
+        let time = previous_test_times
+            .get(run)
+            .copied()
+            .unwrap_or(&captures[1]);
+        run += 1;
+        format!("; finished in {time}s")
+    });
+
+    match TEST_BINARY.captures(previous) {
+        Some(previous) => TEST_BINARY
+            .replace_all(&output, |captures: &Captures| {
+                format!(
+                    "Running {}{} (target/debug/deps/{}-{})",
+                    &captures[1],
+                    captures.get(2).map_or("", |m| m.as_str()),
+                    &captures[3],
+                    &previous[4]
+                )
+            })
+            .into_owned(),
+        None => output.into_owned(),
+    }
+}
+
+lazy_static! {
+    static ref INCLUDE: Regex =
+        Regex::new(r"\{\{#include\s+([^\s:}]+/output\.txt)").unwrap();
+}
+
+/// The chapters which include each `output.txt`, by its path from the root of
+/// the repository.
+fn chapters_showing(
+    src_dir: &Path,
+) -> Result<BTreeMap<PathBuf, Vec<PathBuf>>, Box<dyn Error>> {
+    let cwd = fs::canonicalize(".")?;
+    let mut shown_in: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(src_dir);
+        for captures in INCLUDE.captures_iter(&contents) {
+            let Ok(included) = fs::canonicalize(dir.join(&captures[1])) else {
+                continue;
+            };
+            let included = included
+                .strip_prefix(&cwd)
+                .unwrap_or(&included)
+                .to_path_buf();
+            let chapters = shown_in.entry(included).or_default();
+            if !chapters.iter().any(|chapter| chapter == path) {
+                chapters.push(path.to_path_buf());
+            }
+        }
+    }
+    Ok(shown_in)
+}
+
+/// The lines which differ between `previous` and `updated`, marked with `-`
+/// and `+` as in a diff, but without the unchanged lines around them.
+fn changed_lines(previous: &str, updated: &str) -> Vec<String> {
+    line_diff::lines(previous, updated)
+        .into_iter()
+        .filter_map(|line| match line {
+            line_diff::Line::Same(_) => None,
+            line_diff::Line::Removed(text) => Some(format!("- {text}")),
+            line_diff::Line::Added(text) => Some(format!("+ {text}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_paths_and_keeps_previous_timings() {
+        let previous = "$ cargo test
+   Compiling adder v0.1.0 (file:///projects/adder)
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.57s
+     Running unittests src/lib.rs (target/debug/deps/adder-92948b65e88960b4)
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        let output = "   Compiling adder v0.1.0 (/Users/someone/book/tmp/listings/ch11/listing-11-01)
+warning: unused import
+ --> /Users/someone/.rustup/toolchains/1.90-aarch64-apple-darwin/lib/rustlib/src/rust/library/core/src/lib.rs:1:1
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 3.21s
+     Running unittests src/lib.rs (target/debug/deps/adder-0123456789abcdef)
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+   Doc-tests adder
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.02s
+";
+
+        assert_eq!(
+            normalize(output, previous),
+            "   Compiling adder v0.1.0 (file:///projects/adder)
+warning: unused import
+ --> file:///home/.rustup/toolchains/1.90/lib/rustlib/src/rust/library/core/src/lib.rs:1:1
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.57s
+     Running unittests src/lib.rs (target/debug/deps/adder-92948b65e88960b4)
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+   Doc-tests adder
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.02s
+"
+        );
+    }
+
+    #[test]
+    fn new_output_keeps_its_own_timings() {
+        let output = "    Finished `dev` profile [unoptimized + debuginfo] target(s) in 1.00s\n";
+        assert_eq!(normalize(output, "$ cargo run\n"), output);
+    }
+
+    #[test]
+    fn named_toolchain_paths_are_normalized() {
+        let output = " --> /home/someone/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/core/src/lib.rs:1:1\n";
+        assert_eq!(
+            normalize(output, ""),
+            " --> file:///home/.rustup/toolchains/stable/lib/rustlib/src/rust/library/core/src/lib.rs:1:1\n"
+        );
+    }
+
+    #[test]
+    fn miri_paths_are_normalized() {
+        let output = "     Running `/Users/someone/.rustup/toolchains/nightly-aarch64-apple-darwin/bin/cargo-miri runner target/miri/aarch64-apple-darwin/debug/unsafe-example`\n";
+        assert_eq!(
+            normalize(output, ""),
+            "     Running `file:///home/.rustup/toolchains/nightly/bin/cargo-miri runner target/miri/debug/unsafe-example`\n"
+        );
+    }
+
+    #[test]
+    fn only_changed_lines_are_reported() {
+        let previous = "$ cargo run
+error[E0384]: cannot assign twice to immutable variable `x`
+ --> src/main.rs:4:5
+  |
+2 |     let x = 5;
+";
+        let updated = "$ cargo run
+error[E0384]: cannot assign twice to immutable variable `x`
+ --> src/main.rs:4:5
+  |
+2 |     let x = 5;
+  |         - first assignment to `x`
+";
+        assert_eq!(
+            changed_lines(previous, updated),
+            vec!["+   |         - first assignment to `x`"]
+        );
+
+        assert_eq!(changed_lines("a\nb\nc\n", "a\nB\nc\n"), vec!["- b", "+ B"]);
+    }
+}
@@ -0,0 +1,26 @@
+//! Helpers shared by the tools in `src/bin`.
+
+pub mod listings;
+
+/// Split the `>` markers of any block quotes `line` is in from the rest of it.
+pub fn split_block_quote(line: &str) -> (&str, &str) {
+    let mut rest = line;
+    // Other preprocessors’ Markdown output can indent the markers, too.
+    while let Some(after) = rest.trim_start_matches(' ').strip_prefix('>') {
+        rest = after.strip_prefix(' ').unwrap_or(after);
+    }
+    line.split_at(line.len() - rest.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_and_indented_block_quotes_are_split_off() {
+        assert_eq!(split_block_quote("> > # hidden"), ("> > ", "# hidden"));
+        assert_eq!(split_block_quote("  >>text"), ("  >>", "text"));
+        assert_eq!(split_block_quote(">"), (">", ""));
+        assert_eq!(split_block_quote("plain > text"), ("", "plain > text"));
+    }
+}
@@ -0,0 +1,81 @@
+//! Building the book’s listings one at a time.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether `listing` is one of those picked with `--only=<pattern>`: all of
+/// them when there is no pattern, or else those whose path contains it.
+pub fn is_selected(listing: &Path, only: Option<&str>) -> bool {
+    only.is_none_or(|pattern| listing.to_string_lossy().contains(pattern))
+}
+
+/// A `target` directory for building one listing, which starts out empty and
+/// is deleted when this is dropped.
+///
+/// Every listing needs its own: sharing one between them would mix up the
+/// many listings with the same package name, since Cargo would consider one’s
+/// build fresh for another. Starting out empty also means each build is like
+/// the first time the listing has been built, which is what the book shows.
+#[derive(Debug)]
+pub struct TargetDir {
+    path: PathBuf,
+}
+
+impl TargetDir {
+    /// The listing’s own `target` directory, for when what Cargo prints has to
+    /// show paths like `target/debug/…` the way a reader would see them.
+    pub fn in_listing(listing: &Path) -> TargetDir {
+        TargetDir::new(listing.join("target"))
+    }
+
+    /// A target directory for `listing` in `root`, to set as
+    /// `CARGO_TARGET_DIR`, which keeps the listing’s own directory untouched.
+    pub fn under(root: &Path, listing: &Path) -> TargetDir {
+        TargetDir::new(root.join(listing))
+    }
+
+    fn new(path: PathBuf) -> TargetDir {
+        fs::remove_dir_all(&path).ok();
+        TargetDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TargetDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_filters_by_path() {
+        let listing = Path::new("listings/ch17-async-await/listing-17-01");
+        assert!(is_selected(listing, None));
+        assert!(is_selected(listing, Some("ch17")));
+        assert!(!is_selected(listing, Some("ch16")));
+    }
+
+    #[test]
+    fn target_dirs_start_empty_and_are_deleted() {
+        let root = std::env::temp_dir()
+            .join(format!("rust-book-tools-target-dir-{}", std::process::id()));
+        let listing = Path::new("listings/ch01/listing-01-01");
+        let leftover = root.join(listing).join("debug");
+        fs::create_dir_all(&leftover).unwrap();
+
+        let target_dir = TargetDir::under(&root, listing);
+        assert!(!leftover.exists());
+        fs::create_dir_all(target_dir.path().join("debug")).unwrap();
+        drop(target_dir);
+        assert!(!root.join(listing).exists());
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
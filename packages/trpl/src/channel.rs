@@ -0,0 +1,196 @@
+//! The channel the book uses: Tokio’s unbounded channel, with a receiver which
+//! is also a [`Stream`].
+
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use tokio::sync::mpsc;
+
+pub use tokio::sync::mpsc::{UnboundedSender as Sender, error::TryRecvError};
+
+/// Create a new async channel, returning the sending and receiving halves.
+///
+/// We use Tokio’s `unbounded` channel because it most closely matches the API
+/// of `std::sync::mpsc::channel`. Tokio’s API choices are interesting:
+///
+/// | `tokio::sync::mpsc` | `std::sync::mpsc` |
+/// | ------------------- | ----------------- |
+/// | `channel`           | `sync_channel`    |
+/// | `unbounded_channel` | `channel`         |
+///
+/// The book collapses these differences for pedagogical simplicity, so that
+/// readers are not asking why `unbounded` is now important and can focus on
+/// the more important differences between sync and async APIs.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (tx, Receiver { inner: rx })
+}
+
+/// The receiving half of a [`channel`].
+///
+/// Besides waiting for one message at a time with [`recv`](Receiver::recv),
+/// you can treat the receiver as a [`Stream`] of all the messages, which ends
+/// once every [`Sender`] has been dropped, and use any of the
+/// [`StreamExt`](crate::StreamExt) methods on it directly:
+///
+/// ```
+/// use trpl::StreamExt;
+///
+/// trpl::block_on(async {
+///     let (tx, rx) = trpl::channel();
+///     for n in 1..=3 {
+///         tx.send(n).unwrap();
+///     }
+///     drop(tx);
+///
+///     let doubled: Vec<u32> = rx.map(|n| n * 2).collect().await;
+///     assert_eq!(doubled, vec![2, 4, 6]);
+/// });
+/// ```
+pub struct Receiver<T> {
+    inner: mpsc::UnboundedReceiver<T>,
+}
+
+impl<T> Receiver<T> {
+    /// Wait for the next message. Produces `None` once the channel is closed
+    /// and every message sent before that has been received.
+    pub async fn recv(&mut self) -> Option<T> {
+        self.inner.recv().await
+    }
+
+    /// Wait for at least one message, and then receive as many as are ready,
+    /// up to `limit`, adding them to the end of `buffer`. Produces how many
+    /// were received, which is `0` only once the channel is closed and empty
+    /// (or if `limit` is `0`).
+    pub async fn recv_many(
+        &mut self,
+        buffer: &mut Vec<T>,
+        limit: usize,
+    ) -> usize {
+        self.inner.recv_many(buffer, limit).await
+    }
+
+    /// Receive a message if one is ready right now, without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.inner.try_recv()
+    }
+
+    /// Wait for the next message, blocking the current thread. This is for
+    /// *synchronous* code; calling it from inside an async block or function
+    /// will panic.
+    pub fn blocking_recv(&mut self) -> Option<T> {
+        self.inner.blocking_recv()
+    }
+
+    /// Poll for the next message, as part of implementing a future or stream
+    /// by hand.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.inner.poll_recv(cx)
+    }
+
+    /// Close the channel, so that no more messages can be sent, while still
+    /// letting the ones already sent be received.
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+
+    /// Whether the channel is closed: either [`close`](Receiver::close) was
+    /// called, or every [`Sender`] has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Whether there are no messages waiting to be received right now.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// How many messages are waiting to be received right now.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<T>> {
+        self.inner.poll_recv(cx)
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// A [`Stream`] of the messages sent to a [`Receiver`].
+///
+/// A `Receiver` is a stream on its own now, so you do not need this any more.
+/// It remains so that code which wraps the receiver, as earlier versions of
+/// the book did, keeps working.
+pub struct ReceiverStream<T> {
+    inner: Receiver<T>,
+}
+
+impl<T> ReceiverStream<T> {
+    /// Wrap `receiver` in a `ReceiverStream`.
+    pub fn new(receiver: Receiver<T>) -> ReceiverStream<T> {
+        ReceiverStream { inner: receiver }
+    }
+
+    /// Get back the wrapped receiver.
+    pub fn into_inner(self) -> Receiver<T> {
+        self.inner
+    }
+
+    /// Close the channel, like [`Receiver::close`].
+    pub fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+impl<T> Stream for ReceiverStream<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<T>> {
+        self.inner.poll_recv(cx)
+    }
+}
+
+impl<T> AsRef<Receiver<T>> for ReceiverStream<T> {
+    fn as_ref(&self) -> &Receiver<T> {
+        &self.inner
+    }
+}
+
+impl<T> AsMut<Receiver<T>> for ReceiverStream<T> {
+    fn as_mut(&mut self) -> &mut Receiver<T> {
+        &mut self.inner
+    }
+}
+
+impl<T> From<Receiver<T>> for ReceiverStream<T> {
+    fn from(receiver: Receiver<T>) -> ReceiverStream<T> {
+        ReceiverStream::new(receiver)
+    }
+}
+
+impl<T> fmt::Debug for ReceiverStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReceiverStream")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
@@ -0,0 +1,408 @@
+//! Treating a byte stream, like a network socket, as a stream of messages.
+//!
+//! Async readers and writers deal in raw bytes, but a protocol deals in
+//! *messages*: lines of text, say, or packets which each start with their
+//! length. A [`Framed`] wraps something you can read from and write to, along
+//! with a *codec* which knows how to find where one message (or *frame*) ends
+//! and the next one begins. The result is both a [`Stream`] of the messages
+//! which arrive, so you can use all the [`StreamExt`](crate::StreamExt)
+//! methods on it, and a [`Sink`] you can send messages into, using the methods
+//! from [`SinkExt`].
+//!
+//! There are two codecs built in: [`LinesCodec`], for text with one message
+//! per line, and [`LengthDelimitedCodec`], for binary messages which each
+//! start with their length. To support another protocol, implement
+//! [`Decoder`] and [`Encoder`] for your own codec type.
+//!
+//! `Framed` works with the reader and writer traits from the `futures` crate,
+//! like everything in [`trpl::io`](crate::io). Tokio’s sockets, like
+//! `tokio::net::TcpStream`, use Tokio’s own versions of those traits instead,
+//! so wrap them with [`trpl::io::compat`](crate::io::compat) first:
+//! `Framed::new(trpl::io::compat(socket), LinesCodec::new())`.
+//!
+//! ```
+//! use trpl::{
+//!     StreamExt,
+//!     codec::{Framed, LinesCodec, SinkExt},
+//!     io::Cursor,
+//! };
+//!
+//! trpl::block_on(async {
+//!     // A `Cursor` stands in for a network connection here.
+//!     let connection = Cursor::new(Vec::new());
+//!     let mut framed = Framed::new(connection, LinesCodec::new());
+//!     framed.send("hello").await.unwrap();
+//!     framed.send("world").await.unwrap();
+//!
+//!     // Rewind, and read back what we wrote.
+//!     let mut connection = framed.into_inner();
+//!     connection.set_position(0);
+//!     let lines: Vec<String> = Framed::new(connection, LinesCodec::new())
+//!         .try_collect()
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(lines, vec!["hello", "world"]);
+//! });
+//! ```
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures::{
+    Stream,
+    io::{AsyncRead, AsyncWrite},
+};
+use pin_project_lite::pin_project;
+
+pub use futures::{Sink, SinkExt};
+
+/// How much [`Framed`] tries to read at once.
+const READ_SIZE: usize = 4096;
+
+/// Once this many bytes are waiting to be written, [`Framed`] writes them out
+/// before accepting any more messages.
+const BACKPRESSURE_BOUNDARY: usize = 8 * 1024;
+
+/// Pulls messages out of a buffer of bytes received so far.
+pub trait Decoder {
+    /// The type of message this decodes.
+    type Item;
+
+    /// Try to decode one message from the front of `buf`, removing its bytes
+    /// from the buffer. Produce `Ok(None)` if `buf` does not contain a whole
+    /// message yet, in which case [`Framed`] reads more bytes and tries again.
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Self::Item>>;
+
+    /// Like [`decode`](Decoder::decode), but called once there are no more
+    /// bytes coming. By default, this decodes as usual, and reports an error
+    /// if there are leftover bytes which do not make up a whole message.
+    fn decode_eof(
+        &mut self,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<Option<Self::Item>> {
+        match self.decode(buf)? {
+            Some(item) => Ok(Some(item)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining at the end of the stream",
+            )),
+        }
+    }
+}
+
+/// Turns messages into bytes to send.
+pub trait Encoder<Item> {
+    /// Append the bytes for `item` to the end of `buf`.
+    fn encode(&mut self, item: Item, buf: &mut Vec<u8>) -> io::Result<()>;
+}
+
+pin_project! {
+    /// A [`Stream`] and [`Sink`] of messages, on top of an async reader and
+    /// writer of bytes. See the [module documentation](self) for details.
+    ///
+    /// As a stream, it ends after the first error, whether from reading or
+    /// from decoding: the bytes which could not be decoded are still there, so
+    /// trying again would only produce the same error.
+    pub struct Framed<T, C> {
+        #[pin]
+        io: T,
+        codec: C,
+        read_buf: Vec<u8>,
+        write_buf: Vec<u8>,
+        eof: bool,
+        // Whether the stream has ended, at the end of the input or because of
+        // an error.
+        done: bool,
+    }
+}
+
+impl<T, C> Framed<T, C> {
+    /// Use `codec` to read and write messages on `io`.
+    pub fn new(io: T, codec: C) -> Framed<T, C> {
+        Framed {
+            io,
+            codec,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Get a reference to the underlying reader and writer.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Get a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Get back the underlying reader and writer. Any bytes which have been
+    /// read but not decoded yet, or encoded but not written yet, are lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: AsyncRead, C: Decoder> Stream for Framed<T, C> {
+    type Item = io::Result<C::Item>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if *this.done {
+                return Poll::Ready(None);
+            }
+
+            let decoded = if *this.eof {
+                this.codec.decode_eof(this.read_buf)
+            } else {
+                this.codec.decode(this.read_buf)
+            };
+            match decoded {
+                Ok(Some(item)) => return Poll::Ready(Some(Ok(item))),
+                Ok(None) if *this.eof => {
+                    *this.done = true;
+                    return Poll::Ready(None);
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    *this.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+            }
+
+            let mut chunk = [0; READ_SIZE];
+            match ready!(this.io.as_mut().poll_read(cx, &mut chunk)) {
+                Ok(0) => *this.eof = true,
+                Ok(read) => this.read_buf.extend_from_slice(&chunk[..read]),
+                Err(error) => {
+                    *this.done = true;
+                    return Poll::Ready(Some(Err(error)));
+                }
+            }
+        }
+    }
+}
+
+impl<T, C, Item> Sink<Item> for Framed<T, C>
+where
+    T: AsyncWrite,
+    C: Encoder<Item>,
+{
+    type Error = io::Error;
+
+    fn poll_ready(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.write_buf.len() >= BACKPRESSURE_BOUNDARY {
+            self.poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Item) -> io::Result<()> {
+        let this = self.project();
+        this.codec.encode(item, this.write_buf)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        while !this.write_buf.is_empty() {
+            let written =
+                ready!(this.io.as_mut().poll_write(cx, this.write_buf))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            this.write_buf.drain(..written);
+        }
+        this.io.poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        self.project().io.poll_close(cx)
+    }
+}
+
+impl<T: fmt::Debug, C: fmt::Debug> fmt::Debug for Framed<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Framed")
+            .field("io", &self.io)
+            .field("codec", &self.codec)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A codec for text with one message per line.
+///
+/// Decoding produces each line as a `String`, without the `\n` (or `\r\n`) at
+/// the end; a last line without a newline at the very end of the stream counts,
+/// too. Encoding writes anything which can be viewed as a `&str`, followed by a
+/// `\n`. Lines must be valid UTF-8.
+///
+/// To keep a misbehaving peer from making us buffer an endless line, lines
+/// longer than 64 KiB are rejected as invalid data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LinesCodec {
+    _private: (),
+}
+
+impl LinesCodec {
+    /// The longest line this codec accepts, in bytes, not counting the line
+    /// ending.
+    pub const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+    /// Create a new `LinesCodec`.
+    pub fn new() -> LinesCodec {
+        LinesCodec { _private: () }
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+        // A `\r\n` ending is one byte longer than a `\n` one, so look that far
+        // for the newline before giving up on this line.
+        let limit = buf.len().min(Self::MAX_LINE_LENGTH + 2);
+        let Some(newline) = buf[..limit].iter().position(|&byte| byte == b'\n')
+        else {
+            if buf.len() > Self::MAX_LINE_LENGTH + 1 {
+                return Err(line_too_long());
+            }
+            return Ok(None);
+        };
+
+        let mut line: Vec<u8> = buf.drain(..=newline).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.len() > Self::MAX_LINE_LENGTH {
+            return Err(line_too_long());
+        }
+        utf8(line).map(Some)
+    }
+
+    fn decode_eof(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+        match self.decode(buf)? {
+            Some(line) => Ok(Some(line)),
+            None if buf.is_empty() => Ok(None),
+            None if buf.len() > Self::MAX_LINE_LENGTH => Err(line_too_long()),
+            None => utf8(std::mem::take(buf)).map(Some),
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    fn encode(&mut self, line: T, buf: &mut Vec<u8>) -> io::Result<()> {
+        let line = line.as_ref();
+        if line.len() > Self::MAX_LINE_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line is longer than the maximum line length",
+            ));
+        }
+
+        buf.extend_from_slice(line.as_bytes());
+        buf.push(b'\n');
+        Ok(())
+    }
+}
+
+fn line_too_long() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "line is longer than the maximum line length",
+    )
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// A codec for binary messages which each start with their length.
+///
+/// Each frame is a 4-byte, big-endian length, followed by that many bytes of
+/// data. Decoding produces the data as a `Vec<u8>`; encoding takes anything
+/// which can be viewed as a `&[u8]` and writes the length in front of it.
+///
+/// To keep a misbehaving peer from making us allocate huge amounts of memory,
+/// frames longer than 8 MiB are rejected as invalid data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LengthDelimitedCodec {
+    _private: (),
+}
+
+impl LengthDelimitedCodec {
+    /// The longest frame this codec accepts, in bytes.
+    pub const MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+    /// Create a new `LengthDelimitedCodec`.
+    pub fn new() -> LengthDelimitedCodec {
+        LengthDelimitedCodec { _private: () }
+    }
+}
+
+/// The size of the length at the start of each length-delimited frame.
+const HEADER_SIZE: usize = 4;
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = Vec<u8>;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        let Some(header) = buf.first_chunk::<HEADER_SIZE>() else {
+            return Ok(None);
+        };
+
+        let length = u32::from_be_bytes(*header) as usize;
+        if length > Self::MAX_FRAME_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame is longer than the maximum frame length",
+            ));
+        }
+        if buf.len() < HEADER_SIZE + length {
+            return Ok(None);
+        }
+
+        let frame = buf[HEADER_SIZE..HEADER_SIZE + length].to_vec();
+        buf.drain(..HEADER_SIZE + length);
+        Ok(Some(frame))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthDelimitedCodec {
+    fn encode(&mut self, frame: T, buf: &mut Vec<u8>) -> io::Result<()> {
+        let frame = frame.as_ref();
+        if frame.len() > Self::MAX_FRAME_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame is longer than the maximum frame length",
+            ));
+        }
+
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! A pool of worker threads for CPU-heavy work.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, OnceLock, mpsc},
+    thread,
+};
+
+use futures::channel::oneshot;
+
+use crate::trace;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Run the CPU-heavy closure `f` on a pool of worker threads, and produce its
+/// result once it is done.
+///
+/// An async task which does a lot of computation without ever reaching an
+/// `.await` keeps the runtime from running anything else on that thread in the
+/// meantime, the way the slow loop does in Chapter 17. To get around that, hand
+/// the work to `compute` instead: the closure runs on a separate thread, and
+/// the task just waits for the result, leaving the runtime free to work on
+/// other tasks.
+///
+/// The pool has one thread for each CPU core, and closures take turns on them
+/// in the order they came in. That makes it a good fit for work which keeps a
+/// core busy the whole time, like crunching numbers, and a poor fit for work
+/// which just waits around, like a blocking network call: waiting does not
+/// need a core, but it does tie up one of the pool’s threads.
+///
+/// ```
+/// trpl::block_on(async {
+///     let (sum, product) = trpl::join(
+///         trpl::compute(|| (1..=1_000_000_u64).sum::<u64>()),
+///         trpl::compute(|| (1..=20_u64).product::<u64>()),
+///     )
+///     .await;
+///
+///     assert_eq!(sum, 500_000_500_000);
+///     assert_eq!(product, 2_432_902_008_176_640_000);
+/// });
+/// ```
+///
+/// If `f` panics, the panic is passed along to whoever is awaiting the result,
+/// just as though `f` had run right there; the worker thread itself keeps
+/// going.
+pub async fn compute<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    trace::event!("sending work to the compute pool");
+    let job: Job = Box::new(move || {
+        // The caller may have stopped waiting, in which case nobody wants
+        // the result, so there is nothing to do if sending it fails.
+        let _ = tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    });
+    pool()
+        .send(job)
+        .expect("the compute pool’s threads never stop");
+
+    match rx.await.expect("the compute pool always sends a result") {
+        Ok(output) => output,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// The queue of work for the pool, starting its threads the first time it is
+/// needed.
+fn pool() -> &'static mpsc::Sender<Job> {
+    static POOL: OnceLock<mpsc::Sender<Job>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        for index in 0..threads {
+            let rx = Arc::clone(&rx);
+            thread::Builder::new()
+                .name(format!("trpl-compute-{index}"))
+                .spawn(move || {
+                    loop {
+                        // Only hold the lock while waiting for a job, so that
+                        // the other threads can pick up jobs in the meantime.
+                        let job = rx.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    }
+                })
+                .expect("could not start a compute pool thread");
+        }
+        tx
+    })
+}
@@ -0,0 +1,117 @@
+//! An async condition variable, to go with the async [`Mutex`].
+
+use std::pin::pin;
+
+use tokio::sync::{Mutex, MutexGuard, Notify};
+
+/// An async version of [`std::sync::Condvar`], for use with [`Mutex`].
+///
+/// A condition variable lets a task wait, without holding a lock, until some
+/// condition on the data behind that lock becomes true. The pattern is the
+/// same one we use with threads: lock the mutex, check the condition, and if
+/// it does not hold yet, hand the guard to [`wait`](Condvar::wait), which
+/// unlocks the mutex while waiting and locks it again before returning.
+/// Whoever changes the data calls [`notify_one`](Condvar::notify_one) or
+/// [`notify_all`](Condvar::notify_all) afterward.
+///
+/// As with `std::sync::Condvar`, being woken up does not guarantee that the
+/// condition holds: another task may have gotten to the data first. Always
+/// check the condition again in a loop, or use
+/// [`wait_while`](Condvar::wait_while), which does that for you.
+///
+/// ```
+/// use std::sync::Arc;
+/// use trpl::{Condvar, Mutex};
+///
+/// trpl::block_on(async {
+///     let state = Arc::new((Mutex::new(false), Condvar::new()));
+///
+///     let worker = {
+///         let state = Arc::clone(&state);
+///         trpl::spawn_task(async move {
+///             let (started, condvar) = &*state;
+///             *started.lock().await = true;
+///             condvar.notify_all();
+///         })
+///     };
+///
+///     let (started, condvar) = &*state;
+///     let mut guard = started.lock().await;
+///     while !*guard {
+///         guard = condvar.wait(guard).await;
+///     }
+///     println!("The worker started!");
+///
+///     drop(guard);
+///     worker.await.unwrap();
+/// });
+/// ```
+#[derive(Debug, Default)]
+pub struct Condvar {
+    notify: Notify,
+}
+
+impl Condvar {
+    /// Create a new condition variable, with nobody waiting on it yet.
+    pub fn new() -> Condvar {
+        Condvar {
+            notify: Notify::new(),
+        }
+    }
+
+    /// Unlock the mutex `guard` belongs to, wait until this condition variable
+    /// is notified, then lock the mutex again and return the new guard.
+    ///
+    /// The task starts listening for notifications *before* it unlocks the
+    /// mutex, so a notification sent by another task which locks the mutex in
+    /// the meantime cannot slip through unnoticed.
+    pub async fn wait<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+    ) -> MutexGuard<'a, T> {
+        let mutex: &'a Mutex<T> = MutexGuard::mutex(&guard);
+
+        let mut notified = pin!(self.notify.notified());
+        notified.as_mut().enable();
+        drop(guard);
+
+        notified.await;
+        mutex.lock().await
+    }
+
+    /// Wait, as with [`wait`](Condvar::wait), for as long as `condition`
+    /// returns `true` for the data behind the lock. Returns as soon as it
+    /// returns `false`, which might be right away.
+    pub async fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard).await;
+        }
+        guard
+    }
+
+    /// Wake up one of the tasks waiting on this condition variable.
+    ///
+    /// Unlike with `std::sync::Condvar`, a notification is not lost when
+    /// nobody is waiting: it is saved, and the next call to
+    /// [`wait`](Condvar::wait) returns right away, even though nothing changed
+    /// after it started waiting. (Only one notification is saved, however many
+    /// times this is called.) That is one more reason to check the condition
+    /// again in a loop, or to use [`wait_while`](Condvar::wait_while). If the
+    /// early wakeup is a problem, use [`notify_all`](Condvar::notify_all),
+    /// which only wakes the tasks waiting at the time it is called.
+    pub fn notify_one(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Wake up every task currently waiting on this condition variable.
+    pub fn notify_all(&self) {
+        self.notify.notify_waiters();
+    }
+}
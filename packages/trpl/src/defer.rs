@@ -0,0 +1,110 @@
+//! Async cleanup which runs even if the surrounding future is cancelled.
+
+use std::fmt;
+
+use futures::{FutureExt, future::BoxFuture};
+
+/// Schedule some async cleanup to happen when the returned [`Defer`] guard
+/// goes away.
+///
+/// There is no such thing as an async `Drop`: when a value is dropped, Rust
+/// calls `drop` synchronously and moves on, so there is nowhere to `.await`
+/// anything. That matters for async code in particular, because a future can
+/// be dropped at *any* `.await` point, for example when it loses a
+/// [`select`](crate::select) or hits a timeout. Any cleanup it was going to do
+/// afterward simply never happens.
+///
+/// A `Defer` guard makes sure the cleanup still happens:
+///
+/// - If you get to the end of the happy path, call [`Defer::run`], which runs
+///   the cleanup right there, so you can wait for it to finish.
+/// - If the guard is dropped without being run, including because the future
+///   holding it was cancelled, it spawns the cleanup as a new task with
+///   [`spawn_task`](crate::spawn_task). The cleanup still happens, but nobody
+///   waits for it: that is the best anyone can do without async `Drop`.
+///
+///   Outside of a runtime, where there is nothing to spawn the task on, the
+///   guard runs the cleanup to completion with [`block_on`](crate::block_on)
+///   instead, before `drop` returns. The one exception is when the guard is
+///   dropped while the thread is already panicking: then the cleanup is
+///   skipped, since a second panic from it would abort the whole program.
+/// - If it turns out no cleanup is needed, call [`Defer::cancel`].
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let (tx, mut rx) = trpl::channel();
+///
+///     let slow_work = async {
+///         let _cleanup = trpl::defer(move || async move {
+///             tx.send("cleaned up").unwrap();
+///         });
+///         trpl::sleep(Duration::from_secs(10)).await;
+///     };
+///
+///     // The timeout wins, so `slow_work` is dropped partway through…
+///     trpl::select(slow_work, trpl::sleep(Duration::from_millis(1))).await;
+///
+///     // …but its cleanup runs anyway.
+///     assert_eq!(rx.recv().await, Some("cleaned up"));
+/// });
+/// ```
+pub fn defer<F, Fut>(cleanup: F) -> Defer
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Defer {
+        cleanup: Some(Box::new(move || cleanup().boxed())),
+    }
+}
+
+/// A guard which runs some async cleanup when it goes away. Created by
+/// [`defer`]; see its documentation for details.
+#[must_use = "the cleanup runs as soon as the guard is dropped"]
+pub struct Defer {
+    cleanup: Option<Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>>,
+}
+
+impl Defer {
+    /// Run the cleanup now, and wait for it to finish.
+    pub async fn run(mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup().await;
+        }
+    }
+
+    /// Skip the cleanup entirely.
+    pub fn cancel(mut self) {
+        self.cleanup = None;
+    }
+}
+
+impl Drop for Defer {
+    fn drop(&mut self) {
+        let Some(cleanup) = self.cleanup.take() else {
+            return;
+        };
+
+        // Spawning outside a runtime panics with Tokio, which would abort the
+        // process if we are already unwinding.
+        #[cfg(not(target_arch = "wasm32"))]
+        if crate::Handle::try_current().is_none() {
+            if !std::thread::panicking() {
+                crate::block_on(cleanup());
+            }
+            return;
+        }
+
+        crate::spawn_task(cleanup());
+    }
+}
+
+impl fmt::Debug for Defer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Defer")
+            .field("armed", &self.cleanup.is_some())
+            .finish()
+    }
+}
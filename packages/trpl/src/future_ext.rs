@@ -0,0 +1,155 @@
+//! Extension methods for futures, so they can be used in method-chain style.
+
+use std::{any::Any, panic::AssertUnwindSafe, time::Duration};
+
+use crate::{Timeout, timeout_resumable};
+
+/// Extension methods for working with [`Future`]s, automatically available on
+/// every future once the trait is in scope: `use trpl::FutureExt;`.
+pub trait FutureExt: Future {
+    /// Transform the future’s output with `f` once it finishes.
+    ///
+    /// This is the same as writing `async { f(future.await) }`, which is what
+    /// you will usually see in the book; which one reads better is mostly a
+    /// matter of taste.
+    ///
+    /// ```
+    /// use trpl::FutureExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let length = async { String::from("hello") }.map(|s| s.len()).await;
+    ///     assert_eq!(length, 5);
+    /// });
+    /// ```
+    fn map<T, F>(self, f: F) -> impl Future<Output = T>
+    where
+        F: FnOnce(Self::Output) -> T,
+        Self: Sized,
+    {
+        futures::FutureExt::map(self, f)
+    }
+
+    /// Call `f` with a reference to the future’s output once it finishes, and
+    /// then pass the output along unchanged. Handy for logging.
+    ///
+    /// ```
+    /// use trpl::FutureExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let answer = async { 42 }
+    ///         .inspect(|n| println!("the answer is {n}"))
+    ///         .await;
+    ///     assert_eq!(answer, 42);
+    /// });
+    /// ```
+    fn inspect<F>(self, f: F) -> impl Future<Output = Self::Output>
+    where
+        F: FnOnce(&Self::Output),
+        Self: Sized,
+    {
+        futures::FutureExt::inspect(self, f)
+    }
+
+    /// For a future which produces a `Result`: if it succeeds, pass the value
+    /// to `f` and wait for the future `f` returns, too. If it fails, produce
+    /// the error right away, without calling `f`. This works just like
+    /// [`Result::and_then`], but for async operations which can each fail.
+    ///
+    /// The same thing with `async` and `await` looks like this:
+    /// `async { f(future.await?).await }`.
+    ///
+    /// ```
+    /// use trpl::FutureExt;
+    ///
+    /// async fn parse(text: &str) -> Result<u32, String> {
+    ///     text.parse().map_err(|_| format!("{text:?} is not a number"))
+    /// }
+    ///
+    /// trpl::block_on(async {
+    ///     let doubled = parse("21").and_then(async |n| Ok(n * 2)).await;
+    ///     assert_eq!(doubled, Ok(42));
+    ///
+    ///     let failed = parse("twenty").and_then(async |n| Ok(n * 2)).await;
+    ///     assert!(failed.is_err());
+    /// });
+    /// ```
+    fn and_then<T, U, E, F, Fut>(
+        self,
+        f: F,
+    ) -> impl Future<Output = Result<U, E>>
+    where
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = Result<U, E>>,
+        Self: Future<Output = Result<T, E>> + Sized,
+    {
+        futures::TryFutureExt::and_then(self, f)
+    }
+
+    /// Catch a panic while running this future, so it becomes an ordinary
+    /// `Err` you can report, rather than unwinding through everything that is
+    /// waiting on it. Produces `Ok` with the future’s output if it finishes
+    /// normally, and `Err` with the panic’s payload (the same value
+    /// [`std::panic::catch_unwind`] gives you) if it panics.
+    ///
+    /// Unlike `std::panic::catch_unwind`, this does not require the future to
+    /// be [`UnwindSafe`](std::panic::UnwindSafe), just as
+    /// [`spawn_task`](crate::spawn_task) does not, so be careful about using
+    /// anything the future was in the middle of changing when it panicked.
+    ///
+    /// ```
+    /// use trpl::FutureExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let result = async {
+    ///         panic!("something went wrong");
+    ///     }
+    ///     .catch_unwind()
+    ///     .await;
+    ///
+    ///     let payload = result.unwrap_err();
+    ///     let message = payload.downcast_ref::<&str>().unwrap();
+    ///     assert_eq!(*message, "something went wrong");
+    /// });
+    /// ```
+    fn catch_unwind(
+        self,
+    ) -> impl Future<Output = Result<Self::Output, Box<dyn Any + Send>>>
+    where
+        Self: Sized,
+    {
+        futures::FutureExt::catch_unwind(AssertUnwindSafe(self))
+    }
+
+    /// Wait up to `max_time` for this future to finish. This is the same as
+    /// [`timeout_resumable`], but reads left to right, the same way
+    /// [`StreamExt::timeout`](crate::StreamExt::timeout) does on streams.
+    ///
+    /// If the future does not finish in time, the error hands it back, in case
+    /// you want to keep waiting on it; if not, just drop the error.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use trpl::FutureExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let slow = trpl::sleep(Duration::from_secs(60));
+    ///     let result = slow.timeout(Duration::from_millis(1)).await;
+    ///     assert!(result.is_err());
+    ///
+    ///     let fast = async { "done" };
+    ///     let result = fast.timeout(Duration::from_secs(60)).await;
+    ///     assert_eq!(result.unwrap(), "done");
+    /// });
+    /// ```
+    fn timeout(
+        self,
+        max_time: Duration,
+    ) -> impl Future<Output = Result<Self::Output, Timeout<Self>>>
+    where
+        Self: Sized,
+    {
+        timeout_resumable(self, max_time)
+    }
+}
+
+impl<F: Future + ?Sized> FutureExt for F {}
@@ -0,0 +1,225 @@
+//! Moving back and forth between streams of bytes and async readers.
+//!
+//! Some things hand you their data as a [`Stream`] of chunks of bytes, like the
+//! body of an HTTP response. Others give you an *async reader*, which you pull
+//! bytes out of a bit at a time, like a file or a network socket. The two
+//! functions here convert between them, so that you can use whichever is more
+//! convenient: [`stream_reader`] turns a stream into a reader, and
+//! [`reader_stream`] turns a reader into a stream, which then works with all
+//! the [`StreamExt`](crate::StreamExt) combinators.
+//!
+//! The reader and writer traits are the ones from the `futures` crate, which
+//! work with any runtime. They are re-exported here, along with their `Ext`
+//! traits, which supply methods like `read_to_string` and `lines`, and with
+//! [`Cursor`], an in-memory reader and writer which is handy for trying things
+//! out. Tokio has its own versions of the reader and writer traits, which its
+//! sockets and files use; [`compat`] adapts those to the ones here.
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures::{Stream, TryStreamExt};
+use pin_project_lite::pin_project;
+
+pub use futures::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite,
+    AsyncWriteExt, Cursor,
+};
+pub use futures::stream::IntoAsyncRead as StreamReader;
+
+/// How much [`ReaderStream`] tries to read at once.
+const CHUNK_SIZE: usize = 4096;
+
+/// Turn a stream of chunks of bytes into an async reader.
+///
+/// Each item of the stream is either a chunk of bytes (anything which can be
+/// viewed as a `&[u8]`, like a `Vec<u8>` or a `&str`) or an I/O error, which
+/// the reader reports when it gets to it. The stream must be `Unpin`; if yours
+/// is not, wrap it with `Box::pin` first.
+///
+/// ```
+/// use trpl::{
+///     StreamExt,
+///     io::{AsyncReadExt, stream_reader},
+/// };
+///
+/// trpl::block_on(async {
+///     let chunks = trpl::stream_from_iter(["Hello, ", "world", "!"])
+///         .map(|chunk| Ok::<_, std::io::Error>(chunk.as_bytes()));
+///
+///     let mut text = String::new();
+///     stream_reader(chunks).read_to_string(&mut text).await.unwrap();
+///     assert_eq!(text, "Hello, world!");
+/// });
+/// ```
+pub fn stream_reader<S, B>(stream: S) -> StreamReader<S>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    stream.into_async_read()
+}
+
+/// Turn an async reader into a stream of chunks of bytes.
+///
+/// The stream produces each chunk as it is read, as an `Ok(Vec<u8>)`, and
+/// finishes once the reader runs out of bytes. If reading fails, it produces
+/// the error as an `Err` item and then finishes.
+///
+/// ```
+/// use trpl::{StreamExt, io::reader_stream};
+///
+/// trpl::block_on(async {
+///     let reader: &[u8] = b"some bytes";
+///     let chunks: Vec<Vec<u8>> =
+///         reader_stream(reader).try_collect().await.unwrap();
+///     assert_eq!(chunks.concat(), b"some bytes");
+/// });
+/// ```
+pub fn reader_stream<R: AsyncRead>(reader: R) -> ReaderStream<R> {
+    ReaderStream {
+        reader,
+        buffer: vec![0; CHUNK_SIZE],
+        done: false,
+    }
+}
+
+pin_project! {
+    /// A stream of the bytes read from an async reader. Created by
+    /// [`reader_stream`].
+    pub struct ReaderStream<R> {
+        #[pin]
+        reader: R,
+        buffer: Vec<u8>,
+        done: bool,
+    }
+}
+
+impl<R> ReaderStream<R> {
+    /// Get back the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead> Stream for ReaderStream<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        match this.reader.poll_read(cx, this.buffer) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(0)) => {
+                *this.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(read)) => {
+                Poll::Ready(Some(Ok(this.buffer[..read].to_vec())))
+            }
+            Poll::Ready(Err(error)) => {
+                *this.done = true;
+                Poll::Ready(Some(Err(error)))
+            }
+        }
+    }
+}
+
+/// Use a reader or writer built on Tokio’s `AsyncRead` and `AsyncWrite` traits,
+/// like a `tokio::net::TcpStream`, where the ones from the `futures` crate are
+/// expected, for example with [`Framed`](crate::codec::Framed).
+///
+/// ```
+/// use trpl::{
+///     StreamExt,
+///     codec::{Framed, LinesCodec, SinkExt},
+/// };
+///
+/// trpl::block_on(async {
+///     // The two ends of an in-memory connection, which stand in for the two
+///     // ends of a `tokio::net::TcpStream` here.
+///     let (client, server) = tokio::io::duplex(1024);
+///
+///     let mut client =
+///         Framed::new(trpl::io::compat(client), LinesCodec::new());
+///     let mut server =
+///         Framed::new(trpl::io::compat(server), LinesCodec::new());
+///
+///     client.send("hello").await.unwrap();
+///     assert_eq!(server.next().await.unwrap().unwrap(), "hello");
+/// });
+/// ```
+pub fn compat<T>(io: T) -> Compat<T> {
+    Compat { io }
+}
+
+pin_project! {
+    /// A Tokio reader or writer, usable as a `futures` one. Created by
+    /// [`compat`].
+    #[derive(Debug)]
+    pub struct Compat<T> {
+        #[pin]
+        io: T,
+    }
+}
+
+impl<T> Compat<T> {
+    /// Get back the wrapped reader or writer.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: tokio::io::AsyncRead> AsyncRead for Compat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        ready!(self.project().io.poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+impl<T: tokio::io::AsyncWrite> AsyncWrite for Compat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for ReaderStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReaderStream")
+            .field("reader", &self.reader)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,193 @@
+//! Helpers for waiting on many futures at once, beyond what `futures` offers.
+
+use futures::{StreamExt, stream::FuturesUnordered};
+
+use crate::Sender;
+
+/// One future finishing, as reported by [`join_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The position of the future which finished, in the order the futures
+    /// were passed in.
+    pub index: usize,
+    /// How many of the futures have finished so far, including this one.
+    pub completed: usize,
+    /// How many futures there are in total.
+    pub total: usize,
+}
+
+/// Like [`join_all`](crate::join_all), but sends a [`Progress`] event on `tx`
+/// each time one of the futures finishes.
+///
+/// The outputs come back in the same order as the futures were passed in, no
+/// matter which order they finished in. The progress events come in the order
+/// the futures finished, so you can turn the receiving end into a stream and
+/// use it to drive something like a progress bar. If nobody is listening on
+/// the other end anymore, the events are simply dropped.
+///
+/// ```
+/// use std::time::Duration;
+/// use trpl::{ReceiverStream, StreamExt};
+///
+/// trpl::block_on(async {
+///     let (tx, rx) = trpl::channel();
+///
+///     let downloads = [3, 1, 2].map(|secs| async move {
+///         trpl::sleep(Duration::from_millis(secs)).await;
+///         secs
+///     });
+///     let work = trpl::join_with_progress(downloads, tx);
+///
+///     let report = async {
+///         let mut progress = ReceiverStream::new(rx);
+///         while let Some(event) = progress.next().await {
+///             println!("{}/{} done", event.completed, event.total);
+///         }
+///     };
+///
+///     let (outputs, ()) = trpl::join(work, report).await;
+///     assert_eq!(outputs, vec![3, 1, 2]);
+/// });
+/// ```
+pub async fn join_with_progress<I>(
+    futures: I,
+    tx: Sender<Progress>,
+) -> Vec<<I::Item as Future>::Output>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let mut pending: FuturesUnordered<_> = futures
+        .into_iter()
+        .enumerate()
+        .map(|(index, future)| async move { (index, future.await) })
+        .collect();
+
+    let total = pending.len();
+    let mut outputs: Vec<_> = (0..total).map(|_| None).collect();
+    let mut completed = 0;
+
+    while let Some((index, output)) = pending.next().await {
+        outputs[index] = Some(output);
+        completed += 1;
+        let _ = tx.send(Progress {
+            index,
+            completed,
+            total,
+        });
+    }
+
+    outputs
+        .into_iter()
+        .map(|output| output.expect("every future finished"))
+        .collect()
+}
+
+/// Like [`join_all`](crate::join_all), but never runs more than `limit` of the
+/// futures at the same time.
+///
+/// It starts the first `limit` futures, and then starts the next one each time
+/// one of those finishes. That matters when there are lots of futures: firing
+/// off thousands of web requests all at once with `join_all` can overwhelm the
+/// server on the other end (or your own network connection), while this keeps
+/// only a handful in flight. The outputs come back in the same order as the
+/// futures were passed in.
+///
+/// Panics if `limit` is zero, since then none of the futures could ever run.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let requests = (1..=100).map(|page| async move {
+///         trpl::sleep(Duration::from_millis(1)).await;
+///         format!("page {page}")
+///     });
+///
+///     let pages = trpl::join_all_limited(10, requests).await;
+///     assert_eq!(pages.len(), 100);
+///     assert_eq!(pages[0], "page 1");
+/// });
+/// ```
+pub async fn join_all_limited<I>(
+    limit: usize,
+    futures: I,
+) -> Vec<<I::Item as Future>::Output>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    assert!(limit > 0, "`join_all_limited` needs a limit of at least 1");
+
+    let mut waiting = futures
+        .into_iter()
+        .enumerate()
+        .map(|(index, future)| async move { (index, future.await) });
+    let mut running: FuturesUnordered<_> =
+        waiting.by_ref().take(limit).collect();
+    let mut outputs = Vec::new();
+
+    while let Some((index, output)) = running.next().await {
+        if outputs.len() <= index {
+            outputs.resize_with(index + 1, || None);
+        }
+        outputs[index] = Some(output);
+
+        if let Some(next) = waiting.next() {
+            running.push(next);
+        }
+    }
+
+    outputs
+        .into_iter()
+        .map(|output| output.expect("every future finished"))
+        .collect()
+}
+
+/// Run all of the futures concurrently, and produce the first `Ok` result any
+/// of them returns, dropping (and so canceling) the rest.
+///
+/// A future which fails does not end the race: the others keep going. Only if
+/// *every* future fails does this produce an `Err`, which holds all of the
+/// errors, in the same order as the futures were passed in. (That means
+/// passing in no futures at all produces an `Err` with no errors in it.)
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let mirrors =
+///         [("slow", 20, true), ("down", 1, false), ("fast", 5, true)];
+///     let requests = mirrors.map(|(name, ms, up)| async move {
+///         trpl::sleep(Duration::from_millis(ms)).await;
+///         if up { Ok(name) } else { Err(format!("{name} is down")) }
+///     });
+///
+///     assert_eq!(trpl::race_ok(requests).await, Ok("fast"));
+/// });
+/// ```
+pub async fn race_ok<I, T, E>(futures: I) -> Result<T, Vec<E>>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    let mut pending: FuturesUnordered<_> = futures
+        .into_iter()
+        .enumerate()
+        .map(|(index, future)| async move { (index, future.await) })
+        .collect();
+
+    let mut errors: Vec<_> = (0..pending.len()).map(|_| None).collect();
+
+    while let Some((index, result)) = pending.next().await {
+        match result {
+            Ok(value) => return Ok(value),
+            Err(error) => errors[index] = Some(error),
+        }
+    }
+
+    Err(errors
+        .into_iter()
+        .map(|error| error.expect("every future failed"))
+        .collect())
+}
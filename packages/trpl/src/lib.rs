@@ -14,62 +14,92 @@
 //!    never be broken by upstream changes, e.g. if Tokio does a breaking 2.0
 //!    release at some point.
 
+mod channel;
+#[cfg(not(target_arch = "wasm32"))]
+mod compute;
+mod condvar;
+mod defer;
+mod future_ext;
+mod join;
+mod probe;
+mod retry;
+mod rpc;
+mod rt;
+#[cfg(not(target_arch = "wasm32"))]
+mod signal;
+mod timeout;
+mod trace;
+
+pub mod codec;
+pub mod io;
+pub mod prelude;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod process;
+pub mod stream;
+pub mod test;
+
 // For direct use within the `trpl` crate, *not* re-exported.
-use std::{future::Future, pin::pin};
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Wake, Waker},
+};
 
 use futures::future;
 
 // Re-exports, to be used like `trpl::join`.
 pub use futures::{
-    future::{Either, join, join_all, join3},
+    future::{Either, join, join_all, join3, join4, join5},
     join,
+    task::noop_waker,
 };
 pub use tokio::{
-    fs::read_to_string,
-    runtime::Runtime,
-    // We use the `unbounded` variants because they most closely match the APIs
-    // from `std::sync::mpsc::channel`. Tokio's API choices are interesting:
-    //
-    // | `tokio::sync::mpsc` | `std::sync::mpsc` |
-    // | ------------------- | ----------------- |
-    // | `channel`           | `sync_channel`    |
-    // | `unbounded_channel` | `channel`         |
-    //
-    // The book collapses these differences for pedagogical simplicity, so that
-    // readers are not asking why `unbounded` is now important and can focus on
-    // the more important differences between sync and async APIs.
-    sync::mpsc::{
-        UnboundedReceiver as Receiver, UnboundedSender as Sender,
-        unbounded_channel as channel,
-    },
-    task::{JoinHandle, spawn as spawn_task, yield_now},
-    time::{interval, sleep},
+    // Tokio's `Semaphore` works with any runtime. Its `acquire_owned` permits
+    // (which hold an `Arc` to the semaphore rather than borrowing it) can move
+    // into spawned tasks, and `acquire_many` takes several permits at once.
+    sync::{AcquireError, OwnedSemaphorePermit, Semaphore, SemaphorePermit},
+    // Tokio's `Mutex` works with any runtime, and unlike `std::sync::Mutex`,
+    // its guard can be held across an `.await`. It also lets you narrow a
+    // guard down to one part of the data with `MutexGuard::map`.
+    sync::{MappedMutexGuard, Mutex, MutexGuard},
 };
 
-pub use tokio_stream::{
-    Stream, StreamExt, iter as stream_from_iter,
-    wrappers::{IntervalStream, UnboundedReceiverStream as ReceiverStream},
-};
+pub use channel::{Receiver, ReceiverStream, Sender, TryRecvError, channel};
+#[cfg(not(target_arch = "wasm32"))]
+pub use compute::compute;
+pub use condvar::Condvar;
+pub use defer::{Defer, defer};
+pub use future_ext::FutureExt;
+pub use join::{Progress, join_all_limited, join_with_progress, race_ok};
+pub use probe::{Probe, probe};
+pub use retry::retry;
+pub use rpc::{Responder, RpcReceiver, RpcSender, rpc_channel};
+#[cfg(not(target_arch = "wasm32"))]
+pub use signal::{Signal, SignalKind, ctrl_c, signal};
+pub use timeout::{Timeout, timeout_resumable};
 
-/// Run a single future to completion on a bespoke Tokio `Runtime`.
-///
-/// Every time you call this, a new instance of `tokio::runtime::Runtime` will
-/// be created (see the implementation for details: it is trivial). This is:
-///
-/// - Reasonable for teaching purposes, in that you do not generally need to set
-///   up more than one runtime anyway, and especially do not in basic code like
-///   we are showing!
-///
-/// - Not *that* far off from what Tokio itself does under the hood in its own
-///   `tokio::main` macro for supporting `async fn main`.
-pub fn block_on<F: Future>(future: F) -> F::Output {
-    let rt = Runtime::new().unwrap();
-    rt.block_on(future)
-}
+pub use stream::{StreamExt, merge_all};
+pub use tokio_stream::{Stream, iter as stream_from_iter};
+
+// The runtime-specific APIs: these come from whichever backend is enabled (see
+// the `rt` module for details), but always have the same names.
+#[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+pub use rt::Runtime;
+#[cfg(not(target_arch = "wasm32"))]
+pub use rt::{
+    EnterGuard, Handle, IntervalStream, block_on, enter, interval,
+    read_to_string,
+};
+pub use rt::{
+    JoinError, JoinHandle, Priority, sleep, spawn_task,
+    spawn_task_with_priority, start, yield_now,
+};
 
 /// This function has been renamed to `block_on`; please see its documentation.
 /// This function remains to maintain compatibility with the online versions
 /// of the book that use the name `run`.
+#[cfg(not(target_arch = "wasm32"))]
 pub fn run<F: Future>(future: F) -> F::Output {
     block_on(future)
 }
@@ -115,10 +145,62 @@ where
     select(f1, f2).await
 }
 
+/// Create a [`Waker`] which calls the supplied closure every time it is woken.
+///
+/// This is handy for polling a future “by hand” and seeing exactly when (and
+/// how many times) the future asks to be woken up again. If you do not care
+/// about wakes at all, use [`noop_waker`] instead.
+///
+/// ```
+/// use std::{
+///     future::Future,
+///     pin::pin,
+///     sync::{
+///         Arc,
+///         atomic::{AtomicUsize, Ordering},
+///     },
+///     task::Context,
+/// };
+///
+/// let wakes = Arc::new(AtomicUsize::new(0));
+/// let waker = trpl::waker_fn({
+///     let wakes = Arc::clone(&wakes);
+///     move || {
+///         wakes.fetch_add(1, Ordering::SeqCst);
+///     }
+/// });
+///
+/// let mut cx = Context::from_waker(&waker);
+/// let mut future = pin!(trpl::yield_now());
+/// assert!(future.as_mut().poll(&mut cx).is_pending());
+/// assert_eq!(wakes.load(Ordering::SeqCst), 1);
+/// ```
+pub fn waker_fn<F>(f: F) -> Waker
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    Waker::from(Arc::new(WakerFn(f)))
+}
+
+struct WakerFn<F>(F);
+
+impl<F> Wake for WakerFn<F>
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    fn wake(self: Arc<Self>) {
+        (self.0)();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        (self.0)();
+    }
+}
+
 /// Fetch data from a URL. For more convenient use in _The Rust Programming
 /// Language_, panics instead of returning a [`Result`] if the request fails.
 pub async fn get(url: &str) -> Response {
-    Response(reqwest::get(url).await.unwrap())
+    Response(rt::compat(reqwest::get(url)).await.unwrap())
 }
 
 /// A thin wrapper around [`reqwest::Response`] to make the demos in _The Rust
@@ -131,7 +213,7 @@ impl Response {
     /// If the response cannot be deserialized, this panics instead of returning
     /// a [`Result`] (for convenience in the demo).
     pub async fn text(self) -> String {
-        self.0.text().await.unwrap()
+        rt::compat(self.0.text()).await.unwrap()
     }
 }
 
@@ -0,0 +1,24 @@
+//! The traits and types the book’s listings use most often, so that a listing
+//! can start with a single import:
+//!
+//! ```
+//! use trpl::prelude::*;
+//!
+//! trpl::block_on(async {
+//!     let mut stream = trpl::stream_from_iter([1, 2, 3]).map(|n| n * 2);
+//!     while let Some(n) = stream.next().await {
+//!         println!("{n}");
+//!     }
+//! });
+//! ```
+//!
+//! Functions like `trpl::sleep` and `trpl::join` are deliberately *not* in
+//! here: the book always calls them with the `trpl::` prefix, so it is clear
+//! where they come from.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::IntervalStream;
+pub use crate::{
+    Either, FutureExt, Html, JoinHandle, Receiver, ReceiverStream, Sender,
+    Stream, StreamExt,
+};
@@ -0,0 +1,135 @@
+//! A wrapper for watching how a future or stream gets polled.
+
+use std::{
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+};
+
+use futures::Stream;
+use pin_project_lite::pin_project;
+
+/// Wrap a future or stream so that it prints a line every time it is polled,
+/// every time it returns `Pending` or `Ready`, and every time it is woken.
+///
+/// The output looks something like this:
+///
+/// ```text
+/// [slow] poll
+/// [slow] pending
+/// [slow] woken
+/// [slow] poll
+/// [slow] ready
+/// ```
+///
+/// This is meant for teaching: it lets you *see* the scheduling behavior of an
+/// async runtime instead of having to take our word for it.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let slow = trpl::probe("slow", trpl::sleep(Duration::from_millis(1)));
+///     slow.await;
+/// });
+/// ```
+pub fn probe<T>(name: impl Into<String>, inner: T) -> Probe<T> {
+    Probe {
+        name: Arc::from(name.into()),
+        inner,
+    }
+}
+
+pin_project! {
+    /// A future or stream which reports when it is polled and woken. Created
+    /// by [`probe`].
+    pub struct Probe<T> {
+        name: Arc<str>,
+        #[pin]
+        inner: T,
+    }
+}
+
+impl<T> Probe<T> {
+    /// Get back the wrapped future or stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> fmt::Debug for Probe<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Probe").field("name", &self.name).finish()
+    }
+}
+
+impl<F: Future> Future for Probe<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        println!("[{}] poll", this.name);
+
+        let waker = probe_waker(this.name, cx.waker());
+        let result = this.inner.poll(&mut Context::from_waker(&waker));
+        match result {
+            Poll::Pending => println!("[{}] pending", this.name),
+            Poll::Ready(_) => println!("[{}] ready", this.name),
+        }
+        result
+    }
+}
+
+impl<S: Stream> Stream for Probe<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        println!("[{}] poll", this.name);
+
+        let waker = probe_waker(this.name, cx.waker());
+        let result = this.inner.poll_next(&mut Context::from_waker(&waker));
+        match result {
+            Poll::Pending => println!("[{}] pending", this.name),
+            Poll::Ready(Some(_)) => println!("[{}] ready (item)", this.name),
+            Poll::Ready(None) => {
+                println!("[{}] ready (end of stream)", this.name)
+            }
+        }
+        result
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Wrap the runtime's waker so that waking it also prints a line. We have to
+/// build a new one on every poll, because the runtime is allowed to hand us a
+/// different waker each time.
+fn probe_waker(name: &Arc<str>, inner: &Waker) -> Waker {
+    Waker::from(Arc::new(ProbeWaker {
+        name: Arc::clone(name),
+        inner: inner.clone(),
+    }))
+}
+
+struct ProbeWaker {
+    name: Arc<str>,
+    inner: Waker,
+}
+
+impl Wake for ProbeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        println!("[{}] woken", self.name);
+        self.inner.wake_by_ref();
+    }
+}
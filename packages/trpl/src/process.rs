@@ -0,0 +1,180 @@
+//! Running other programs without blocking.
+//!
+//! This is the async counterpart to [`std::process`]. Building a [`Command`]
+//! works the same way, but waiting for the program to finish is a future, so
+//! other tasks keep running in the meantime. A spawned [`Child`] can also hand
+//! over its output as a [`Stream`] of lines, which arrive as the program
+//! prints them.
+//!
+//! ```no_run
+//! use trpl::{StreamExt, process::Command};
+//!
+//! trpl::block_on(async {
+//!     let mut child = Command::new("cargo").arg("build").spawn().unwrap();
+//!
+//!     let mut lines = child.stdout_lines().unwrap();
+//!     while let Some(line) = lines.next().await {
+//!         println!("cargo says: {}", line.unwrap());
+//!     }
+//!
+//!     let status = child.wait().await.unwrap();
+//!     println!("cargo finished: {status}");
+//! });
+//! ```
+
+use std::{
+    ffi::OsStr,
+    fmt, io,
+    path::Path,
+    pin::Pin,
+    process::Stdio,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, io::AsyncRead};
+
+use crate::{
+    codec::{Framed, LinesCodec},
+    rt::{RawChild, RawCommand},
+};
+
+pub use std::process::{ExitStatus, Output};
+
+/// Whichever backend is running, a child’s output is read through this.
+pub(crate) type Reader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A program to run, along with its arguments and environment. This works like
+/// [`std::process::Command`], but its methods for running the program are
+/// async.
+pub struct Command {
+    inner: RawCommand,
+}
+
+impl Command {
+    /// Start building a command to run `program`.
+    pub fn new(program: impl AsRef<OsStr>) -> Command {
+        Command {
+            inner: RawCommand::new(program),
+        }
+    }
+
+    /// Add an argument to pass to the program.
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Command {
+        self.inner.arg(arg);
+        self
+    }
+
+    /// Add several arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    /// Set an environment variable for the program.
+    pub fn env(
+        &mut self,
+        key: impl AsRef<OsStr>,
+        value: impl AsRef<OsStr>,
+    ) -> &mut Command {
+        self.inner.env(key, value);
+        self
+    }
+
+    /// Set the directory to run the program in.
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Command {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Run the program, with its input and output connected to ours, and wait
+    /// for it to finish.
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        self.inner.status().await
+    }
+
+    /// Run the program and wait for it to finish, collecting everything it
+    /// writes to its standard output and standard error.
+    pub async fn output(&mut self) -> io::Result<Output> {
+        self.inner.output().await
+    }
+
+    /// Start the program, and return right away with a [`Child`] for watching
+    /// it. The program’s standard output is captured, so that you can read it
+    /// with [`Child::stdout_lines`]; its input and standard error are
+    /// connected to ours.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        self.inner.stdout(Stdio::piped());
+        Ok(Child {
+            inner: RawChild::spawn(&mut self.inner)?,
+        })
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// A running program, started with [`Command::spawn`].
+///
+/// Dropping a `Child` does not stop the program; use [`Child::kill`] for that.
+#[derive(Debug)]
+pub struct Child {
+    inner: RawChild,
+}
+
+impl Child {
+    /// The operating system’s id for the process, if it is still running.
+    pub fn id(&self) -> Option<u32> {
+        self.inner.id()
+    }
+
+    /// Take the program’s standard output, as a stream of lines. Produces
+    /// `None` if it has already been taken. Each line is a `Result`, since
+    /// reading it can fail, and it can turn out not to be valid UTF-8.
+    pub fn stdout_lines(&mut self) -> Option<Lines> {
+        let stdout = self.inner.take_stdout()?;
+        Some(Lines {
+            inner: Framed::new(stdout, LinesCodec::new()),
+        })
+    }
+
+    /// Wait for the program to finish.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.inner.wait().await
+    }
+
+    /// Ask the operating system to stop the program. Call
+    /// [`wait`](Child::wait) afterward to wait until it has actually stopped.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.inner.kill()
+    }
+}
+
+/// The lines a [`Child`] writes to its standard output, without the newlines.
+/// Created by [`Child::stdout_lines`].
+pub struct Lines {
+    inner: Framed<Reader, LinesCodec>,
+}
+
+impl Stream for Lines {
+    type Item = io::Result<String>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl fmt::Debug for Lines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lines").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,46 @@
+//! Trying something again when it fails.
+
+/// Call the async function `f` until it succeeds, up to `attempts` times in
+/// all, and produce its first `Ok` result. If every attempt fails, produce the
+/// error from the last one.
+///
+/// `f` is an `AsyncFnMut`, so it can be an async closure which keeps track of
+/// things between attempts. To wait a bit before trying again, just sleep at
+/// the start of the closure.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let mut attempt = 0;
+///     let result = trpl::retry(3, async || {
+///         attempt += 1;
+///         trpl::sleep(Duration::from_millis(1)).await;
+///         if attempt < 3 {
+///             Err(format!("attempt {attempt} failed"))
+///         } else {
+///             Ok("finally!")
+///         }
+///     })
+///     .await;
+///
+///     assert_eq!(result, Ok("finally!"));
+/// });
+/// ```
+///
+/// Panics if `attempts` is zero, since then there would be no result at all.
+pub async fn retry<T, E, F>(attempts: usize, mut f: F) -> Result<T, E>
+where
+    F: AsyncFnMut() -> Result<T, E>,
+{
+    assert!(attempts > 0, "`retry` needs at least 1 attempt");
+
+    let mut result = f().await;
+    for _ in 1..attempts {
+        if result.is_ok() {
+            break;
+        }
+        result = f().await;
+    }
+    result
+}
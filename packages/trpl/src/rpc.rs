@@ -0,0 +1,108 @@
+//! Request/response channels, for the actor pattern.
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::trace;
+
+/// Create a channel for sending requests which each get a response.
+///
+/// A common way to structure a concurrent program is as a set of *actors*:
+/// tasks which each own some state and talk to the rest of the program only
+/// through messages. With a plain [`channel`](crate::channel), getting an
+/// answer back from an actor means setting up a second channel for the replies
+/// and keeping track of which reply goes with which request. Here, that is
+/// built in: [`RpcSender::call`] sends a request and waits for the response to
+/// that particular request.
+///
+/// ```
+/// use trpl::rpc_channel;
+///
+/// enum Command {
+///     Add(u32),
+///     Total,
+/// }
+///
+/// trpl::block_on(async {
+///     let (counter, mut requests) = rpc_channel();
+///
+///     // The actor: it owns `total`, and nothing else can touch it.
+///     trpl::spawn_task(async move {
+///         let mut total = 0;
+///         while let Some((command, responder)) = requests.recv().await {
+///             if let Command::Add(n) = command {
+///                 total += n;
+///             }
+///             responder.respond(total);
+///         }
+///     });
+///
+///     counter.call(Command::Add(2)).await;
+///     counter.call(Command::Add(40)).await;
+///     assert_eq!(counter.call(Command::Total).await, Some(42));
+/// });
+/// ```
+pub fn rpc_channel<Req, Resp>() -> (RpcSender<Req, Resp>, RpcReceiver<Req, Resp>)
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    (RpcSender { tx }, RpcReceiver { rx })
+}
+
+/// The sending half of an [`rpc_channel`], for making calls. Clone it to make
+/// calls from more than one place.
+#[derive(Debug)]
+pub struct RpcSender<Req, Resp> {
+    tx: mpsc::UnboundedSender<(Req, Responder<Resp>)>,
+}
+
+impl<Req, Resp> RpcSender<Req, Resp> {
+    /// Send `request`, and wait for the response to it.
+    ///
+    /// Produces `None` if there is no response coming: either the receiving
+    /// half has been dropped, or it dropped the [`Responder`] for this
+    /// request without responding.
+    pub async fn call(&self, request: Req) -> Option<Resp> {
+        let (tx, rx) = oneshot::channel();
+        trace::event!("rpc call sent");
+        self.tx.send((request, Responder { tx })).ok()?;
+        let response = rx.await.ok();
+        trace::event!(answered = response.is_some(), "rpc call finished");
+        response
+    }
+}
+
+impl<Req, Resp> Clone for RpcSender<Req, Resp> {
+    fn clone(&self) -> Self {
+        RpcSender {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+/// The receiving half of an [`rpc_channel`], for handling calls.
+#[derive(Debug)]
+pub struct RpcReceiver<Req, Resp> {
+    rx: mpsc::UnboundedReceiver<(Req, Responder<Resp>)>,
+}
+
+impl<Req, Resp> RpcReceiver<Req, Resp> {
+    /// Wait for the next request, along with the [`Responder`] to use to answer
+    /// it. Produces `None` once every [`RpcSender`] has been dropped.
+    pub async fn recv(&mut self) -> Option<(Req, Responder<Resp>)> {
+        self.rx.recv().await
+    }
+}
+
+/// Sends the response to one request received from an [`RpcReceiver`].
+#[derive(Debug)]
+pub struct Responder<Resp> {
+    tx: oneshot::Sender<Resp>,
+}
+
+impl<Resp> Responder<Resp> {
+    /// Send `response` back to the caller. If the caller has stopped waiting
+    /// for it (for example, because its call was cancelled), the response is
+    /// simply dropped.
+    pub fn respond(self, response: Resp) {
+        let _ = self.tx.send(response);
+    }
+}
@@ -0,0 +1,49 @@
+//! Making a runtime current outside of `block_on`.
+
+use std::marker::PhantomData;
+
+use super::RawEnterGuard;
+
+/// Make a runtime available to synchronous code, so that it can create futures
+/// which need one, like [`sleep`](crate::sleep), and store them to run later.
+///
+/// With the Tokio backend, a timer hooks itself up to the current runtime as
+/// soon as it is created, so creating one outside of [`block_on`] panics with
+/// a message about there being no reactor running. Creating it while holding
+/// the guard this returns works instead, and the future can then be awaited
+/// from any later `block_on` call, even after the guard is gone. With the smol
+/// backend, timers work anywhere to begin with, so this does nothing, but it
+/// is still fine to call, so the same code works with either backend.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// // Plain synchronous code, outside of any `block_on`…
+/// let delay = {
+///     let _guard = trpl::enter();
+///     trpl::sleep(Duration::from_millis(1))
+/// };
+///
+/// // …and the timer still works once something drives it.
+/// trpl::block_on(delay);
+/// ```
+///
+/// The guard cannot be sent to another thread: it only affects the thread it
+/// was created on, until it is dropped.
+///
+/// [`block_on`]: crate::block_on
+pub fn enter() -> EnterGuard {
+    EnterGuard {
+        _raw: RawEnterGuard::enter(),
+        _not_send: PhantomData,
+    }
+}
+
+/// Keeps a runtime current on this thread until it is dropped. Created by
+/// [`enter`].
+#[derive(Debug)]
+pub struct EnterGuard {
+    _raw: RawEnterGuard,
+    /// The Tokio guard is tied to its thread; match that with every backend.
+    _not_send: PhantomData<*const ()>,
+}
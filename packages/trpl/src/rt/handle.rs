@@ -0,0 +1,76 @@
+//! A handle to the running runtime, so sync code can hand work back to it.
+
+use super::{JoinHandle, RawHandle, join_handle};
+
+/// A handle to the runtime which is running the current [`block_on`] call.
+///
+/// This is useful when some *synchronous* code—say, a callback a C library
+/// calls on its own thread—needs to hand work off to the async runtime. Grab
+/// the handle while you are still inside `block_on`, move it to where it is
+/// needed, and then use it to [`spawn`] tasks or to [`block_on`] a future from
+/// there.
+///
+/// ```
+/// use std::thread;
+///
+/// let answer = trpl::block_on(async {
+///     let handle = trpl::Handle::current();
+///
+///     // Some synchronous code on another thread…
+///     let callback = thread::spawn(move || {
+///         // …which needs the result of async work.
+///         handle.block_on(async { 21 * 2 })
+///     });
+///
+///     callback.join().unwrap()
+/// });
+///
+/// assert_eq!(answer, 42);
+/// ```
+///
+/// [`block_on`]: crate::block_on
+/// [`spawn`]: Handle::spawn
+#[derive(Clone, Debug)]
+pub struct Handle {
+    raw: RawHandle,
+}
+
+impl Handle {
+    /// Get a handle to the current runtime.
+    ///
+    /// Panics if called outside of [`block_on`](crate::block_on) (or a task
+    /// spawned from it); use [`try_current`](Handle::try_current) if you are
+    /// not sure.
+    pub fn current() -> Handle {
+        Handle::try_current().expect(
+            "`trpl::Handle::current` must be called inside `trpl::block_on`",
+        )
+    }
+
+    /// Get a handle to the current runtime, if there is one.
+    pub fn try_current() -> Option<Handle> {
+        RawHandle::try_current().map(|raw| Handle { raw })
+    }
+
+    /// Spawn a new task on the runtime, just like
+    /// [`spawn_task`](crate::spawn_task). This works from any thread.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (task, handle) = join_handle::task(future);
+        self.raw.spawn_detached(task);
+        handle
+    }
+
+    /// Run a future to completion on the runtime, blocking the current thread
+    /// until it finishes.
+    ///
+    /// This is for *synchronous* code: like [`block_on`](crate::block_on),
+    /// calling it from inside an async block or function will panic with the
+    /// Tokio backend, and always blocks the executor.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.raw.block_on(future)
+    }
+}
@@ -0,0 +1,202 @@
+//! The `JoinHandle` every backend hands out, so that tasks behave the same no
+//! matter which one is running them.
+//!
+//! The backend spawns (and detaches) the future returned by [`task`], and the
+//! result comes back to the [`JoinHandle`] over a oneshot channel. That gives
+//! us the same semantics as Tokio: dropping the handle does not cancel the
+//! task, awaiting it produces a `Result`, and a panic in the task shows up as a
+//! [`JoinError`] instead of tearing down the executor.
+
+use std::{
+    any::Any,
+    error::Error,
+    fmt,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    FutureExt,
+    channel::oneshot,
+    future::{AbortHandle, Abortable, Either},
+};
+
+use crate::trace;
+
+/// Wrap `future` so that its result is sent to the returned [`JoinHandle`].
+/// The backend is responsible for actually running the wrapped future.
+pub(crate) fn task<F: Future>(
+    future: F,
+) -> (impl Future<Output = ()>, JoinHandle<F::Output>) {
+    let (tx, rx) = oneshot::channel();
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let future = Abortable::new(
+        AssertUnwindSafe(future).catch_unwind(),
+        abort_registration,
+    );
+
+    let task = async move {
+        trace::event!("task started");
+
+        // If the task was aborted, we just drop `tx`, which the handle reports
+        // as cancellation. If the `JoinHandle` was dropped, nobody is waiting
+        // for the result, and that is fine too.
+        match future.await {
+            Ok(result) => {
+                trace::event!(panicked = result.is_err(), "task finished");
+                let _ = tx.send(result.map_err(JoinError::panic));
+            }
+            Err(_aborted) => trace::event!("task aborted"),
+        }
+    };
+
+    #[cfg(feature = "tracing")]
+    let task = tracing::Instrument::instrument(task, trace::task_span());
+
+    (task, JoinHandle { rx, abort_handle })
+}
+
+/// An owned permission to wait for a spawned task to finish, like Tokio’s
+/// `JoinHandle`. Created by `spawn_task`.
+///
+/// Dropping a `JoinHandle` does *not* cancel the task; use [`abort`] for that.
+///
+/// [`abort`]: JoinHandle::abort
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    rx: oneshot::Receiver<Result<T, JoinError>>,
+    abort_handle: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancel the task. Awaiting the handle afterward produces a
+    /// [`JoinError`] unless the task had already finished.
+    pub fn abort(&self) {
+        self.abort_handle.abort();
+    }
+
+    /// Wait up to `max_time` for the task to finish. Produces `Some` with the
+    /// task’s result if it finishes in time, and `None` if it is still
+    /// running.
+    ///
+    /// Either way, you keep the handle, so a supervisor which finds a task
+    /// stuck can decide what to do about it: keep waiting, or give up and
+    /// [`abort`](JoinHandle::abort) it.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// trpl::block_on(async {
+    ///     let mut stuck = trpl::spawn_task(async {
+    ///         trpl::sleep(Duration::from_secs(60)).await;
+    ///     });
+    ///
+    ///     if stuck.join_timeout(Duration::from_millis(10)).await.is_none() {
+    ///         println!("The task is stuck; stopping it.");
+    ///         stuck.abort();
+    ///     }
+    ///
+    ///     assert!(stuck.await.unwrap_err().is_cancelled());
+    /// });
+    /// ```
+    pub async fn join_timeout(
+        &mut self,
+        max_time: Duration,
+    ) -> Option<Result<T, JoinError>> {
+        match crate::select(self, crate::sleep(max_time)).await {
+            Either::Left(result) => Some(result),
+            Either::Right(()) => None,
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        self.rx.poll_unpin(cx).map(|result| match result {
+            Ok(result) => result,
+            Err(oneshot::Canceled) => Err(JoinError::cancelled()),
+        })
+    }
+}
+
+/// The error produced when awaiting a [`JoinHandle`] whose task panicked or
+/// was cancelled before it finished.
+pub struct JoinError {
+    repr: Repr,
+}
+
+enum Repr {
+    Cancelled,
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+impl JoinError {
+    fn cancelled() -> JoinError {
+        JoinError {
+            repr: Repr::Cancelled,
+        }
+    }
+
+    fn panic(payload: Box<dyn Any + Send + 'static>) -> JoinError {
+        JoinError {
+            repr: Repr::Panic(payload),
+        }
+    }
+
+    /// Whether the task was cancelled before it finished.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.repr, Repr::Cancelled)
+    }
+
+    /// Whether the task panicked.
+    pub fn is_panic(&self) -> bool {
+        matches!(self.repr, Repr::Panic(_))
+    }
+
+    /// Get the payload the task panicked with, for example to resume the panic
+    /// with [`std::panic::resume_unwind`].
+    ///
+    /// Panics if the task was cancelled instead of panicking.
+    pub fn into_panic(self) -> Box<dyn Any + Send + 'static> {
+        self.try_into_panic()
+            .expect("`JoinError` reason is not a panic.")
+    }
+
+    /// Get the payload the task panicked with, or the error back if the task
+    /// was cancelled instead.
+    pub fn try_into_panic(
+        self,
+    ) -> Result<Box<dyn Any + Send + 'static>, JoinError> {
+        match self.repr {
+            Repr::Panic(payload) => Ok(payload),
+            Repr::Cancelled => Err(self),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.repr {
+            Repr::Cancelled => f.write_str("task was cancelled"),
+            Repr::Panic(_) => f.write_str("task panicked"),
+        }
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.repr {
+            Repr::Cancelled => f.write_str("JoinError::Cancelled"),
+            Repr::Panic(_) => f.write_str("JoinError::Panic(..)"),
+        }
+    }
+}
+
+impl Error for JoinError {}
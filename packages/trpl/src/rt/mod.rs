@@ -0,0 +1,156 @@
+//! The runtime facade.
+//!
+//! Everything which depends on *which* async runtime is driving the futures
+//! lives here, behind one set of names, so that the rest of the crate (and the
+//! book!) does not have to care. The backend is picked with Cargo features:
+//!
+//! - `runtime-tokio`, the default, uses Tokio’s multi-threaded runtime.
+//! - `runtime-smol` uses smol’s global executor.
+//!
+//! At least one of them must be enabled. Features add up across everything in
+//! a build, so if some other crate turns on `runtime-tokio` while you ask for
+//! `runtime-smol`, both end up enabled; then Tokio wins, since it is the
+//! default. In the browser (`wasm32`), neither one applies: there, we always
+//! use the browser’s own event loop.
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(feature = "runtime-tokio"),
+    not(feature = "runtime-smol")
+))]
+compile_error!(
+    "Enable one of the `runtime-tokio` (the default) or `runtime-smol` \
+     features."
+);
+
+#[cfg(not(target_arch = "wasm32"))]
+mod enter;
+#[cfg(not(target_arch = "wasm32"))]
+mod handle;
+mod join_handle;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use enter::{EnterGuard, enter};
+#[cfg(not(target_arch = "wasm32"))]
+pub use handle::Handle;
+pub use join_handle::{JoinError, JoinHandle};
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+mod tokio_rt;
+#[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+pub use tokio_rt::*;
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "runtime-smol",
+    not(feature = "runtime-tokio")
+))]
+mod smol_rt;
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "runtime-smol",
+    not(feature = "runtime-tokio")
+))]
+pub use smol_rt::*;
+
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub use wasm::*;
+
+/// Run a future to completion, for code which should also work in the browser.
+///
+/// On most targets, this is just [`block_on`] for a future which produces
+/// `()`. In the browser, where blocking is not allowed, it instead hands the
+/// future off to the browser’s event loop and returns immediately.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn start<F>(future: F)
+where
+    F: Future<Output = ()>,
+{
+    block_on(future)
+}
+
+/// Spawn a new task on the runtime, returning a [`JoinHandle`] so you can await
+/// its result.
+///
+/// The task starts running right away, whether or not you ever await the
+/// handle, and dropping the handle does *not* cancel the task. To cancel it,
+/// use [`JoinHandle::abort`].
+///
+/// Inside [`trpl::test::with_seed`](crate::test::with_seed), tasks go to the
+/// seeded scheduler instead of the runtime.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn_task<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_task_with_priority(Priority::Normal, future)
+}
+
+/// How urgently a task spawned with [`spawn_task_with_priority`] should run,
+/// compared to the others.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Run ahead of any [`Normal`](Priority::Normal) tasks which are ready at
+    /// the same time.
+    High,
+    /// The priority of every task spawned with [`spawn_task`].
+    #[default]
+    Normal,
+}
+
+/// Like [`spawn_task`], but with a hint about how urgently the task should run.
+///
+/// Priorities are only *hints*. Neither Tokio nor smol has any notion of task
+/// priority, so on a real runtime the hint is ignored and this is exactly the
+/// same as `spawn_task`. The seeded scheduler in
+/// [`trpl::test::with_seed`](crate::test::with_seed) does honor it, though: it
+/// uses two levels, and whenever any high-priority tasks are ready to make
+/// progress, it picks one of them before considering the normal ones. That
+/// makes it a good way to see what prioritization does to the order of the
+/// output:
+///
+/// ```
+/// use trpl::{Priority, test::with_seed};
+///
+/// let order = with_seed(1, async {
+///     let (tx, mut rx) = trpl::channel();
+///     for (name, priority) in [
+///         ("normal", Priority::Normal),
+///         ("urgent", Priority::High),
+///         ("whenever", Priority::Normal),
+///     ] {
+///         let tx = tx.clone();
+///         trpl::spawn_task_with_priority(priority, async move {
+///             tx.send(name).unwrap();
+///         });
+///     }
+///     drop(tx);
+///
+///     let mut order = vec![];
+///     while let Some(name) = rx.recv().await {
+///         order.push(name);
+///     }
+///     order
+/// });
+///
+/// assert_eq!(order[0], "urgent");
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn_task_with_priority<F>(
+    priority: Priority,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    crate::trace::event!(?priority, "spawning task");
+    let (task, handle) = join_handle::task(future);
+    if let Err(task) = crate::test::spawn_seeded(Box::pin(task), priority) {
+        spawn_detached(task);
+    }
+    handle
+}
@@ -0,0 +1,273 @@
+//! The smol backend, used when the `runtime-smol` feature is enabled and
+//! `runtime-tokio` is not.
+//!
+//! The names and behavior here match the Tokio backend as closely as we can
+//! manage, so the book’s examples work unchanged with either one. Two caveats:
+//!
+//! - The timing-based `StreamExt` methods (`timeout`, `timeout_repeating`,
+//!   `throttle`, and `chunks_timeout`) come from `tokio-stream` and need
+//!   Tokio’s timer, so they are left out with this backend.
+//! - `trpl::get` still uses `reqwest`, which needs Tokio; we run it through
+//!   `async-compat`, which supplies a Tokio context behind the scenes.
+
+use std::{
+    cell::Cell,
+    io,
+    pin::Pin,
+    sync::{Mutex, OnceLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt, channel::oneshot};
+
+use crate::trace;
+
+pub use smol::{fs::read_to_string, future::yield_now};
+
+/// Run a single future to completion, blocking the current thread until it
+/// finishes, and return its output (so a `main` which returns a `Result` can
+/// simply return this). This is `smol::block_on`; tasks spawned with
+/// [`spawn_task`](crate::spawn_task) run on smol’s global executor in the
+/// meantime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let _inside = Inside::enter();
+    smol::block_on(future)
+}
+
+/// Hand a task off to smol’s global executor. The caller has already wired it
+/// up to its `JoinHandle`, so we detach it: unlike with `smol::spawn`, dropping
+/// the handle must not cancel the task, to match how Tokio behaves.
+pub(crate) fn spawn_detached<F>(task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let mut task = Box::pin(task);
+    smol::spawn(std::future::poll_fn(move |cx| {
+        let _inside = Inside::enter();
+        task.as_mut().poll(cx)
+    }))
+    .detach();
+}
+
+thread_local! {
+    static INSIDE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks this thread as inside the runtime until it is dropped.
+///
+/// smol’s global executor is always there, so unlike with Tokio, there is no
+/// runtime to ask about whether we are inside it. Instead, we keep track
+/// ourselves: while `block_on` runs, while one of our tasks is being polled,
+/// and while an [`EnterGuard`](crate::EnterGuard) is held. That way
+/// [`Handle::try_current`](crate::Handle::try_current) gives the same answers
+/// with either backend.
+#[derive(Debug)]
+struct Inside {
+    was_inside: bool,
+}
+
+impl Inside {
+    fn enter() -> Inside {
+        Inside {
+            was_inside: INSIDE.replace(true),
+        }
+    }
+}
+
+impl Drop for Inside {
+    fn drop(&mut self) {
+        INSIDE.set(self.was_inside);
+    }
+}
+
+/// Wait until `duration` has elapsed.
+pub async fn sleep(duration: Duration) {
+    trace::event!(?duration, "sleep started");
+    smol::Timer::after(duration).await;
+    trace::event!(?duration, "sleep finished");
+}
+
+/// Create an [`Interval`] which ticks once every `period`, starting right
+/// away. Like Tokio’s `interval`, the first tick completes immediately.
+pub fn interval(period: Duration) -> Interval {
+    Interval {
+        timer: smol::Timer::interval_at(Instant::now(), period),
+    }
+}
+
+/// A timer which ticks at a regular interval. Created by [`interval`].
+#[derive(Debug)]
+pub struct Interval {
+    timer: smol::Timer,
+}
+
+impl Interval {
+    /// Wait for the next tick.
+    pub async fn tick(&mut self) -> Instant {
+        self.timer.next().await.expect("interval timers never end")
+    }
+}
+
+/// A [`Stream`] which yields every tick of an [`Interval`].
+#[derive(Debug)]
+pub struct IntervalStream {
+    interval: Interval,
+}
+
+impl IntervalStream {
+    /// Wrap an [`Interval`] as a stream.
+    pub fn new(interval: Interval) -> IntervalStream {
+        IntervalStream { interval }
+    }
+
+    /// Get back the wrapped [`Interval`].
+    pub fn into_inner(self) -> Interval {
+        self.interval
+    }
+}
+
+impl Stream for IntervalStream {
+    type Item = Instant;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Instant>> {
+        self.interval.timer.poll_next_unpin(cx)
+    }
+}
+
+/// smol’s timers work on any thread, with or without an executor, so there is
+/// nothing to set up.
+pub(crate) fn with_runtime<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+/// Wait for Ctrl+C. Backs [`trpl::ctrl_c`](crate::ctrl_c).
+///
+/// The `ctrlc` crate lets us install exactly one handler per process, so we
+/// install it the first time anyone asks, and it wakes everyone who is waiting
+/// at the time each signal arrives.
+pub(crate) async fn ctrl_c() -> io::Result<()> {
+    static WAITING: Mutex<Vec<oneshot::Sender<()>>> = Mutex::new(Vec::new());
+    static HANDLER: OnceLock<Result<(), String>> = OnceLock::new();
+
+    let (tx, rx) = oneshot::channel();
+    WAITING.lock().unwrap().push(tx);
+
+    HANDLER
+        .get_or_init(|| {
+            ctrlc::set_handler(|| {
+                for tx in WAITING.lock().unwrap().drain(..) {
+                    let _ = tx.send(());
+                }
+            })
+            .map_err(|error| error.to_string())
+        })
+        .clone()
+        .map_err(io::Error::other)?;
+
+    rx.await.map_err(io::Error::other)
+}
+
+/// Listen for signals of one kind. Backs [`trpl::signal`](crate::signal).
+#[cfg(unix)]
+pub(crate) fn signal(
+    kind: crate::SignalKind,
+) -> io::Result<futures::stream::BoxStream<'static, ()>> {
+    use async_signal::{Signal, Signals};
+
+    let signal = match kind {
+        crate::SignalKind::Interrupt => Signal::Int,
+        crate::SignalKind::Terminate => Signal::Term,
+        crate::SignalKind::Hangup => Signal::Hup,
+    };
+    let signals = Signals::new([signal])?;
+    Ok(signals
+        .filter_map(|signal| std::future::ready(signal.ok().map(|_| ())))
+        .boxed())
+}
+
+/// Elsewhere, there are no such signals, so there is nothing to listen for.
+#[cfg(not(unix))]
+pub(crate) fn signal(
+    _kind: crate::SignalKind,
+) -> io::Result<futures::stream::BoxStream<'static, ()>> {
+    Ok(Box::pin(futures::stream::pending()))
+}
+
+/// Make a future which needs Tokio (like a `reqwest` request) runnable on the
+/// smol backend.
+pub(crate) fn compat<F: Future>(future: F) -> async_compat::Compat<F> {
+    async_compat::Compat::new(future)
+}
+
+/// The smol side of [`Handle`](crate::Handle). smol’s global executor is
+/// always available, so there is nothing to hold on to.
+#[derive(Clone, Debug)]
+pub(crate) struct RawHandle;
+
+impl RawHandle {
+    pub(crate) fn try_current() -> Option<RawHandle> {
+        INSIDE.get().then_some(RawHandle)
+    }
+
+    pub(crate) fn spawn_detached<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        spawn_detached(task);
+    }
+
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        block_on(future)
+    }
+}
+
+/// The smol side of [`EnterGuard`](crate::EnterGuard). smol’s timers work on
+/// any thread, so all there is to enter is our own record of being inside.
+#[derive(Debug)]
+pub(crate) struct RawEnterGuard {
+    _inside: Inside,
+}
+
+impl RawEnterGuard {
+    pub(crate) fn enter() -> RawEnterGuard {
+        RawEnterGuard {
+            _inside: Inside::enter(),
+        }
+    }
+}
+
+/// The smol side of [`trpl::process::Command`](crate::process::Command).
+pub(crate) type RawCommand = smol::process::Command;
+
+/// The smol side of [`trpl::process::Child`](crate::process::Child).
+#[derive(Debug)]
+pub(crate) struct RawChild(smol::process::Child);
+
+impl RawChild {
+    pub(crate) fn spawn(command: &mut RawCommand) -> io::Result<RawChild> {
+        command.spawn().map(RawChild)
+    }
+
+    pub(crate) fn id(&self) -> Option<u32> {
+        Some(self.0.id())
+    }
+
+    pub(crate) fn take_stdout(&mut self) -> Option<crate::process::Reader> {
+        let stdout = self.0.stdout.take()?;
+        Some(Box::new(stdout))
+    }
+
+    pub(crate) async fn wait(
+        &mut self,
+    ) -> io::Result<std::process::ExitStatus> {
+        self.0.status().await
+    }
+
+    pub(crate) fn kill(&mut self) -> io::Result<()> {
+        self.0.kill()
+    }
+}
@@ -0,0 +1,187 @@
+//! The Tokio backend, used when the `runtime-tokio` feature is enabled (which
+//! it is by default). Almost everything here is a straight re-export.
+
+use std::{sync::OnceLock, time::Duration};
+
+use crate::trace;
+
+pub use tokio::{
+    fs::read_to_string, runtime::Runtime, task::yield_now, time::interval,
+};
+pub use tokio_stream::wrappers::IntervalStream;
+
+/// Wait until `duration` has elapsed. This is Tokio’s `sleep`, so it produces
+/// a [`tokio::time::Sleep`] future.
+pub fn sleep(duration: Duration) -> tokio::time::Sleep {
+    trace::event!(?duration, "sleep started");
+    tokio::time::sleep(duration)
+}
+
+/// Run a single future to completion on a bespoke Tokio `Runtime`.
+///
+/// Every time you call this, a new instance of `tokio::runtime::Runtime` will
+/// be created (see the implementation for details: it is trivial). This is:
+///
+/// - Reasonable for teaching purposes, in that you do not generally need to set
+///   up more than one runtime anyway, and especially do not in basic code like
+///   we are showing!
+///
+/// - Not *that* far off from what Tokio itself does under the hood in its own
+///   `tokio::main` macro for supporting `async fn main`.
+///
+/// Since this returns whatever the future produces, it works with `main`
+/// functions which return a `Result`, just like `async fn main` does with
+/// `tokio::main`. If the future produces an `Err`, `main` returns it, so Rust
+/// prints the error and exits with a nonzero exit code:
+///
+/// ```no_run
+/// use std::error::Error;
+///
+/// fn main() -> Result<(), Box<dyn Error>> {
+///     trpl::block_on(async {
+///         let contents = trpl::read_to_string("hello.txt").await?;
+///         println!("{contents}");
+///         Ok(())
+///     })
+/// }
+/// ```
+///
+/// This is not available in the browser, which does not allow blocking; use
+/// [`start`](crate::start) instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let rt = Runtime::new().unwrap();
+    rt.block_on(future)
+}
+
+/// Hand a task off to the runtime. The caller has already wired it up to its
+/// `JoinHandle`, so there is nothing to return.
+pub(crate) fn spawn_detached<F>(task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(task);
+}
+
+/// Run `f` with a Tokio runtime available in the background, so that timers
+/// created while it runs work even though `f` is not running *on* Tokio.
+pub(crate) fn with_runtime<R>(f: impl FnOnce() -> R) -> R {
+    let rt = Runtime::new().unwrap();
+    let _guard = rt.enter();
+    f()
+}
+
+/// Wait for Ctrl+C. Backs [`trpl::ctrl_c`](crate::ctrl_c).
+pub(crate) async fn ctrl_c() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+/// Listen for signals of one kind. Backs [`trpl::signal`](crate::signal).
+#[cfg(unix)]
+pub(crate) fn signal(
+    kind: crate::SignalKind,
+) -> std::io::Result<futures::stream::BoxStream<'static, ()>> {
+    use futures::StreamExt;
+    use tokio::signal::unix::{self, SignalKind};
+
+    let kind = match kind {
+        crate::SignalKind::Interrupt => SignalKind::interrupt(),
+        crate::SignalKind::Terminate => SignalKind::terminate(),
+        crate::SignalKind::Hangup => SignalKind::hangup(),
+    };
+    let mut signal = unix::signal(kind)?;
+    Ok(futures::stream::poll_fn(move |cx| signal.poll_recv(cx)).boxed())
+}
+
+/// Elsewhere, there are no such signals, so there is nothing to listen for.
+#[cfg(not(unix))]
+pub(crate) fn signal(
+    _kind: crate::SignalKind,
+) -> std::io::Result<futures::stream::BoxStream<'static, ()>> {
+    Ok(Box::pin(futures::stream::pending()))
+}
+
+/// Make a future which needs Tokio (like a `reqwest` request) runnable on the
+/// current backend. With Tokio, there is nothing to do!
+pub(crate) fn compat<F: Future>(future: F) -> F {
+    future
+}
+
+/// The Tokio side of [`Handle`](crate::Handle).
+#[derive(Clone, Debug)]
+pub(crate) struct RawHandle(tokio::runtime::Handle);
+
+impl RawHandle {
+    pub(crate) fn try_current() -> Option<RawHandle> {
+        tokio::runtime::Handle::try_current().ok().map(RawHandle)
+    }
+
+    pub(crate) fn spawn_detached<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.0.spawn(task);
+    }
+
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+}
+
+/// The Tokio side of [`EnterGuard`](crate::EnterGuard).
+///
+/// Timers stop working once the runtime they were created on shuts down, so
+/// rather than a runtime which goes away along with the guard, this enters a
+/// shared background runtime which lasts as long as the program does.
+#[derive(Debug)]
+pub(crate) struct RawEnterGuard {
+    _guard: tokio::runtime::EnterGuard<'static>,
+}
+
+impl RawEnterGuard {
+    pub(crate) fn enter() -> RawEnterGuard {
+        static BACKGROUND: OnceLock<Runtime> = OnceLock::new();
+        let runtime = BACKGROUND.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("trpl-background")
+                .enable_all()
+                .build()
+                .unwrap()
+        });
+        RawEnterGuard {
+            _guard: runtime.enter(),
+        }
+    }
+}
+
+/// The Tokio side of [`trpl::process::Command`](crate::process::Command).
+pub(crate) type RawCommand = tokio::process::Command;
+
+/// The Tokio side of [`trpl::process::Child`](crate::process::Child).
+#[derive(Debug)]
+pub(crate) struct RawChild(tokio::process::Child);
+
+impl RawChild {
+    pub(crate) fn spawn(command: &mut RawCommand) -> std::io::Result<RawChild> {
+        command.spawn().map(RawChild)
+    }
+
+    pub(crate) fn id(&self) -> Option<u32> {
+        self.0.id()
+    }
+
+    pub(crate) fn take_stdout(&mut self) -> Option<crate::process::Reader> {
+        let stdout = self.0.stdout.take()?;
+        Some(Box::new(crate::io::compat(stdout)))
+    }
+
+    pub(crate) async fn wait(
+        &mut self,
+    ) -> std::io::Result<std::process::ExitStatus> {
+        self.0.wait().await
+    }
+
+    pub(crate) fn kill(&mut self) -> std::io::Result<()> {
+        self.0.start_kill()
+    }
+}
@@ -0,0 +1,76 @@
+//! The browser backend, used on `wasm32-unknown-unknown`.
+//!
+//! There is no way to block the main thread in a browser, and no thread pool
+//! to hand work off to, so this supplies browser-friendly versions of the
+//! handful of APIs which cannot simply be re-exported from Tokio there:
+//!
+//! - [`start`] instead of `block_on`: it hands the future off to the browser’s
+//!   event loop and returns immediately.
+//! - [`spawn_task`] and [`JoinHandle`], built on `wasm-bindgen-futures`.
+//! - [`sleep`], built on the browser’s `setTimeout`.
+//!
+//! Everything else in the crate, including channels, `join`, `select`, and the
+//! stream combinators, works the same way it does natively. The exceptions are
+//! APIs which need Tokio’s timer driver or the file system (`interval`,
+//! `IntervalStream`, `read_to_string`, and the timing-based `StreamExt`
+//! methods), which are not available in the browser.
+
+use std::time::Duration;
+
+use super::{JoinHandle, Priority, join_handle};
+use crate::trace;
+
+pub use tokio::task::yield_now;
+
+/// Run a future to completion on the browser’s event loop.
+///
+/// Unlike `block_on`, this cannot wait for the future to finish, because the
+/// browser’s main thread must never block, so it returns immediately and the
+/// future runs in the background. This is what you want for the top level of
+/// a program running in the browser, in place of `trpl::block_on`.
+pub fn start<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Spawn a new task on the browser’s event loop, returning a [`JoinHandle`] so
+/// you can await its result.
+///
+/// This mirrors `tokio::task::spawn`, which is what `trpl::spawn_task` is on
+/// other targets, except that the future does not need to be `Send`, since
+/// everything in the browser runs on one thread.
+pub fn spawn_task<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    let (task, handle) = join_handle::task(future);
+    wasm_bindgen_futures::spawn_local(task);
+    handle
+}
+
+/// Like [`spawn_task`]. The browser’s event loop has no notion of priority, so
+/// the hint is ignored.
+pub fn spawn_task_with_priority<F>(
+    _priority: Priority,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    spawn_task(future)
+}
+
+/// Wait until `duration` has elapsed, using the browser’s timers.
+pub fn sleep(duration: Duration) -> gloo_timers::future::TimeoutFuture {
+    trace::event!(?duration, "sleep started");
+    gloo_timers::future::sleep(duration)
+}
+
+/// `reqwest` uses the browser’s `fetch` here, so there is nothing to do.
+pub(crate) fn compat<F: Future>(future: F) -> F {
+    future
+}
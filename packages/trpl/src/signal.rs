@@ -0,0 +1,118 @@
+//! Waiting for signals from the operating system, for graceful shutdown.
+
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, stream::BoxStream};
+
+/// Wait until the user presses <kbd>Ctrl</kbd>+<kbd>C</kbd> (or the process
+/// otherwise receives an interrupt signal).
+///
+/// Once you start waiting, pressing <kbd>Ctrl</kbd>+<kbd>C</kbd> no longer
+/// kills the program immediately, so it is up to you to shut down. That is
+/// the point: race the program’s main loop against this, and clean up when it
+/// wins.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use trpl::{Either, StreamExt};
+///
+/// trpl::block_on(async {
+///     let ticks = async {
+///         let mut ticks = trpl::IntervalStream::new(trpl::interval(
+///             Duration::from_secs(1),
+///         ));
+///         while ticks.next().await.is_some() {
+///             println!("tick");
+///         }
+///     };
+///
+///     match trpl::select(ticks, trpl::ctrl_c()).await {
+///         Either::Left(()) => println!("The ticks ran out?!"),
+///         Either::Right(()) => println!("Shutting down gracefully…"),
+///     }
+/// });
+/// ```
+///
+/// For convenience in the examples, this panics instead of returning a
+/// [`Result`] if listening for the signal fails.
+pub async fn ctrl_c() {
+    crate::rt::ctrl_c()
+        .await
+        .expect("failed to listen for Ctrl+C");
+}
+
+/// A kind of signal to listen for with [`signal`]. These correspond to the
+/// Unix signals of the same names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SignalKind {
+    /// `SIGINT`, which is what pressing <kbd>Ctrl</kbd>+<kbd>C</kbd> sends.
+    Interrupt,
+    /// `SIGTERM`, the polite request to shut down which tools like `kill`,
+    /// `systemd`, and Docker send by default.
+    Terminate,
+    /// `SIGHUP`, sent when the terminal a program is running in goes away. Many
+    /// long-running programs also take it as a request to reload their
+    /// configuration.
+    Hangup,
+}
+
+/// Listen for signals of the given `kind`, as a stream which produces an item
+/// each time one arrives.
+///
+/// Signals like these are mostly a Unix thing. On other platforms, the stream
+/// never produces anything, so code which uses it still builds and runs; it
+/// just never hears about any signals. (For <kbd>Ctrl</kbd>+<kbd>C</kbd>, use
+/// [`ctrl_c`], which works everywhere.)
+///
+/// Since it is a stream, you can combine it with other sources of shutdown
+/// requests using [`merge`](crate::StreamExt::merge):
+///
+/// ```no_run
+/// use trpl::{SignalKind, StreamExt};
+///
+/// trpl::block_on(async {
+///     let mut shutdown = trpl::signal(SignalKind::Terminate)
+///         .merge(trpl::signal(SignalKind::Hangup))
+///         .merge(trpl::signal(SignalKind::Interrupt));
+///
+///     shutdown.next().await;
+///     println!("Shutting down gracefully…");
+/// });
+/// ```
+///
+/// For convenience in the examples, this panics instead of returning a
+/// [`Result`] if listening for the signal fails.
+pub fn signal(kind: SignalKind) -> Signal {
+    Signal {
+        inner: crate::rt::signal(kind).unwrap_or_else(|error| {
+            panic!("failed to listen for {kind:?}: {error}")
+        }),
+    }
+}
+
+/// A stream of signals of one kind. Created by [`signal`].
+pub struct Signal {
+    inner: BoxStream<'static, ()>,
+}
+
+impl Stream for Signal {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<()>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+impl fmt::Debug for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signal").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,260 @@
+//! Splitting one stream into a labeled sub-stream per key.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+use super::wakers::WakerSet;
+
+/// A stream of [`Group`]s, one for each distinct key. Created by
+/// [`StreamExt::group_by`].
+///
+/// [`StreamExt::group_by`]: super::StreamExt::group_by
+pub struct GroupBy<S: Stream, K, F> {
+    shared: Arc<Mutex<Shared<S, K, F>>>,
+    wakers: WakerSet,
+}
+
+/// All the items from a [`GroupBy`]’s upstream which have the same key.
+pub struct Group<S: Stream, K: Eq + Hash, F> {
+    key: K,
+    shared: Arc<Mutex<Shared<S, K, F>>>,
+    wakers: WakerSet,
+    slot: usize,
+}
+
+struct Shared<S: Stream, K, F> {
+    /// `None` once the upstream has finished.
+    stream: Option<Pin<Box<S>>>,
+    key_fn: F,
+    groups: HashMap<K, GroupState<S::Item>>,
+    /// Groups which have been created but not yet handed out by the
+    /// [`GroupBy`] stream.
+    new_groups: VecDeque<K>,
+    /// Whether the [`GroupBy`] itself has been dropped, in which case items
+    /// with new keys can be thrown away.
+    outer_dropped: bool,
+}
+
+struct GroupState<T> {
+    /// Where this group’s waker lives in the [`WakerSet`].
+    slot: usize,
+    buffer: VecDeque<T>,
+    /// Whether this group’s [`Group`] has been dropped, so its items can be
+    /// thrown away instead of buffered forever.
+    dropped: bool,
+}
+
+/// The [`GroupBy`] stream itself always uses the first slot.
+const OUTER: usize = 0;
+
+pub(super) fn group_by<S, K, F>(stream: S, key_fn: F) -> GroupBy<S, K, F>
+where
+    S: Stream,
+    K: Eq + Hash + Clone,
+    F: FnMut(&S::Item) -> K,
+{
+    GroupBy {
+        shared: Arc::new(Mutex::new(Shared {
+            stream: Some(Box::pin(stream)),
+            key_fn,
+            groups: HashMap::new(),
+            new_groups: VecDeque::new(),
+            outer_dropped: false,
+        })),
+        wakers: WakerSet::default(),
+    }
+}
+
+/// What happened when we pulled one item from upstream on someone’s behalf.
+enum Pulled<T> {
+    /// Nothing is ready yet.
+    Pending,
+    /// The upstream is finished.
+    Done,
+    /// The item belongs to the group in the slot doing the pulling.
+    Mine(T),
+    /// The item went to some other group (and was buffered, if need be).
+    Routed,
+}
+
+impl<S, K, F> Shared<S, K, F>
+where
+    S: Stream,
+    K: Eq + Hash + Clone,
+    F: FnMut(&S::Item) -> K,
+{
+    fn pull(&mut self, slot: usize, wakers: &WakerSet) -> Pulled<S::Item> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Pulled::Done;
+        };
+
+        let waker = wakers.waker();
+        let item =
+            match stream.as_mut().poll_next(&mut Context::from_waker(&waker)) {
+                Poll::Pending => return Pulled::Pending,
+                Poll::Ready(None) => {
+                    self.stream = None;
+                    // Everyone waiting needs to find out that we are done.
+                    waker.wake();
+                    return Pulled::Done;
+                }
+                Poll::Ready(Some(item)) => item,
+            };
+
+        let key = (self.key_fn)(&item);
+        match self.groups.get_mut(&key) {
+            Some(group) if group.slot == slot => Pulled::Mine(item),
+            Some(group) => {
+                if !group.dropped {
+                    group.buffer.push_back(item);
+                    wakers.wake(group.slot);
+                }
+                Pulled::Routed
+            }
+            None => {
+                if !self.outer_dropped {
+                    let slot = self.groups.len() + 1;
+                    self.groups.insert(
+                        key.clone(),
+                        GroupState {
+                            slot,
+                            buffer: VecDeque::from([item]),
+                            dropped: false,
+                        },
+                    );
+                    self.new_groups.push_back(key);
+                    wakers.wake(OUTER);
+                }
+                Pulled::Routed
+            }
+        }
+    }
+}
+
+impl<S, K, F> Stream for GroupBy<S, K, F>
+where
+    S: Stream,
+    K: Eq + Hash + Clone,
+    F: FnMut(&S::Item) -> K,
+{
+    type Item = (K, Group<S, K, F>);
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        self.wakers.register(OUTER, cx.waker());
+
+        loop {
+            if let Some(key) = shared.new_groups.pop_front() {
+                let slot = shared.groups[&key].slot;
+                let group = Group {
+                    key: key.clone(),
+                    shared: Arc::clone(&self.shared),
+                    wakers: self.wakers.clone(),
+                    slot,
+                };
+                return Poll::Ready(Some((key, group)));
+            }
+
+            match shared.pull(OUTER, &self.wakers) {
+                Pulled::Pending => return Poll::Pending,
+                Pulled::Done => return Poll::Ready(None),
+                Pulled::Mine(_) => {
+                    unreachable!("items always belong to a group")
+                }
+                Pulled::Routed => {}
+            }
+        }
+    }
+}
+
+impl<S, K, F> Group<S, K, F>
+where
+    S: Stream,
+    K: Eq + Hash,
+{
+    /// The key shared by every item in this group.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<S, K, F> Stream for Group<S, K, F>
+where
+    S: Stream,
+    K: Eq + Hash + Clone,
+    F: FnMut(&S::Item) -> K,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        self.wakers.register(self.slot, cx.waker());
+
+        loop {
+            let group = shared
+                .groups
+                .get_mut(&self.key)
+                .expect("a group’s state lives as long as the group");
+            if let Some(item) = group.buffer.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+
+            match shared.pull(self.slot, &self.wakers) {
+                Pulled::Pending => return Poll::Pending,
+                Pulled::Done => return Poll::Ready(None),
+                Pulled::Mine(item) => return Poll::Ready(Some(item)),
+                Pulled::Routed => {}
+            }
+        }
+    }
+}
+
+impl<S: Stream, K, F> Drop for GroupBy<S, K, F> {
+    fn drop(&mut self) {
+        // If the lock is poisoned, everything is going down anyway.
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.outer_dropped = true;
+        }
+    }
+}
+
+impl<S: Stream, K: Eq + Hash, F> Drop for Group<S, K, F> {
+    fn drop(&mut self) {
+        // If the lock is poisoned, everything is going down anyway.
+        let Ok(mut shared) = self.shared.lock() else {
+            return;
+        };
+        if let Some(group) = shared.groups.get_mut(&self.key) {
+            group.dropped = true;
+            group.buffer.clear();
+        }
+    }
+}
+
+impl<S: Stream, K, F> fmt::Debug for GroupBy<S, K, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupBy").finish_non_exhaustive()
+    }
+}
+
+impl<S: Stream, K: Eq + Hash + fmt::Debug, F> fmt::Debug for Group<S, K, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,104 @@
+//! Merging any number of streams, taking turns fairly.
+
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+/// Combine any number of streams into one, producing items from each as soon
+/// as they are ready, and taking turns between them.
+///
+/// Each time the merged stream is polled, it starts with the stream *after*
+/// the one which produced the previous item, and goes around in order from
+/// there. So a stream which is always ready cannot crowd out the others: when
+/// they are all ready, they each get one item in, round and round. A stream
+/// which finishes drops out, and the merged stream finishes once all of them
+/// have.
+///
+/// This is the fair way to merge more than two streams. Chaining
+/// [`StreamExt::merge`] calls, as in `a.merge(b).merge(c)`, gives `c` half the
+/// turns and `a` and `b` only a quarter each, since the outer `merge` only
+/// alternates between `c` and the inner merged stream.
+///
+/// ```
+/// use trpl::StreamExt;
+///
+/// trpl::block_on(async {
+///     // All three are always ready, but each still gets its turn.
+///     let merged = trpl::merge_all([
+///         trpl::stream_from_iter(vec!["a"; 3]),
+///         trpl::stream_from_iter(vec!["b"; 3]),
+///         trpl::stream_from_iter(vec!["c"; 3]),
+///     ]);
+///
+///     let order: Vec<_> = merged.collect().await;
+///     assert_eq!(order, ["a", "b", "c", "a", "b", "c", "a", "b", "c"]);
+/// });
+/// ```
+///
+/// [`StreamExt::merge`]: super::StreamExt::merge
+pub fn merge_all<I>(streams: I) -> MergeAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Stream,
+{
+    MergeAll {
+        streams: streams.into_iter().map(Box::pin).collect(),
+        next: 0,
+    }
+}
+
+/// A stream which takes turns producing items from several streams. Created by
+/// [`merge_all`].
+pub struct MergeAll<S> {
+    /// The streams which have not finished yet, in the order they take turns.
+    streams: Vec<Pin<Box<S>>>,
+    /// Which stream gets the first look the next time around.
+    next: usize,
+}
+
+impl<S: Stream> Stream for MergeAll<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        let mut checked = 0;
+        while checked < self.streams.len() {
+            let index = (self.next + checked) % self.streams.len();
+            match self.streams[index].as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    self.next = (index + 1) % self.streams.len();
+                    return Poll::Ready(Some(item));
+                }
+                Poll::Ready(None) => {
+                    // Everything after it moves up a slot, so the stream
+                    // which was next in line is now at `index`. Start going
+                    // around again from there.
+                    self.streams.remove(index);
+                    self.next = index;
+                    checked = 0;
+                }
+                Poll::Pending => checked += 1,
+            }
+        }
+
+        if self.streams.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S> fmt::Debug for MergeAll<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeAll")
+            .field("remaining", &self.streams.len())
+            .finish_non_exhaustive()
+    }
+}
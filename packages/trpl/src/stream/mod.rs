@@ -0,0 +1,802 @@
+//! Working with streams: the [`StreamExt`] trait and the types its methods
+//! return.
+//!
+//! Most of `StreamExt` forwards to [`tokio_stream::StreamExt`], so the basics
+//! behave exactly as they do there. We supply our own trait, rather than just
+//! re-exporting that one, so that we can add the extra combinators the book
+//! uses for teaching without asking readers to import a second trait.
+
+mod group_by;
+mod merge_all;
+mod partition;
+mod wakers;
+
+use std::{
+    any::Any, hash::Hash, panic::AssertUnwindSafe, pin::pin, time::Duration,
+};
+
+use futures::{Stream, TryStream, future::Either, stream::FuturesUnordered};
+#[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+use tokio::time::Interval;
+#[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+use tokio_stream::Elapsed;
+use tokio_stream::FromStream;
+
+pub use futures::stream::Peekable;
+pub use group_by::{Group, GroupBy};
+pub use merge_all::{MergeAll, merge_all};
+pub use partition::Partition;
+
+/// Extension methods for working with [`Stream`]s, automatically available on
+/// every stream once the trait is in scope: `use trpl::StreamExt;`.
+pub trait StreamExt: Stream {
+    /// Get the next item from the stream, or `None` once it is finished.
+    fn next(&mut self) -> impl Future<Output = Option<Self::Item>>
+    where
+        Self: Unpin,
+    {
+        tokio_stream::StreamExt::next(self)
+    }
+
+    /// Wait for whichever comes first: the next item from the stream, or
+    /// `other` finishing. Produces `Either::Left` with the result of
+    /// [`next`](StreamExt::next) in the first case, and `Either::Right` with
+    /// the output of `other` in the second.
+    ///
+    /// If `other` wins, no item is lost: the stream just has not produced one
+    /// yet, so you can keep calling `next` (or `next_or`) on it afterward.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use trpl::{Either, ReceiverStream, StreamExt};
+    ///
+    /// trpl::block_on(async {
+    ///     let (tx, rx) = trpl::channel();
+    ///     let mut messages = ReceiverStream::new(rx);
+    ///
+    ///     // Nothing has been sent yet, so the timer wins…
+    ///     let timer = trpl::sleep(Duration::from_millis(10));
+    ///     match messages.next_or(timer).await {
+    ///         Either::Left(message) => println!("got {message:?}"),
+    ///         Either::Right(()) => println!("still waiting…"),
+    ///     }
+    ///
+    ///     // …but the next time around, a message is ready right away.
+    ///     tx.send("hello").unwrap();
+    ///     let timer = trpl::sleep(Duration::from_millis(10));
+    ///     let outcome = messages.next_or(timer).await;
+    ///     assert!(matches!(outcome, Either::Left(Some("hello"))));
+    /// });
+    /// ```
+    fn next_or<F>(
+        &mut self,
+        other: F,
+    ) -> impl Future<Output = Either<Option<Self::Item>, F::Output>>
+    where
+        F: Future,
+        Self: Unpin,
+    {
+        crate::select(StreamExt::next(self), other)
+    }
+
+    /// Get the next item from a stream of `Result`s: `Ok(Some(item))` for an
+    /// `Ok` item, `Err(error)` for an `Err` item, and `Ok(None)` once the
+    /// stream is finished.
+    fn try_next<T, E>(&mut self) -> impl Future<Output = Result<Option<T>, E>>
+    where
+        Self: Stream<Item = Result<T, E>> + Unpin,
+    {
+        tokio_stream::StreamExt::try_next(self)
+    }
+
+    /// Transform each item with `f`.
+    fn map<T, F>(self, f: F) -> impl Stream<Item = T>
+    where
+        F: FnMut(Self::Item) -> T,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::map(self, f)
+    }
+
+    /// Call `f` with a reference to each item as it goes by, without changing
+    /// anything. This is handy for printing what is happening partway through
+    /// a chain of combinators:
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let total = trpl::stream_from_iter(1..=3)
+    ///         .inspect(|n| println!("before: {n}"))
+    ///         .map(|n| n * 10)
+    ///         .inspect(|n| println!("after: {n}"))
+    ///         .fold(0, |total, n| total + n)
+    ///         .await;
+    ///     assert_eq!(total, 60);
+    /// });
+    /// ```
+    fn inspect<F>(self, f: F) -> impl Stream<Item = Self::Item>
+    where
+        F: FnMut(&Self::Item),
+        Self: Sized,
+    {
+        futures::StreamExt::inspect(self, f)
+    }
+
+    /// Like [`inspect`](StreamExt::inspect), but for a stream of `Result`s:
+    /// call `f` with a reference to each error as it goes by, and pass `Ok`
+    /// items along untouched.
+    fn inspect_err<T, E, F>(self, f: F) -> impl Stream<Item = Result<T, E>>
+    where
+        F: FnMut(&E),
+        Self: Stream<Item = Result<T, E>> + Sized,
+    {
+        futures::TryStreamExt::inspect_err(self, f)
+    }
+
+    /// Transform each item with `f` until it returns `None`, then stop.
+    fn map_while<T, F>(self, f: F) -> impl Stream<Item = T>
+    where
+        F: FnMut(Self::Item) -> Option<T>,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::map_while(self, f)
+    }
+
+    /// Transform each item with the async function `f`, waiting for each
+    /// result before moving on to the next item.
+    ///
+    /// `f` can be an async closure, like `async |item| { … }`, or a regular
+    /// closure which returns a future, like `|item| async move { … }`.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let mut total = 0;
+    ///     let running_totals: Vec<u32> = trpl::stream_from_iter([1, 2, 3])
+    ///         .then(async |n| {
+    ///             trpl::sleep(Duration::from_millis(1)).await;
+    ///             total += n;
+    ///             total
+    ///         })
+    ///         .collect()
+    ///         .await;
+    ///     assert_eq!(running_totals, vec![1, 3, 6]);
+    /// });
+    /// ```
+    fn then<T, F>(self, f: F) -> impl Stream<Item = T>
+    where
+        F: AsyncFnMut(Self::Item) -> T,
+        Self: Sized,
+    {
+        let state = (Box::pin(self), f);
+        futures::stream::unfold(state, |(mut stream, mut f)| async move {
+            let item = StreamExt::next(&mut stream).await?;
+            let output = f(item).await;
+            Some((output, (stream, f)))
+        })
+    }
+
+    /// Combine two streams into one, producing items from either as soon as
+    /// they are ready.
+    ///
+    /// The merged stream takes turns checking which of the two goes first, so
+    /// even if one of them is always ready, like a fast interval, the other
+    /// still gets every second item in whenever it has one. To merge more than
+    /// two streams fairly, use [`merge_all`] rather than chaining `merge`
+    /// calls.
+    fn merge<U>(self, other: U) -> impl Stream<Item = Self::Item>
+    where
+        U: Stream<Item = Self::Item>,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::merge(self, other)
+    }
+
+    /// Keep only the items for which `f` returns `true`.
+    fn filter<F>(self, f: F) -> impl Stream<Item = Self::Item>
+    where
+        F: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::filter(self, f)
+    }
+
+    /// Like [`filter`](StreamExt::filter), but with an async function, for
+    /// when deciding whether to keep an item means waiting for something.
+    fn filter_async<F>(self, f: F) -> impl Stream<Item = Self::Item>
+    where
+        F: AsyncFnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        let state = (Box::pin(self), f);
+        futures::stream::unfold(state, |(mut stream, mut f)| async move {
+            loop {
+                let item = StreamExt::next(&mut stream).await?;
+                if f(&item).await {
+                    return Some((item, (stream, f)));
+                }
+            }
+        })
+    }
+
+    /// Transform each item with `f`, keeping only the `Some` results.
+    fn filter_map<T, F>(self, f: F) -> impl Stream<Item = T>
+    where
+        F: FnMut(Self::Item) -> Option<T>,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::filter_map(self, f)
+    }
+
+    /// Make the stream keep returning `None` once it has returned `None` once.
+    fn fuse(self) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::fuse(self)
+    }
+
+    /// Produce at most the first `n` items.
+    fn take(self, n: usize) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::take(self, n)
+    }
+
+    /// Produce items as long as `f` returns `true` for them, then stop.
+    fn take_while<F>(self, f: F) -> impl Stream<Item = Self::Item>
+    where
+        F: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::take_while(self, f)
+    }
+
+    /// Skip the first `n` items.
+    fn skip(self, n: usize) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::skip(self, n)
+    }
+
+    /// Skip items as long as `f` returns `true` for them.
+    fn skip_while<F>(self, f: F) -> impl Stream<Item = Self::Item>
+    where
+        F: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::skip_while(self, f)
+    }
+
+    /// Check whether `f` returns `true` for every item, stopping at the first
+    /// item for which it does not.
+    fn all<F>(&mut self, f: F) -> impl Future<Output = bool>
+    where
+        Self: Unpin,
+        F: FnMut(Self::Item) -> bool,
+    {
+        tokio_stream::StreamExt::all(self, f)
+    }
+
+    /// Check whether `f` returns `true` for any item, stopping at the first
+    /// item for which it does.
+    fn any<F>(&mut self, f: F) -> impl Future<Output = bool>
+    where
+        Self: Unpin,
+        F: FnMut(Self::Item) -> bool,
+    {
+        tokio_stream::StreamExt::any(self, f)
+    }
+
+    /// Produce all of this stream’s items, then all of `other`’s.
+    fn chain<U>(self, other: U) -> impl Stream<Item = Self::Item>
+    where
+        U: Stream<Item = Self::Item>,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::chain(self, other)
+    }
+
+    /// Combine every item into one value, starting with `init`.
+    fn fold<B, F>(self, init: B, f: F) -> impl Future<Output = B>
+    where
+        Self: Sized,
+        F: FnMut(B, Self::Item) -> B,
+    {
+        tokio_stream::StreamExt::fold(self, init, f)
+    }
+
+    /// Gather every item into a collection, such as a `Vec` or a `String`.
+    ///
+    /// This is handy for checking or printing what a stream produces without
+    /// writing out a `while let` loop by hand. You usually need to say which
+    /// collection you want, just as with [`Iterator::collect`]:
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let doubled = trpl::stream_from_iter(1..=3).map(|n| n * 2);
+    ///     assert_eq!(doubled.collect::<Vec<_>>().await, vec![2, 4, 6]);
+    /// });
+    /// ```
+    fn collect<T>(self) -> impl Future<Output = T>
+    where
+        T: FromStream<Self::Item>,
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::collect(self)
+    }
+
+    /// Gather every item from a stream of `Result`s into a collection, stopping
+    /// at the first `Err` and producing that error instead.
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let all_ok = trpl::stream_from_iter([Ok::<_, String>(1), Ok(2)]);
+    ///     assert_eq!(all_ok.try_collect::<Vec<_>>().await, Ok(vec![1, 2]));
+    ///
+    ///     let one_bad = trpl::stream_from_iter([
+    ///         Ok(1),
+    ///         Err(String::from("oh no")),
+    ///         Ok(3),
+    ///     ]);
+    ///     assert_eq!(
+    ///         one_bad.try_collect::<Vec<_>>().await,
+    ///         Err(String::from("oh no"))
+    ///     );
+    /// });
+    /// ```
+    fn try_collect<C>(
+        self,
+    ) -> impl Future<Output = Result<C, <Self as TryStream>::Error>>
+    where
+        C: Default + Extend<<Self as TryStream>::Ok>,
+        Self: TryStream + Sized,
+    {
+        futures::TryStreamExt::try_collect(self)
+    }
+
+    /// Run the async function `f` on every item, with up to `limit` of the
+    /// calls running concurrently (or any number, if `limit` is `None`).
+    ///
+    /// Unlike a `while let` loop, this does not wait for one item to finish
+    /// before starting on the next: as soon as an item arrives, it starts a
+    /// call to `f` for it, as long as there are fewer than `limit` calls in
+    /// flight. Since the calls share `f`, it has to be an `AsyncFn`, which
+    /// captures things by shared reference.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     trpl::stream_from_iter(1..=10)
+    ///         .for_each_concurrent(Some(3), async |n| {
+    ///             trpl::sleep(Duration::from_millis(10)).await;
+    ///             println!("processed {n}");
+    ///         })
+    ///         .await;
+    /// });
+    /// ```
+    fn for_each_concurrent<F>(
+        self,
+        limit: Option<usize>,
+        f: F,
+    ) -> impl Future<Output = ()>
+    where
+        F: AsyncFn(Self::Item),
+        Self: Sized,
+    {
+        async move {
+            let mut stream = std::pin::pin!(self.fuse());
+            let mut running = FuturesUnordered::new();
+            let limit = limit.unwrap_or(usize::MAX);
+            let f = &f;
+
+            loop {
+                let has_room = running.len() < limit;
+                let idle = running.is_empty();
+                let next_item = async {
+                    if has_room {
+                        StreamExt::next(&mut stream).await
+                    } else {
+                        std::future::pending().await
+                    }
+                };
+                let next_done = async {
+                    if idle {
+                        std::future::pending().await
+                    } else {
+                        futures::StreamExt::next(&mut running).await
+                    }
+                };
+
+                match crate::select(next_item, next_done).await {
+                    Either::Left(Some(item)) => running.push(f(item)),
+                    Either::Left(None) => break,
+                    Either::Right(_) => {}
+                }
+            }
+
+            while futures::StreamExt::next(&mut running).await.is_some() {}
+        }
+    }
+
+    /// Run the async function `f` on each item from a stream of `Result`s, one
+    /// item at a time, stopping at the first error. That error can come either
+    /// from the stream itself or from `f`; either way, it is what the returned
+    /// future produces. If there are no errors, it produces `Ok(())` once the
+    /// stream is finished.
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let (tx, mut rx) = trpl::channel();
+    ///     let numbers = trpl::stream_from_iter(["1", "2", "three", "4"])
+    ///         .map(|s| s.parse::<u32>());
+    ///
+    ///     let result = numbers
+    ///         .try_for_each(|n| {
+    ///             let tx = tx.clone();
+    ///             async move {
+    ///                 tx.send(n).unwrap();
+    ///                 Ok(())
+    ///             }
+    ///         })
+    ///         .await;
+    ///
+    ///     assert!(result.is_err());
+    ///     drop(tx);
+    ///     assert_eq!(rx.recv().await, Some(1));
+    ///     assert_eq!(rx.recv().await, Some(2));
+    ///     assert_eq!(rx.recv().await, None);
+    /// });
+    /// ```
+    fn try_for_each<T, E, F, Fut>(
+        self,
+        f: F,
+    ) -> impl Future<Output = Result<(), E>>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        Self: Stream<Item = Result<T, E>> + Sized,
+    {
+        futures::TryStreamExt::try_for_each(self, f)
+    }
+
+    /// Produce an `Err(Elapsed)` item whenever the next item takes longer
+    /// than `duration` to arrive, and `Ok(item)` otherwise. Needs Tokio’s
+    /// timer, so only available with the `runtime-tokio` feature.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+    fn timeout(
+        self,
+        duration: Duration,
+    ) -> impl Stream<Item = Result<Self::Item, Elapsed>>
+    where
+        Self: Sized,
+    {
+        let items = tokio_stream::StreamExt::timeout(self, duration);
+        tokio_stream::StreamExt::map(items, |item| {
+            #[cfg(feature = "tracing")]
+            if item.is_err() {
+                crate::trace::event!("stream item timed out");
+            }
+            item
+        })
+    }
+
+    /// Produce items until `duration` has passed, and then finish, even if the
+    /// stream has more items to give. The clock starts when `take_for` is
+    /// called.
+    ///
+    /// This is a different tool from [`timeout`](StreamExt::timeout), which
+    /// puts a limit on how long *each* item may take, and produces an error
+    /// item whenever one is late, but keeps going afterward. `take_for` puts
+    /// one deadline on the stream as a whole, and quietly stops there.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use trpl::{IntervalStream, StreamExt};
+    ///
+    /// trpl::block_on(async {
+    ///     // Without `take_for`, this stream would never end.
+    ///     let interval = trpl::interval(Duration::from_millis(1));
+    ///     let ticks = IntervalStream::new(interval)
+    ///         .take_for(Duration::from_millis(20));
+    ///
+    ///     let count = ticks.fold(0, |count, _| count + 1).await;
+    ///     println!("got {count} ticks before the deadline");
+    /// });
+    /// ```
+    fn take_for(self, duration: Duration) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::take_until(self, crate::sleep(duration))
+    }
+
+    /// Like [`timeout`](StreamExt::timeout), but keeps producing an
+    /// `Err(Elapsed)` item on every tick of `interval` for as long as the
+    /// next item has not arrived. Needs Tokio’s timer, so only available with
+    /// the `runtime-tokio` feature.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+    fn timeout_repeating(
+        self,
+        interval: Interval,
+    ) -> impl Stream<Item = Result<Self::Item, Elapsed>>
+    where
+        Self: Sized,
+    {
+        let items = tokio_stream::StreamExt::timeout_repeating(self, interval);
+        tokio_stream::StreamExt::map(items, |item| {
+            #[cfg(feature = "tracing")]
+            if item.is_err() {
+                crate::trace::event!("stream item timed out");
+            }
+            item
+        })
+    }
+
+    /// Wait at least `duration` between items. Needs Tokio’s timer, so only
+    /// available with the `runtime-tokio` feature.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+    fn throttle(self, duration: Duration) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::throttle(self, duration)
+    }
+
+    /// Split time into windows of `period`, and at the end of each window,
+    /// produce only the most recent item which arrived during it, dropping
+    /// the rest. Windows in which nothing arrived produce nothing. If the
+    /// stream finishes partway through a window, its last item comes out right
+    /// away.
+    ///
+    /// Where [`throttle`](StreamExt::throttle) slows a stream down but keeps
+    /// every item, `sample` keeps up with a fast stream by skipping items, so
+    /// it suits values where only the latest one matters, like a sensor
+    /// reading or a progress update.
+    ///
+    /// ```
+    /// use std::{pin::pin, time::Duration};
+    /// use trpl::{ReceiverStream, StreamExt};
+    ///
+    /// trpl::block_on(async {
+    ///     let (tx, rx) = trpl::channel();
+    ///     trpl::spawn_task(async move {
+    ///         for percent in 0..=100 {
+    ///             tx.send(percent).unwrap();
+    ///             trpl::sleep(Duration::from_millis(1)).await;
+    ///         }
+    ///     });
+    ///
+    ///     let window = Duration::from_millis(20);
+    ///     let mut updates = pin!(ReceiverStream::new(rx).sample(window));
+    ///     while let Some(percent) = updates.next().await {
+    ///         println!("{percent}% done");
+    ///     }
+    /// });
+    /// ```
+    fn sample(self, period: Duration) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        let state = (Box::pin(self), false);
+        futures::stream::unfold(state, move |(mut stream, done)| async move {
+            if done {
+                return None;
+            }
+
+            let mut latest = None;
+            let mut window = pin!(crate::sleep(period));
+            loop {
+                let next = StreamExt::next(&mut stream);
+                match crate::select(next, window.as_mut()).await {
+                    Either::Left(Some(item)) => latest = Some(item),
+                    Either::Left(None) => {
+                        return latest.map(|item| (item, (stream, true)));
+                    }
+                    Either::Right(()) => match latest.take() {
+                        Some(item) => return Some((item, (stream, false))),
+                        None => window.set(crate::sleep(period)),
+                    },
+                }
+            }
+        })
+    }
+
+    /// Gather items into `Vec`s of up to `max_size` items, producing each one
+    /// when it is full or once `duration` has passed since its first item,
+    /// whichever comes first. Needs Tokio’s timer, so only available with the
+    /// `runtime-tokio` feature.
+    ///
+    /// Panics if `max_size` is zero.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "runtime-tokio"))]
+    #[track_caller]
+    fn chunks_timeout(
+        self,
+        max_size: usize,
+        duration: Duration,
+    ) -> impl Stream<Item = Vec<Self::Item>>
+    where
+        Self: Sized,
+    {
+        tokio_stream::StreamExt::chunks_timeout(self, max_size, duration)
+    }
+
+    /// Gather whatever items are ready right now into a `Vec` of up to
+    /// `max_size` items, without waiting for more to arrive.
+    ///
+    /// Each time the stream is polled, this waits for at least one item, and
+    /// then keeps taking items for as long as the stream has them ready
+    /// immediately. So when a producer is far ahead, the chunks come out full;
+    /// when it is slow, they hold just one item each. Compare
+    /// [`chunks_timeout`](StreamExt::chunks_timeout), which waits for a while
+    /// to try to fill each chunk.
+    ///
+    /// ```
+    /// use trpl::{ReceiverStream, StreamExt};
+    ///
+    /// trpl::block_on(async {
+    ///     let (tx, rx) = trpl::channel();
+    ///     for n in 1..=5 {
+    ///         tx.send(n).unwrap();
+    ///     }
+    ///     drop(tx);
+    ///
+    ///     let chunks: Vec<Vec<u32>> =
+    ///         ReceiverStream::new(rx).ready_chunks(2).collect().await;
+    ///     assert_eq!(chunks, vec![vec![1, 2], vec![3, 4], vec![5]]);
+    /// });
+    /// ```
+    ///
+    /// Panics if `max_size` is zero.
+    #[track_caller]
+    fn ready_chunks(
+        self,
+        max_size: usize,
+    ) -> impl Stream<Item = Vec<Self::Item>>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::ready_chunks(self, max_size)
+    }
+
+    /// Catch a panic while getting the next item, and produce it as an `Err`
+    /// item holding the panic’s payload, instead of unwinding through whoever
+    /// is reading the stream. Items produced normally come through as `Ok`.
+    ///
+    /// A stream which panicked partway through making an item cannot be
+    /// trusted to carry on from there, so the `Err` item is the last one: the
+    /// stream finishes right after it. As with
+    /// [`FutureExt::catch_unwind`](crate::FutureExt::catch_unwind), the stream
+    /// does not need to be [`UnwindSafe`](std::panic::UnwindSafe).
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let results: Vec<_> = trpl::stream_from_iter([1, 2, 0, 4])
+    ///         .map(|n| 12 / n)
+    ///         .catch_unwind()
+    ///         .collect()
+    ///         .await;
+    ///
+    ///     assert_eq!(results.len(), 3);
+    ///     assert_eq!(results[0].as_ref().ok(), Some(&12));
+    ///     assert_eq!(results[1].as_ref().ok(), Some(&6));
+    ///     assert!(results[2].is_err(), "dividing by zero panicked");
+    /// });
+    /// ```
+    fn catch_unwind(
+        self,
+    ) -> impl Stream<Item = Result<Self::Item, Box<dyn Any + Send>>>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::catch_unwind(AssertUnwindSafe(self))
+    }
+
+    /// Make it possible to look at the next item without taking it out of
+    /// the stream. See [`Peekable`].
+    fn peekable(self) -> Peekable<Self>
+    where
+        Self: Sized,
+    {
+        futures::StreamExt::peekable(self)
+    }
+
+    /// Split the stream into two: one with the items for which `predicate`
+    /// returns `true`, and one with the rest.
+    ///
+    /// The two halves share the original stream, and whichever one asks for
+    /// an item first pulls it from upstream and hands it over to the other
+    /// half if it belongs there. That means you should consume both halves
+    /// *concurrently*, for example with [`join`](crate::join): items for a
+    /// half nobody is reading from pile up in memory until someone does (or
+    /// until that half is dropped).
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let numbers = trpl::stream_from_iter(1..=6);
+    ///     let (evens, odds) = numbers.partition(|n| n % 2 == 0);
+    ///
+    ///     let (evens, odds): (Vec<_>, Vec<_>) =
+    ///         trpl::join(evens.collect(), odds.collect()).await;
+    ///     assert_eq!(evens, vec![2, 4, 6]);
+    ///     assert_eq!(odds, vec![1, 3, 5]);
+    /// });
+    /// ```
+    fn partition<F>(
+        self,
+        predicate: F,
+    ) -> (Partition<Self, F>, Partition<Self, F>)
+    where
+        F: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        partition::partition(self, predicate)
+    }
+
+    /// Split the stream into one labeled sub-stream per distinct key.
+    ///
+    /// The resulting stream produces a `(key, group)` pair the first time an
+    /// item with a new key shows up, where `group` is a [`Group`]: a stream
+    /// of all the items with that key, starting with that first one. As with
+    /// [`partition`](StreamExt::partition), the groups all share the original
+    /// stream, so read from them *concurrently*, for example by spawning a
+    /// task for each one. Items for a group nobody is reading from pile up
+    /// until someone does (or until that group is dropped).
+    ///
+    /// ```
+    /// use trpl::StreamExt;
+    ///
+    /// trpl::block_on(async {
+    ///     let lines = trpl::stream_from_iter([
+    ///         String::from("INFO starting"),
+    ///         String::from("WARN disk almost full"),
+    ///         String::from("INFO ready"),
+    ///     ]);
+    ///     let mut groups = lines.group_by(|line| line[..4].to_string());
+    ///
+    ///     let mut handles = vec![];
+    ///     while let Some((level, group)) = groups.next().await {
+    ///         handles.push(trpl::spawn_task(async move {
+    ///             let lines: Vec<_> = group.collect().await;
+    ///             (level, lines.len())
+    ///         }));
+    ///     }
+    ///
+    ///     let mut counts = vec![];
+    ///     for handle in handles {
+    ///         counts.push(handle.await.unwrap());
+    ///     }
+    ///     assert_eq!(
+    ///         counts,
+    ///         vec![(String::from("INFO"), 2), (String::from("WARN"), 1)]
+    ///     );
+    /// });
+    /// ```
+    fn group_by<K, F>(self, key_fn: F) -> GroupBy<Self, K, F>
+    where
+        K: Eq + Hash + Clone,
+        F: FnMut(&Self::Item) -> K,
+        Self: Sized,
+    {
+        group_by::group_by(self, key_fn)
+    }
+}
+
+impl<S: Stream + ?Sized> StreamExt for S {}
@@ -0,0 +1,140 @@
+//! Splitting one stream into two based on a predicate.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+use super::wakers::WakerSet;
+
+/// One half of a stream split by [`StreamExt::partition`].
+///
+/// [`StreamExt::partition`]: super::StreamExt::partition
+pub struct Partition<S: Stream, F> {
+    shared: Arc<Mutex<Shared<S, F>>>,
+    wakers: WakerSet,
+    side: usize,
+}
+
+struct Shared<S: Stream, F> {
+    /// `None` once the upstream has finished.
+    stream: Option<Pin<Box<S>>>,
+    predicate: F,
+    /// Items which have already been pulled from upstream, waiting for their
+    /// side to ask for them: index `0` for matches, `1` for the rest.
+    buffers: [VecDeque<S::Item>; 2],
+    /// Whether each side has been dropped, in which case its items can be
+    /// thrown away instead of buffered forever.
+    dropped: [bool; 2],
+}
+
+const MATCHING: usize = 0;
+const REST: usize = 1;
+
+pub(super) fn partition<S, F>(
+    stream: S,
+    predicate: F,
+) -> (Partition<S, F>, Partition<S, F>)
+where
+    S: Stream,
+    F: FnMut(&S::Item) -> bool,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        stream: Some(Box::pin(stream)),
+        predicate,
+        buffers: [VecDeque::new(), VecDeque::new()],
+        dropped: [false, false],
+    }));
+    let wakers = WakerSet::default();
+
+    let matching = Partition {
+        shared: Arc::clone(&shared),
+        wakers: wakers.clone(),
+        side: MATCHING,
+    };
+    let rest = Partition {
+        shared,
+        wakers,
+        side: REST,
+    };
+    (matching, rest)
+}
+
+impl<S, F> Stream for Partition<S, F>
+where
+    S: Stream,
+    F: FnMut(&S::Item) -> bool,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        let shared = &mut *shared;
+
+        if let Some(item) = shared.buffers[self.side].pop_front() {
+            return Poll::Ready(Some(item));
+        }
+
+        self.wakers.register(self.side, cx.waker());
+        let waker = self.wakers.waker();
+        let mut upstream_cx = Context::from_waker(&waker);
+
+        loop {
+            let Some(stream) = shared.stream.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            match stream.as_mut().poll_next(&mut upstream_cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    shared.stream = None;
+                    self.wakers.wake(1 - self.side);
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(item)) => {
+                    let side = if (shared.predicate)(&item) {
+                        MATCHING
+                    } else {
+                        REST
+                    };
+                    if side == self.side {
+                        return Poll::Ready(Some(item));
+                    }
+                    if !shared.dropped[side] {
+                        shared.buffers[side].push_back(item);
+                        self.wakers.wake(side);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: Stream, F> Drop for Partition<S, F> {
+    fn drop(&mut self) {
+        // If the lock is poisoned, everything is going down anyway.
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.dropped[self.side] = true;
+            shared.buffers[self.side].clear();
+        }
+    }
+}
+
+impl<S: Stream, F> fmt::Debug for Partition<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.side == MATCHING {
+            "matching"
+        } else {
+            "rest"
+        };
+        f.debug_struct("Partition").field("side", &side).finish()
+    }
+}
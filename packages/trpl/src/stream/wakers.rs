@@ -0,0 +1,74 @@
+//! Waking every consumer of a stream which is shared between several of them.
+
+use std::{
+    sync::{Arc, Mutex},
+    task::{Wake, Waker},
+};
+
+/// The wakers for everyone reading from one shared upstream stream, by slot.
+///
+/// When several streams take turns polling the same upstream, only the one
+/// which polled most recently would get woken when the upstream is ready. So
+/// instead, each consumer registers its waker here, and the upstream gets
+/// polled with [`WakerSet::waker`], which wakes *all* of them. Whoever gets
+/// there first takes the next item and hands it to its rightful owner.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WakerSet(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    wakers: Mutex<Vec<Option<Waker>>>,
+}
+
+impl WakerSet {
+    /// Record that the consumer in `slot` is waiting, and should be woken by
+    /// this waker.
+    pub(crate) fn register(&self, slot: usize, waker: &Waker) {
+        let mut wakers = self.0.wakers.lock().unwrap();
+        if wakers.len() <= slot {
+            wakers.resize(slot + 1, None);
+        }
+        match &mut wakers[slot] {
+            Some(existing) if existing.will_wake(waker) => {}
+            entry => *entry = Some(waker.clone()),
+        }
+    }
+
+    /// Wake the consumer in `slot`, if it is waiting.
+    pub(crate) fn wake(&self, slot: usize) {
+        let waker = self
+            .0
+            .wakers
+            .lock()
+            .unwrap()
+            .get_mut(slot)
+            .and_then(Option::take);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// A waker which wakes every consumer which is waiting.
+    pub(crate) fn waker(&self) -> Waker {
+        Waker::from(Arc::clone(&self.0))
+    }
+}
+
+impl Wake for Inner {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers: Vec<_> = self
+            .wakers
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
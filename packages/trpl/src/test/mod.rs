@@ -0,0 +1,20 @@
+//! Utilities for testing async code, including the book’s own listings.
+
+mod poll;
+// The records' timestamps need `Instant::now`, which panics in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+mod seeded;
+
+pub use poll::{
+    assert_next_pending, assert_next_ready, assert_pending, assert_ready,
+    poll_next_once, poll_once,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use recording::{Record, Recording, RecordingSender, recording_channel};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use seeded::spawn_seeded;
+#[cfg(not(target_arch = "wasm32"))]
+pub use seeded::with_seed;
@@ -0,0 +1,83 @@
+//! Polling futures and streams by hand, for unit tests.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+
+/// Poll `future` exactly once, with a waker which does nothing, and return the
+/// result.
+///
+/// This is the building block for [`assert_pending`] and [`assert_ready`]; use
+/// it directly if you want to `match` on the [`Poll`] yourself.
+pub fn poll_once<F: Future + ?Sized>(future: Pin<&mut F>) -> Poll<F::Output> {
+    let waker = crate::noop_waker();
+    future.poll(&mut Context::from_waker(&waker))
+}
+
+/// Poll `stream` exactly once for its next item, with a waker which does
+/// nothing, and return the result. The stream counterpart to [`poll_once`].
+pub fn poll_next_once<S: Stream + ?Sized>(
+    stream: Pin<&mut S>,
+) -> Poll<Option<S::Item>> {
+    let waker = crate::noop_waker();
+    stream.poll_next(&mut Context::from_waker(&waker))
+}
+
+/// Assert that polling `future` once returns [`Poll::Pending`].
+///
+/// ```
+/// use std::pin::pin;
+/// use trpl::test::{assert_pending, assert_ready};
+///
+/// let (tx, mut rx) = trpl::channel();
+/// let mut received = pin!(rx.recv());
+///
+/// assert_pending(received.as_mut());
+/// tx.send("hello").unwrap();
+/// assert_eq!(assert_ready(received.as_mut()), Some("hello"));
+/// ```
+#[track_caller]
+pub fn assert_pending<F: Future + ?Sized>(future: Pin<&mut F>) {
+    if poll_once(future).is_ready() {
+        panic!("expected the future to be pending, but it was ready");
+    }
+}
+
+/// Assert that polling `future` once returns [`Poll::Ready`], and return the
+/// output so you can make further assertions about it.
+#[track_caller]
+pub fn assert_ready<F: Future + ?Sized>(future: Pin<&mut F>) -> F::Output {
+    match poll_once(future) {
+        Poll::Ready(output) => output,
+        Poll::Pending => {
+            panic!("expected the future to be ready, but it was pending")
+        }
+    }
+}
+
+/// Assert that polling `stream` once for its next item returns
+/// [`Poll::Pending`]. The stream counterpart to [`assert_pending`].
+#[track_caller]
+pub fn assert_next_pending<S: Stream + ?Sized>(stream: Pin<&mut S>) {
+    if poll_next_once(stream).is_ready() {
+        panic!("expected the stream to be pending, but it was ready");
+    }
+}
+
+/// Assert that polling `stream` once for its next item returns
+/// [`Poll::Ready`], and return the item (or `None`, if the stream ended). The
+/// stream counterpart to [`assert_ready`].
+#[track_caller]
+pub fn assert_next_ready<S: Stream + ?Sized>(
+    stream: Pin<&mut S>,
+) -> Option<S::Item> {
+    match poll_next_once(stream) {
+        Poll::Ready(item) => item,
+        Poll::Pending => {
+            panic!("expected the stream to be ready, but it was pending")
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! A channel sender which remembers everything sent through it, and when.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::error::SendError;
+
+use crate::{Receiver, Sender};
+
+/// Create a channel like [`trpl::channel`](crate::channel), but with a
+/// [`RecordingSender`] which keeps a log of every value sent through it.
+pub fn recording_channel<T: Clone>() -> (RecordingSender<T>, Receiver<T>) {
+    let (tx, rx) = crate::channel();
+    (RecordingSender::new(tx), rx)
+}
+
+/// Wraps a [`Sender`], recording a copy of every value it sends along with how
+/// long after the `RecordingSender` was created it was sent.
+///
+/// That lets a test check both *what* went through a channel and *when*: for
+/// example, that a throttled stream really did drop the values which arrived
+/// too quickly, and spaced out the rest.
+///
+/// ```
+/// use std::time::Duration;
+/// use trpl::test::recording_channel;
+///
+/// trpl::block_on(async {
+///     let (tx, mut rx) = recording_channel();
+///     let recording = tx.recording();
+///
+///     tx.send("first").unwrap();
+///     trpl::sleep(Duration::from_millis(10)).await;
+///     tx.send("second").unwrap();
+///     drop(tx);
+///
+///     while rx.recv().await.is_some() {}
+///
+///     assert_eq!(recording.values(), vec!["first", "second"]);
+///     let records = recording.records();
+///     let gap = records[1].elapsed - records[0].elapsed;
+///     assert!(gap >= Duration::from_millis(10));
+/// });
+/// ```
+///
+/// Clones share the same log and the same start time.
+#[derive(Debug)]
+pub struct RecordingSender<T> {
+    tx: Sender<T>,
+    recording: Recording<T>,
+}
+
+impl<T: Clone> RecordingSender<T> {
+    /// Start recording everything sent with `tx`. Timestamps count from now.
+    pub fn new(tx: Sender<T>) -> RecordingSender<T> {
+        RecordingSender {
+            tx,
+            recording: Recording {
+                start: Instant::now(),
+                records: Arc::new(Mutex::new(Vec::new())),
+            },
+        }
+    }
+
+    /// Send a value, just like [`Sender::send`], recording it if the send
+    /// succeeds.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        // Hold the lock while sending, so that the order of the records always
+        // matches the order in which the values went into the channel, even
+        // when several clones are sending at once.
+        let mut records = self.recording.records.lock().unwrap();
+        self.tx.send(value.clone())?;
+        records.push(Record {
+            value,
+            elapsed: self.recording.start.elapsed(),
+        });
+        Ok(())
+    }
+
+    /// Get a handle to the log. Unlike the sender itself, this does not keep
+    /// the channel open, so you can drop the sender (to let the receiver
+    /// finish) and still check the records afterward.
+    pub fn recording(&self) -> Recording<T> {
+        self.recording.clone()
+    }
+}
+
+impl<T> Clone for RecordingSender<T> {
+    fn clone(&self) -> Self {
+        RecordingSender {
+            tx: self.tx.clone(),
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+/// The log of a [`RecordingSender`].
+#[derive(Debug)]
+pub struct Recording<T> {
+    start: Instant,
+    records: Arc<Mutex<Vec<Record<T>>>>,
+}
+
+impl<T: Clone> Recording<T> {
+    /// Every value sent so far, along with when it was sent, in order.
+    pub fn records(&self) -> Vec<Record<T>> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Every value sent so far, in order, without the timestamps.
+    pub fn values(&self) -> Vec<T> {
+        let records = self.records.lock().unwrap();
+        records.iter().map(|record| record.value.clone()).collect()
+    }
+}
+
+impl<T> Recording<T> {
+    /// How many values have been sent so far.
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// Whether nothing has been sent yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Recording<T> {
+    fn clone(&self) -> Self {
+        Recording {
+            start: self.start,
+            records: Arc::clone(&self.records),
+        }
+    }
+}
+
+/// One value sent through a [`RecordingSender`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record<T> {
+    /// The value which was sent.
+    pub value: T,
+    /// How long after the [`RecordingSender`] was created the value was sent.
+    pub elapsed: Duration,
+}
@@ -0,0 +1,280 @@
+//! A scheduler which picks the next task to run based on a seed.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    pin::{Pin, pin},
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Wake, Waker},
+};
+
+use crate::rt::{self, Priority};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Run a future to completion, like [`block_on`](crate::block_on), but with
+/// every task spawned inside it scheduled in an order determined by `seed`.
+///
+/// Whenever more than one task is ready to make progress, a real runtime picks
+/// which one goes next based on things like which thread got there first, so
+/// the interleaving of their output can change from run to run. Here, the
+/// choice comes from a pseudo-random number generator seeded with `seed`
+/// instead, and everything runs on the current thread. That means:
+///
+/// - Running the same code with the same seed again produces the same
+///   interleaving, so when a test catches an ordering bug, you can reproduce
+///   it.
+/// - Running it with different seeds produces different interleavings, so you
+///   can show that the order really is up to the scheduler.
+///
+/// Tasks created with [`spawn_task`](crate::spawn_task) go to this scheduler;
+/// the future passed in here is scheduled like any other task. The one
+/// exception is tasks spawned with a [`Priority::High`] hint (using
+/// [`spawn_task_with_priority`](crate::spawn_task_with_priority)): whenever
+/// any of those are ready, the scheduler picks one of them first. Timers still
+/// work, but since they fire based on real time, code which mixes timers with
+/// many ready tasks will not be perfectly reproducible.
+///
+/// ```
+/// use trpl::test::with_seed;
+///
+/// fn run(seed: u64) -> Vec<&'static str> {
+///     with_seed(seed, async {
+///         let (tx, mut rx) = trpl::channel();
+///         for name in ["a", "b", "c"] {
+///             let tx = tx.clone();
+///             trpl::spawn_task(async move { tx.send(name).unwrap() });
+///         }
+///         drop(tx);
+///
+///         let mut order = vec![];
+///         while let Some(name) = rx.recv().await {
+///             order.push(name);
+///         }
+///         order
+///     })
+/// }
+///
+/// assert_eq!(run(1), run(1));
+/// ```
+pub fn with_seed<F: Future>(seed: u64, future: F) -> F::Output {
+    rt::with_runtime(|| {
+        let scheduler = Arc::new(Scheduler::new(seed));
+        let _current = Current::enter(Arc::clone(&scheduler));
+        scheduler.run(future)
+    })
+}
+
+/// Makes a scheduler the current one until dropped, even if its future
+/// panics, so that later code on the same thread does not keep spawning onto
+/// it.
+struct Current {
+    scheduler: Arc<Scheduler>,
+    previous: Option<Arc<Scheduler>>,
+}
+
+impl Current {
+    fn enter(scheduler: Arc<Scheduler>) -> Current {
+        let previous = CURRENT.replace(Some(Arc::clone(&scheduler)));
+        Current {
+            scheduler,
+            previous,
+        }
+    }
+}
+
+impl Drop for Current {
+    fn drop(&mut self) {
+        CURRENT.set(self.previous.take());
+
+        // Tasks which never finished hold wakers, which hold the scheduler,
+        // which holds the tasks, so they would never be freed. Take them out
+        // before dropping them, since their destructors may spawn or wake
+        // other tasks.
+        let tasks = {
+            let mut state = self
+                .scheduler
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.ready.clear();
+            state.high_priority.clear();
+            std::mem::take(&mut state.tasks)
+        };
+        drop(tasks);
+    }
+}
+
+/// Hand `task` to the seeded scheduler, if we are inside [`with_seed`], or
+/// back to the caller if not, so it can go to the real runtime instead.
+pub(crate) fn spawn_seeded(task: Task, priority: Priority) -> Result<(), Task> {
+    CURRENT.with_borrow(|current| match current {
+        Some(scheduler) => {
+            scheduler.spawn(task, priority);
+            Ok(())
+        }
+        None => Err(task),
+    })
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Scheduler>>> =
+        const { RefCell::new(None) };
+}
+
+/// The id of the future passed to [`with_seed`] itself. Spawned tasks get ids
+/// counting up from there.
+const MAIN: usize = 0;
+
+struct Scheduler {
+    state: Mutex<State>,
+    /// Signaled whenever a task becomes ready, so that the scheduler can sleep
+    /// while waiting for a timer (or another thread) to wake something.
+    ready: Condvar,
+}
+
+struct State {
+    rng: SplitMix64,
+    next_id: usize,
+    /// The tasks which have been woken and not yet polled again. This is a
+    /// `Vec` rather than a set so that iteration order is deterministic.
+    ready: Vec<usize>,
+    /// The spawned tasks which have not finished yet. A task is missing from
+    /// here while it is being polled, and for good once it finishes.
+    tasks: HashMap<usize, Task>,
+    /// The ids of the unfinished tasks spawned with [`Priority::High`].
+    /// Everything else, including [`MAIN`], has [`Priority::Normal`].
+    high_priority: HashSet<usize>,
+}
+
+impl Scheduler {
+    fn new(seed: u64) -> Scheduler {
+        Scheduler {
+            state: Mutex::new(State {
+                rng: SplitMix64(seed),
+                next_id: MAIN + 1,
+                ready: vec![MAIN],
+                tasks: HashMap::new(),
+                high_priority: HashSet::new(),
+            }),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn spawn(self: &Arc<Self>, task: Task, priority: Priority) {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.tasks.insert(id, task);
+        if priority == Priority::High {
+            state.high_priority.insert(id);
+        }
+        state.ready.push(id);
+        self.ready.notify_one();
+    }
+
+    fn wake(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        if !state.ready.contains(&id) {
+            state.ready.push(id);
+            self.ready.notify_one();
+        }
+    }
+
+    fn run<F: Future>(self: &Arc<Self>, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let main_waker = self.waker(MAIN);
+
+        loop {
+            let (id, task) = self.next_ready();
+            match task {
+                None => {
+                    let mut cx = Context::from_waker(&main_waker);
+                    if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        return output;
+                    }
+                }
+
+                Some(mut task) => {
+                    let waker = self.waker(id);
+                    let mut cx = Context::from_waker(&waker);
+                    let poll = task.as_mut().poll(&mut cx);
+                    let mut state = self.state.lock().unwrap();
+                    if poll.is_pending() {
+                        state.tasks.insert(id, task);
+                    } else {
+                        state.high_priority.remove(&id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Block until at least one task is ready, then pick one of the ready ones
+    /// using the seeded generator, preferring high-priority ones. Returns
+    /// `None` as the task for [`MAIN`], since that future lives on
+    /// [`Scheduler::run`]’s stack instead of in the task map.
+    fn next_ready(&self) -> (usize, Option<Task>) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            while state.ready.is_empty() {
+                state = self.ready.wait(state).unwrap();
+            }
+
+            let urgent: Vec<usize> = (0..state.ready.len())
+                .filter(|&i| state.high_priority.contains(&state.ready[i]))
+                .collect();
+            let index = if urgent.is_empty() {
+                let len = state.ready.len();
+                state.rng.below(len)
+            } else {
+                urgent[state.rng.below(urgent.len())]
+            };
+            let id = state.ready.swap_remove(index);
+            if id == MAIN {
+                return (id, None);
+            }
+
+            // A task can be woken after it has already finished; skip it.
+            if let Some(task) = state.tasks.remove(&id) {
+                return (id, Some(task));
+            }
+        }
+    }
+
+    fn waker(self: &Arc<Self>, id: usize) -> Waker {
+        Waker::from(Arc::new(TaskWaker {
+            id,
+            scheduler: Arc::clone(self),
+        }))
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    scheduler: Arc<Scheduler>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.scheduler.wake(self.id);
+    }
+}
+
+/// A tiny, fast, *deterministic* pseudo-random number generator. This is not
+/// suitable for anything but picking task orders!
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
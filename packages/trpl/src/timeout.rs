@@ -0,0 +1,79 @@
+//! Timeouts which hand back the future instead of canceling it.
+
+use std::{error::Error, fmt, future::Future, pin::Pin, time::Duration};
+
+use futures::future::Either;
+
+use crate::trace;
+
+/// Wait up to `max_time` for `future` to finish. If it does, produce its
+/// output as `Ok`. If it does not, produce `Err(Timeout)`, which holds the
+/// unfinished future so you can decide what to do with it.
+///
+/// A plain timeout built on [`select`](crate::select), like the one we write
+/// in Chapter 17, *cancels* the slow future by dropping it. That is often what
+/// you want, but not always: maybe the work is almost done, and it would be a
+/// shame to throw it away and start over. Since `timeout_resumable` gives the
+/// future back, you can keep waiting on it (it picks up right where it left
+/// off), try again with a longer limit, or drop it after all.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// trpl::block_on(async {
+///     let slow = async {
+///         trpl::sleep(Duration::from_millis(50)).await;
+///         "finally done"
+///     };
+///
+///     let limit = Duration::from_millis(1);
+///     let message = match trpl::timeout_resumable(slow, limit).await {
+///         Ok(message) => message,
+///         Err(timeout) => {
+///             println!("Taking a while; giving it one more chance…");
+///             timeout.remaining_future.await
+///         }
+///     };
+///
+///     assert_eq!(message, "finally done");
+/// });
+/// ```
+pub async fn timeout_resumable<F: Future>(
+    future: F,
+    max_time: Duration,
+) -> Result<F::Output, Timeout<F>> {
+    // Box the future so that we can pin it while polling it here, but still
+    // move it back out to the caller afterward.
+    let mut future = Box::pin(future);
+    match crate::select(future.as_mut(), crate::sleep(max_time)).await {
+        Either::Left(output) => Ok(output),
+        Either::Right(()) => {
+            trace::event!(?max_time, "timed out");
+            Err(Timeout {
+                remaining_future: future,
+            })
+        }
+    }
+}
+
+/// The error from [`timeout_resumable`] when the future did not finish in
+/// time.
+pub struct Timeout<F> {
+    /// The future which timed out, exactly as far along as it got. You can
+    /// `.await` it directly, or pass it to [`timeout_resumable`] again.
+    pub remaining_future: Pin<Box<F>>,
+}
+
+impl<F> fmt::Debug for Timeout<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout").finish_non_exhaustive()
+    }
+}
+
+impl<F> fmt::Display for Timeout<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the future did not finish in time")
+    }
+}
+
+impl<F> Error for Timeout<F> {}
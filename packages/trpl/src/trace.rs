@@ -0,0 +1,31 @@
+//! Optional instrumentation, turned on with the `tracing` feature.
+//!
+//! With the feature enabled, `trpl` emits [`tracing`] spans and events (all
+//! with the target `trpl`) as tasks are spawned and finish, as timers start
+//! and fire, as `rpc_channel` calls go back and forth, and as timeouts expire.
+//! Install any `tracing` subscriber to watch them. Without the feature,
+//! everything here compiles away to nothing.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+/// Emit a trace-level event with the `trpl` target, if the `tracing` feature
+/// is enabled. Takes the same arguments as `tracing::trace!`.
+macro_rules! event {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!(target: "trpl", $($arg)+);
+    }};
+}
+
+pub(crate) use event;
+
+/// Create the span a newly spawned task runs in, with a unique id so that
+/// output from different tasks can be told apart.
+#[cfg(feature = "tracing")]
+pub(crate) fn task_span() -> tracing::Span {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    tracing::trace_span!(target: "trpl", "task", id)
+}
@@ -35,6 +35,35 @@ fn re_exported_block_on_works() {
     assert_eq!(val, "Hello");
 }
 
+/// `start` is the browser-friendly entry point; everywhere else it has to
+/// behave exactly like `block_on`.
+#[test]
+fn start_works() {
+    let (tx, mut rx) = trpl::channel();
+    trpl::start(async move {
+        trpl::sleep(Duration::from_micros(1)).await;
+        tx.send("Hello").unwrap();
+    });
+
+    assert_eq!(rx.try_recv(), Ok("Hello"));
+}
+
+/// `block_on` hands back the future's output untouched, so a `main` which
+/// returns a `Result` can return it directly and get Rust's usual error report
+/// and exit code.
+#[test]
+fn block_on_fallible_main() {
+    fn main() -> Result<(), std::io::Error> {
+        trpl::block_on(async {
+            trpl::read_to_string("tests/integration/missing.txt").await?;
+            Ok(())
+        })
+    }
+
+    let error = main().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
 #[test]
 fn re_exported_spawn_works() {
     let result = trpl::block_on(async {
@@ -46,6 +75,152 @@ fn re_exported_spawn_works() {
     assert_eq!(result, vec!["Hello", "Goodbye"]);
 }
 
+/// Every runtime backend has to report panics and cancellation the same way
+/// Tokio does, via the `JoinError`.
+mod join_handle {
+    use super::*;
+
+    #[test]
+    fn panic() {
+        let error = trpl::block_on(async {
+            trpl::spawn_task(async { panic!("oh no") })
+                .await
+                .unwrap_err()
+        });
+
+        assert!(error.is_panic());
+        assert_eq!(error.into_panic().downcast_ref(), Some(&"oh no"));
+    }
+
+    #[test]
+    fn abort() {
+        let error = trpl::block_on(async {
+            let handle = trpl::spawn_task(async {
+                trpl::sleep(Duration::from_secs(10)).await;
+            });
+            handle.abort();
+            handle.await.unwrap_err()
+        });
+
+        assert!(error.is_cancelled());
+    }
+
+    #[test]
+    fn join_timeout() {
+        trpl::block_on(async {
+            let mut quick = trpl::spawn_task(async { "done" });
+            let result = quick.join_timeout(Duration::from_secs(5)).await;
+            assert_eq!(result.unwrap().unwrap(), "done");
+
+            let mut slow = trpl::spawn_task(async {
+                trpl::sleep(Duration::from_millis(50)).await;
+                "slow"
+            });
+            assert!(slow.join_timeout(Duration::ZERO).await.is_none());
+            assert_eq!(slow.await.unwrap(), "slow");
+        });
+    }
+
+    #[test]
+    fn dropping_does_not_cancel() {
+        let result = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            drop(trpl::spawn_task(async move {
+                trpl::sleep(Duration::from_millis(1)).await;
+                tx.send("still ran").unwrap();
+            }));
+            rx.recv().await
+        });
+
+        assert_eq!(result, Some("still ran"));
+    }
+}
+
+mod enter {
+    use super::*;
+
+    #[test]
+    fn timers_created_outside_block_on() {
+        let (short, long) = {
+            let _guard = trpl::enter();
+            (
+                trpl::sleep(Duration::from_millis(1)),
+                trpl::sleep(Duration::from_millis(5)),
+            )
+        };
+
+        // Each one can be driven by a different `block_on` call.
+        trpl::block_on(short);
+        trpl::block_on(long);
+    }
+}
+
+mod handle {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn available_inside_block_on() {
+        let handle = trpl::block_on(async { trpl::Handle::try_current() });
+        assert!(handle.is_some());
+    }
+
+    #[test]
+    fn available_inside_spawned_tasks() {
+        let handle = trpl::block_on(async {
+            trpl::spawn_task(async { trpl::Handle::try_current() }).await
+        });
+        assert!(handle.unwrap().is_some());
+    }
+
+    #[test]
+    fn unavailable_outside_block_on() {
+        assert!(trpl::Handle::try_current().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "must be called inside `trpl::block_on`")]
+    fn current_panics_outside_block_on() {
+        trpl::Handle::current();
+    }
+
+    #[test]
+    fn spawn_from_sync_code() {
+        let result = trpl::block_on(async {
+            let handle = trpl::Handle::current();
+            let task = thread::spawn(move || {
+                handle.spawn(async {
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    "from a callback"
+                })
+            })
+            .join()
+            .unwrap();
+
+            task.await.unwrap()
+        });
+
+        assert_eq!(result, "from a callback");
+    }
+
+    #[test]
+    fn block_on_from_sync_code() {
+        let result = trpl::block_on(async {
+            let handle = trpl::Handle::current();
+            thread::spawn(move || {
+                handle.block_on(async {
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    "from a callback"
+                })
+            })
+            .join()
+            .unwrap()
+        });
+
+        assert_eq!(result, "from a callback");
+    }
+}
+
 #[test]
 fn re_exported_sleep_works() {
     let val = trpl::block_on(async {
@@ -99,6 +274,27 @@ mod re_exported_join_apis_work {
         assert_eq!(result, (1, 2, 3));
     }
 
+    #[test]
+    fn join4_and_join5_fns() {
+        let result = trpl::block_on(async {
+            trpl::join4(async { 1 }, async { 2 }, async { 3 }, async { 4 })
+                .await
+        });
+        assert_eq!(result, (1, 2, 3, 4));
+
+        let result = trpl::block_on(async {
+            trpl::join5(
+                async { 1 },
+                async { "two" },
+                async { 3.0 },
+                async { '4' },
+                async { [5] },
+            )
+            .await
+        });
+        assert_eq!(result, (1, "two", 3.0, '4', [5]));
+    }
+
     #[test]
     fn join_all_fn() {
         let result = trpl::block_on(async {
@@ -125,6 +321,120 @@ mod re_exported_join_apis_work {
         );
     }
 
+    #[test]
+    fn join_all_limited() {
+        use std::cell::Cell;
+
+        let running = Cell::new(0);
+        let most_running = Cell::new(0);
+
+        let outputs = trpl::block_on(async {
+            let futures = [5, 1, 3, 2, 4, 1, 2].map(|ms| {
+                let running = &running;
+                let most_running = &most_running;
+                async move {
+                    running.set(running.get() + 1);
+                    most_running.set(most_running.get().max(running.get()));
+                    trpl::sleep(Duration::from_millis(ms)).await;
+                    running.set(running.get() - 1);
+                    ms
+                }
+            });
+            trpl::join_all_limited(3, futures).await
+        });
+
+        assert_eq!(outputs, vec![5, 1, 3, 2, 4, 1, 2]);
+        assert_eq!(most_running.get(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn join_all_limited_with_zero_limit() {
+        trpl::block_on(trpl::join_all_limited(0, [async {}]));
+    }
+
+    #[test]
+    fn retry() {
+        let (result, attempts) = trpl::block_on(async {
+            let mut attempts = 0;
+            let result = trpl::retry(3, async || {
+                attempts += 1;
+                Err::<(), _>(attempts)
+            })
+            .await;
+            (result, attempts)
+        });
+
+        assert_eq!(result, Err(3));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn race_ok() {
+        let (first_ok, all_failed) = trpl::block_on(async {
+            let futures = [(1, false), (20, true), (10, true)].map(
+                |(ms, succeed)| async move {
+                    trpl::sleep(Duration::from_millis(ms)).await;
+                    if succeed { Ok(ms) } else { Err(ms) }
+                },
+            );
+            let first_ok = trpl::race_ok(futures).await;
+
+            let futures = [20, 1, 10].map(|ms| async move {
+                trpl::sleep(Duration::from_millis(ms)).await;
+                Err::<(), _>(ms)
+            });
+            let all_failed = trpl::race_ok(futures).await;
+
+            (first_ok, all_failed)
+        });
+
+        assert_eq!(first_ok, Ok(10));
+        assert_eq!(all_failed, Err(vec![20, 1, 10]));
+    }
+
+    #[test]
+    fn join_with_progress() {
+        use trpl::Progress;
+
+        let (outputs, progress) = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            let futures = [20, 1, 10].map(|ms| async move {
+                trpl::sleep(Duration::from_millis(ms)).await;
+                ms
+            });
+
+            let outputs = trpl::join_with_progress(futures, tx).await;
+            let mut progress = vec![];
+            while let Some(event) = rx.recv().await {
+                progress.push(event);
+            }
+            (outputs, progress)
+        });
+
+        assert_eq!(outputs, vec![20, 1, 10]);
+        assert_eq!(
+            progress,
+            vec![
+                Progress {
+                    index: 1,
+                    completed: 1,
+                    total: 3
+                },
+                Progress {
+                    index: 2,
+                    completed: 2,
+                    total: 3
+                },
+                Progress {
+                    index: 0,
+                    completed: 3,
+                    total: 3
+                },
+            ]
+        );
+    }
+
     #[test]
     fn join_macro() {
         let result = trpl::block_on(async {
@@ -191,6 +501,95 @@ fn race_continues_to_work() {
     assert!(matches!(val, Either::Right(Fast)));
 }
 
+mod defer {
+    use std::panic::AssertUnwindSafe;
+
+    use super::*;
+
+    #[test]
+    fn run() {
+        let events = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            let cleanup = trpl::defer({
+                let tx = tx.clone();
+                move || async move {
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    tx.send("cleanup").unwrap();
+                }
+            });
+
+            tx.send("work").unwrap();
+            cleanup.run().await;
+            tx.send("after").unwrap();
+            drop(tx);
+
+            let mut events = vec![];
+            while let Some(event) = rx.recv().await {
+                events.push(event);
+            }
+            events
+        });
+
+        assert_eq!(events, vec!["work", "cleanup", "after"]);
+    }
+
+    #[test]
+    fn runs_on_cancellation() {
+        let event = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            let work = async move {
+                let _cleanup = trpl::defer(move || async move {
+                    tx.send("cleanup").unwrap();
+                });
+                trpl::sleep(Duration::from_secs(10)).await;
+            };
+
+            trpl::select(work, trpl::sleep(Duration::from_millis(1))).await;
+            rx.recv().await
+        });
+
+        assert_eq!(event, Some("cleanup"));
+    }
+
+    #[test]
+    fn cancel() {
+        let event = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel::<&str>();
+            trpl::defer(move || async move {
+                tx.send("cleanup").unwrap();
+            })
+            .cancel();
+            rx.recv().await
+        });
+
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn runs_outside_a_runtime() {
+        let (tx, mut rx) = trpl::channel();
+        drop(trpl::defer(move || async move {
+            tx.send("cleanup").unwrap();
+        }));
+
+        assert_eq!(rx.try_recv(), Ok("cleanup"));
+    }
+
+    #[test]
+    fn skipped_when_panicking_outside_a_runtime() {
+        let (tx, mut rx) = trpl::channel();
+        let panicked = std::panic::catch_unwind(AssertUnwindSafe(move || {
+            let _cleanup = trpl::defer(move || async move {
+                tx.send("cleanup").unwrap();
+            });
+            panic!("before the cleanup");
+        }));
+
+        assert!(panicked.is_err());
+        assert!(rx.try_recv().is_err());
+    }
+}
+
 #[test]
 fn yield_now() {
     let result = trpl::block_on(async {
@@ -201,6 +600,140 @@ fn yield_now() {
     assert_eq!(result, "done");
 }
 
+#[test]
+fn noop_waker() {
+    use std::{pin::pin, task::Context};
+
+    let waker = trpl::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(async { "done" });
+
+    assert_eq!(
+        future.as_mut().poll(&mut cx),
+        std::task::Poll::Ready("done")
+    );
+}
+
+#[test]
+fn waker_fn() {
+    use std::{
+        pin::pin,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        task::{Context, Poll},
+    };
+
+    let wakes = Arc::new(AtomicUsize::new(0));
+    let waker = trpl::waker_fn({
+        let wakes = Arc::clone(&wakes);
+        move || {
+            wakes.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let mut cx = Context::from_waker(&waker);
+
+    let (tx, mut rx) = trpl::channel::<u32>();
+    let mut recv = pin!(rx.recv());
+    assert_eq!(recv.as_mut().poll(&mut cx), Poll::Pending);
+    assert_eq!(wakes.load(Ordering::SeqCst), 0);
+
+    tx.send(1).unwrap();
+    assert_eq!(wakes.load(Ordering::SeqCst), 1);
+    assert_eq!(recv.as_mut().poll(&mut cx), Poll::Ready(Some(1)));
+}
+
+mod probe {
+    use super::*;
+    use trpl::StreamExt;
+
+    #[test]
+    fn future() {
+        let result = trpl::block_on(async {
+            trpl::probe("sleep", async {
+                trpl::sleep(Duration::from_millis(1)).await;
+                "done"
+            })
+            .await
+        });
+
+        assert_eq!(result, "done");
+    }
+
+    #[test]
+    fn stream() {
+        let result: Vec<u32> = trpl::block_on(async {
+            trpl::probe("numbers", trpl::stream_from_iter([1, 2, 3]))
+                .collect()
+                .await
+        });
+
+        assert_eq!(result, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn into_inner() {
+        let probe = trpl::probe("answer", async { 42 });
+        let result = trpl::block_on(probe.into_inner());
+        assert_eq!(result, 42);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c() {
+    use std::{pin::pin, process::Command};
+
+    let result = trpl::block_on(async {
+        let mut ctrl_c = pin!(trpl::ctrl_c());
+
+        // Polling once installs the signal handler, so it is safe to interrupt
+        // ourselves afterward.
+        trpl::test::assert_pending(ctrl_c.as_mut());
+        let status = Command::new("kill")
+            .args(["-INT", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let timeout = trpl::sleep(Duration::from_secs(5));
+        trpl::select(ctrl_c, timeout).await
+    });
+
+    assert!(matches!(result, Either::Left(())));
+}
+
+#[cfg(unix)]
+#[test]
+fn signal() {
+    use std::process::Command;
+    use trpl::{SignalKind, StreamExt};
+
+    let result = trpl::block_on(async {
+        let mut signals = trpl::signal(SignalKind::Hangup)
+            .merge(trpl::signal(SignalKind::Terminate));
+
+        for kind in ["-HUP", "-TERM"] {
+            let status = Command::new("kill")
+                .args([kind, &std::process::id().to_string()])
+                .status()
+                .unwrap();
+            assert!(status.success());
+
+            let timeout = trpl::sleep(Duration::from_secs(5));
+            if let Either::Right(()) =
+                trpl::select(signals.next(), timeout).await
+            {
+                return Err(kind);
+            }
+        }
+        Ok(())
+    });
+
+    assert_eq!(result, Ok(()));
+}
+
 #[test]
 fn read_to_string() {
     let result = trpl::block_on(async {
@@ -251,18 +784,1385 @@ fn receiver_stream() {
     assert_eq!(result, vec![123]);
 }
 
-#[test]
-fn re_exported_interval_stream_works() {
-    use trpl::{IntervalStream, StreamExt};
+mod mutex {
+    use super::*;
+    use std::sync::Arc;
+    use trpl::{MappedMutexGuard, Mutex, MutexGuard};
 
-    trpl::block_on(async {
-        let mut interval_stream =
-            IntervalStream::new(trpl::interval(Duration::from_millis(1)))
-                .take(1);
+    #[derive(Default)]
+    struct Stats {
+        hits: u32,
+        misses: u32,
+    }
 
-        assert!(interval_stream.next().await.is_some());
-        assert!(interval_stream.next().await.is_none());
-    });
+    async fn hits(stats: &Mutex<Stats>) -> MappedMutexGuard<'_, u32> {
+        MutexGuard::map(stats.lock().await, |stats| &mut stats.hits)
+    }
+
+    #[test]
+    fn condvar_wakes_waiters() {
+        let woken = trpl::block_on(async {
+            let state = Arc::new((Mutex::new(0), trpl::Condvar::new()));
+
+            let waiters: Vec<_> = (1..=3)
+                .map(|needed| {
+                    let state = Arc::clone(&state);
+                    trpl::spawn_task(async move {
+                        let (count, condvar) = &*state;
+                        let guard = count.lock().await;
+                        let guard =
+                            condvar.wait_while(guard, |n| *n < needed).await;
+                        *guard
+                    })
+                })
+                .collect();
+
+            let (count, condvar) = &*state;
+            for _ in 0..3 {
+                trpl::sleep(Duration::from_millis(1)).await;
+                *count.lock().await += 1;
+                condvar.notify_all();
+            }
+
+            let mut woken = vec![];
+            for waiter in waiters {
+                woken.push(waiter.await.unwrap());
+            }
+            woken
+        });
+
+        assert_eq!(woken, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn condvar_saves_one_notification_for_the_next_waiter() {
+        let ready = trpl::block_on(async {
+            let state = Arc::new((Mutex::new(false), trpl::Condvar::new()));
+            let (ready, condvar) = &*state;
+
+            // Nobody is waiting yet, so the next `wait` returns right away,
+            // without anything having changed…
+            condvar.notify_one();
+            let guard = condvar.wait(ready.lock().await).await;
+            assert!(!*guard);
+
+            // …which is why waiting for a condition needs a loop.
+            let setter = {
+                let state = Arc::clone(&state);
+                trpl::spawn_task(async move {
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    let (ready, condvar) = &*state;
+                    *ready.lock().await = true;
+                    condvar.notify_one();
+                })
+            };
+            condvar.notify_one();
+            let guard = condvar.wait_while(guard, |ready| !*ready).await;
+            let ready = *guard;
+            drop(guard);
+            setter.await.unwrap();
+            ready
+        });
+
+        assert!(ready);
+    }
+
+    #[test]
+    fn map_guard_to_one_field() {
+        let stats = trpl::block_on(async {
+            let stats = Arc::new(Mutex::new(Stats::default()));
+
+            let handles: Vec<_> = (0..5)
+                .map(|_| {
+                    let stats = Arc::clone(&stats);
+                    trpl::spawn_task(async move {
+                        let mut hits = hits(&stats).await;
+                        trpl::yield_now().await;
+                        *hits += 1;
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+
+            stats.lock().await.misses += 1;
+            Arc::into_inner(stats).unwrap().into_inner()
+        });
+
+        assert_eq!(stats.hits, 5);
+        assert_eq!(stats.misses, 1);
+    }
+}
+
+mod receiver {
+    use trpl::StreamExt;
+
+    #[test]
+    fn is_a_stream() {
+        let items: Vec<u32> = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            for n in 1..=4 {
+                tx.send(n).unwrap();
+            }
+            drop(tx);
+
+            assert_eq!(rx.next().await, Some(1));
+            rx.filter(|n| n % 2 == 0).collect().await
+        });
+
+        assert_eq!(items, vec![2, 4]);
+    }
+
+    #[test]
+    fn still_works_wrapped_in_receiver_stream() {
+        let items: Vec<&str> = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            tx.send("hi").unwrap();
+            drop(tx);
+            trpl::ReceiverStream::new(rx).collect().await
+        });
+
+        assert_eq!(items, vec!["hi"]);
+    }
+
+    #[test]
+    fn try_recv() {
+        let (tx, mut rx) = trpl::channel::<u32>();
+        assert_eq!(rx.try_recv(), Err(trpl::TryRecvError::Empty));
+        tx.send(1).unwrap();
+        assert_eq!(rx.len(), 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+        drop(tx);
+        assert!(rx.is_closed());
+        assert_eq!(rx.try_recv(), Err(trpl::TryRecvError::Disconnected));
+    }
+}
+
+mod rpc_channel {
+    use trpl::rpc_channel;
+
+    #[test]
+    fn responses_go_to_the_right_caller() {
+        let responses = trpl::block_on(async {
+            let (tx, mut rx) = rpc_channel::<u32, u32>();
+            trpl::spawn_task(async move {
+                while let Some((n, responder)) = rx.recv().await {
+                    responder.respond(n * 10);
+                }
+            });
+
+            let calls = (1..=3).map(|n| {
+                let tx = tx.clone();
+                async move { tx.call(n).await }
+            });
+            trpl::join_all(calls).await
+        });
+
+        assert_eq!(responses, vec![Some(10), Some(20), Some(30)]);
+    }
+
+    #[test]
+    fn no_response() {
+        trpl::block_on(async {
+            let (tx, mut rx) = rpc_channel::<&str, &str>();
+            let server = trpl::spawn_task(async move {
+                let (_request, responder) = rx.recv().await.unwrap();
+                drop(responder);
+            });
+
+            assert_eq!(tx.call("hello?").await, None);
+            server.await.unwrap();
+            assert_eq!(tx.call("anyone?").await, None);
+        });
+    }
+}
+
+mod semaphore {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use trpl::Semaphore;
+
+    #[test]
+    fn owned_permits_limit_spawned_tasks() {
+        let most_running = trpl::block_on(async {
+            let semaphore = Arc::new(Semaphore::new(2));
+            let running = Arc::new(AtomicUsize::new(0));
+            let most_running = Arc::new(AtomicUsize::new(0));
+
+            let mut handles = vec![];
+            for _ in 0..6 {
+                let permit =
+                    Arc::clone(&semaphore).acquire_owned().await.unwrap();
+                let running = Arc::clone(&running);
+                let most_running = Arc::clone(&most_running);
+                handles.push(trpl::spawn_task(async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now, Ordering::SeqCst);
+                    trpl::sleep(Duration::from_millis(2)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    drop(permit);
+                }));
+            }
+            for handle in handles {
+                handle.await.unwrap();
+            }
+            most_running.load(Ordering::SeqCst)
+        });
+
+        assert!(most_running <= 2);
+    }
+
+    #[test]
+    fn acquire_many() {
+        trpl::block_on(async {
+            let semaphore = Semaphore::new(3);
+            let permits = semaphore.acquire_many(2).await.unwrap();
+            assert_eq!(semaphore.available_permits(), 1);
+            assert!(semaphore.try_acquire_many(2).is_err());
+
+            drop(permits);
+            assert_eq!(semaphore.available_permits(), 3);
+        });
+    }
+}
+
+mod timeout_resumable {
+    use super::*;
+
+    #[test]
+    fn finishes_in_time() {
+        let result = trpl::block_on(trpl::timeout_resumable(
+            async { "done" },
+            Duration::from_millis(10),
+        ));
+        assert!(matches!(result, Ok("done")));
+    }
+
+    #[test]
+    fn resumes_after_timing_out() {
+        let steps = trpl::block_on(async {
+            let (tx, mut rx) = trpl::channel();
+            let work = async move {
+                tx.send("started").unwrap();
+                trpl::sleep(Duration::from_millis(20)).await;
+                tx.send("finished").unwrap();
+                42
+            };
+
+            let timeout =
+                trpl::timeout_resumable(work, Duration::from_millis(1))
+                    .await
+                    .unwrap_err();
+            assert_eq!(timeout.remaining_future.await, 42);
+
+            let mut steps = vec![];
+            while let Some(step) = rx.recv().await {
+                steps.push(step);
+            }
+            steps
+        });
+
+        // Resuming did not start the work over from the beginning.
+        assert_eq!(steps, vec!["started", "finished"]);
+    }
+}
+
+mod compute {
+    use trpl::FutureExt;
+
+    #[test]
+    fn runs_closures_off_the_runtime_thread() {
+        let (results, runtime_thread) = trpl::block_on(async {
+            let runtime_thread = std::thread::current().id();
+            let work = (1..=4_u64).map(|n| {
+                trpl::compute(move || {
+                    let thread = std::thread::current().id();
+                    (n * n, thread)
+                })
+            });
+            (trpl::join_all(work).await, runtime_thread)
+        });
+
+        let squares: Vec<u64> = results.iter().map(|(n, _)| *n).collect();
+        assert_eq!(squares, vec![1, 4, 9, 16]);
+        assert!(results.iter().all(|(_, thread)| *thread != runtime_thread));
+    }
+
+    #[test]
+    fn passes_panics_along() {
+        let result = trpl::block_on(async {
+            trpl::compute(|| -> u32 { panic!("bad math") })
+                .catch_unwind()
+                .await
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"bad math"));
+
+        // The pool is still working afterward.
+        assert_eq!(trpl::block_on(trpl::compute(|| 2 + 2)), 4);
+    }
+}
+
+mod future_ext {
+    use super::*;
+    use trpl::FutureExt;
+
+    #[test]
+    fn timeout() {
+        trpl::block_on(async {
+            let quick = async { 1 }.timeout(Duration::from_secs(5)).await;
+            assert!(matches!(quick, Ok(1)));
+
+            let slow = trpl::sleep(Duration::from_millis(20))
+                .timeout(Duration::from_millis(1))
+                .await;
+            assert!(slow.is_err());
+        });
+    }
+
+    #[test]
+    fn map_and_inspect() {
+        let (seen, output) = trpl::block_on(async {
+            let mut seen = None;
+            let output = async { 20 }
+                .inspect(|n| seen = Some(*n))
+                .map(|n| n + 1)
+                .await;
+            (seen, output)
+        });
+
+        assert_eq!(seen, Some(20));
+        assert_eq!(output, 21);
+    }
+
+    #[test]
+    fn catch_unwind() {
+        trpl::block_on(async {
+            let fine = async { 1 }.catch_unwind().await;
+            assert_eq!(fine.ok(), Some(1));
+
+            let panicked = async {
+                if true {
+                    panic!("oh no");
+                }
+            }
+            .catch_unwind()
+            .await;
+            let payload = panicked.unwrap_err();
+            assert_eq!(payload.downcast_ref::<&str>(), Some(&"oh no"));
+        });
+    }
+
+    #[test]
+    fn and_then() {
+        trpl::block_on(async {
+            let ok = async { Ok::<_, String>(1) }
+                .and_then(async |n| Ok(n + 1))
+                .await;
+            assert_eq!(ok, Ok(2));
+
+            let mut called = false;
+            let err = async { Err::<u32, _>("nope") }
+                .and_then(async |n| {
+                    called = true;
+                    Ok(n + 1)
+                })
+                .await;
+            assert_eq!(err, Err("nope"));
+            assert!(!called);
+        });
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_events {
+    use super::*;
+    use std::{
+        fmt,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicU64, Ordering},
+        },
+    };
+    use tracing::{
+        Event, Metadata, Subscriber,
+        field::{Field, Visit},
+        span,
+    };
+
+    /// Just enough of a subscriber to remember every `trpl` event message.
+    #[derive(Clone, Default)]
+    struct Messages {
+        messages: Arc<Mutex<Vec<String>>>,
+        next_span: Arc<AtomicU64>,
+    }
+
+    impl Subscriber for Messages {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "trpl"
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(
+                self.next_span.fetch_add(1, Ordering::SeqCst) + 1,
+            )
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            struct Message<'a>(&'a mut String);
+
+            impl Visit for Message<'_> {
+                fn record_debug(
+                    &mut self,
+                    field: &Field,
+                    value: &dyn fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        *self.0 = format!("{value:?}");
+                    }
+                }
+            }
+
+            let mut message = String::new();
+            event.record(&mut Message(&mut message));
+            self.messages.lock().unwrap().push(message);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn tasks_sleeps_and_timeouts() {
+        let subscriber = Messages::default();
+        let messages = Arc::clone(&subscriber.messages);
+
+        // `with_default` only applies to the current thread, so use the seeded
+        // scheduler, which runs every task right here.
+        tracing::subscriber::with_default(subscriber, || {
+            trpl::test::with_seed(0, async {
+                trpl::spawn_task(async {}).await.unwrap();
+                trpl::sleep(Duration::from_millis(1)).await;
+                let never = std::future::pending::<()>();
+                let result =
+                    trpl::timeout_resumable(never, Duration::ZERO).await;
+                assert!(result.is_err());
+            });
+        });
+
+        let messages = messages.lock().unwrap();
+        for expected in [
+            "spawning task",
+            "task started",
+            "task finished",
+            "sleep started",
+            "timed out",
+        ] {
+            assert!(
+                messages.iter().any(|message| message == expected),
+                "no {expected:?} event in {messages:?}"
+            );
+        }
+    }
+}
+
+mod io {
+    use trpl::{
+        StreamExt,
+        io::{AsyncBufReadExt, AsyncReadExt, reader_stream, stream_reader},
+    };
+
+    #[test]
+    fn stream_reader_reads_lines_across_chunks() {
+        let lines: Vec<String> = trpl::block_on(async {
+            let chunks = trpl::stream_from_iter(["one\ntw", "o\nthr", "ee\n"])
+                .map(|chunk| Ok(chunk.as_bytes()));
+            stream_reader(chunks)
+                .lines()
+                .map(Result::unwrap)
+                .collect()
+                .await
+        });
+
+        assert_eq!(lines, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn stream_reader_reports_errors() {
+        let result = trpl::block_on(async {
+            let chunks = trpl::stream_from_iter([
+                Ok("fine\n".as_bytes()),
+                Err(std::io::Error::other("broken")),
+            ]);
+            let lines: Vec<_> = stream_reader(chunks).lines().collect().await;
+            lines
+        });
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].as_ref().unwrap(), "fine");
+        assert_eq!(result[1].as_ref().unwrap_err().to_string(), "broken");
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes: Vec<u8> = (0..10_000).map(|n| (n % 256) as u8).collect();
+        let round_tripped = trpl::block_on(async {
+            let mut reader = stream_reader(reader_stream(&bytes[..]));
+            let mut round_tripped = vec![];
+            reader.read_to_end(&mut round_tripped).await.unwrap();
+            round_tripped
+        });
+
+        assert_eq!(round_tripped, bytes);
+    }
+}
+
+mod codec {
+    use trpl::{
+        StreamExt,
+        codec::{
+            Decoder, Encoder, Framed, LengthDelimitedCodec, LinesCodec, SinkExt,
+        },
+        io::Cursor,
+    };
+
+    #[test]
+    fn lines() {
+        let lines: Vec<String> = trpl::block_on(async {
+            let input = Cursor::new(b"one\r\ntwo\nlast".to_vec());
+            Framed::new(input, LinesCodec::new())
+                .try_collect()
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(lines, vec!["one", "two", "last"]);
+    }
+
+    #[test]
+    fn lines_must_be_utf8() {
+        let result = trpl::block_on(async {
+            let input = Cursor::new(b"\xff\n".to_vec());
+            Framed::new(input, LinesCodec::new()).next().await.unwrap()
+        });
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn lines_over_the_limit() {
+        let max = LinesCodec::MAX_LINE_LENGTH;
+        let mut codec = LinesCodec::new();
+
+        let mut longest = vec![b'a'; max];
+        longest.extend_from_slice(b"\r\n");
+        assert_eq!(codec.decode(&mut longest).unwrap().unwrap().len(), max);
+
+        // Rejected as soon as there are too many bytes without a newline,
+        // rather than once the newline finally arrives.
+        let mut endless = vec![b'a'; max + 2];
+        assert!(codec.decode(&mut endless).is_err());
+
+        let mut last = vec![b'a'; max + 1];
+        assert_eq!(codec.decode(&mut last).unwrap(), None);
+        assert!(codec.decode_eof(&mut last).is_err());
+
+        let mut buf = vec![];
+        assert!(codec.encode("a".repeat(max + 1), &mut buf).is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn chat_over_a_tokio_connection() {
+        let replies: Vec<String> = trpl::block_on(async {
+            let (client, server) = tokio::io::duplex(64);
+            let mut server =
+                Framed::new(trpl::io::compat(server), LinesCodec::new());
+            let echo = trpl::spawn_task(async move {
+                while let Some(line) = server.next().await {
+                    server.send(format!("echo: {}", line.unwrap())).await?;
+                }
+                SinkExt::<String>::close(&mut server).await
+            });
+
+            let mut client =
+                Framed::new(trpl::io::compat(client), LinesCodec::new());
+            client.send("hello").await.unwrap();
+            client.send("x".repeat(100)).await.unwrap();
+            let mut replies = vec![];
+            replies.push(client.next().await.unwrap().unwrap());
+            replies.push(client.next().await.unwrap().unwrap());
+            SinkExt::<String>::close(&mut client).await.unwrap();
+
+            assert!(client.next().await.is_none());
+            echo.await.unwrap().unwrap();
+            replies
+        });
+
+        assert_eq!(
+            replies,
+            [
+                "echo: hello".to_string(),
+                format!("echo: {}", "x".repeat(100))
+            ]
+        );
+    }
+
+    #[test]
+    fn length_delimited_round_trip() {
+        let (written, frames) = trpl::block_on(async {
+            let mut framed = Framed::new(
+                Cursor::new(Vec::new()),
+                LengthDelimitedCodec::new(),
+            );
+            framed.send(b"hi".as_slice()).await.unwrap();
+            framed.send(vec![0; 5000]).await.unwrap();
+            framed.send(b"".as_slice()).await.unwrap();
+
+            let mut connection = framed.into_inner();
+            let written = connection.get_ref()[..6].to_vec();
+            connection.set_position(0);
+            let frames: Vec<Vec<u8>> =
+                Framed::new(connection, LengthDelimitedCodec::new())
+                    .try_collect()
+                    .await
+                    .unwrap();
+            (written, frames)
+        });
+
+        assert_eq!(written, [0, 0, 0, 2, b'h', b'i']);
+        assert_eq!(frames, vec![b"hi".to_vec(), vec![0; 5000], vec![]]);
+    }
+
+    #[test]
+    fn length_delimited_partial_frames() {
+        let mut codec = LengthDelimitedCodec::new();
+        let mut buf = vec![0, 0, 0, 3, b'a'];
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(b"bc");
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(b"abc".to_vec()));
+        assert!(buf.is_empty());
+
+        let mut truncated = vec![0, 0, 0, 3, b'a'];
+        assert!(codec.decode_eof(&mut truncated).is_err());
+
+        let mut huge = u32::MAX.to_be_bytes().to_vec();
+        assert!(codec.decode(&mut huge).is_err());
+    }
+    /// Every item the stream produces, up to a limit, so a stream which never
+    /// ends fails the test instead of hanging it.
+    fn items<C: Decoder>(
+        codec: C,
+        input: Vec<u8>,
+    ) -> Vec<std::io::Result<C::Item>> {
+        trpl::block_on(
+            Framed::new(Cursor::new(input), codec).take(10).collect(),
+        )
+    }
+
+    #[test]
+    fn ends_after_trailing_garbage() {
+        let items =
+            items(LengthDelimitedCodec::new(), vec![0, 0, 0, 1, b'a', 0]);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), b"a");
+        assert_eq!(
+            items[1].as_ref().unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn ends_after_a_frame_over_the_limit() {
+        let mut input = vec![0, 0, 0, 1, b'a'];
+        input.extend_from_slice(&u32::MAX.to_be_bytes());
+        input.extend_from_slice(b"more bytes");
+        let items = items(LengthDelimitedCodec::new(), input);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), b"a");
+        assert_eq!(
+            items[1].as_ref().unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+}
+
+#[cfg(unix)]
+mod process {
+    use trpl::{StreamExt, process::Command};
+
+    #[test]
+    fn output() {
+        let output =
+            trpl::block_on(Command::new("echo").arg("hello").output()).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello\n");
+    }
+
+    #[test]
+    fn status() {
+        let status = trpl::block_on(Command::new("false").status()).unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn stdout_lines() {
+        let (lines, status) = trpl::block_on(async {
+            let mut child = Command::new("sh")
+                .args(["-c", "echo one; sleep 0.01; echo two"])
+                .spawn()
+                .unwrap();
+
+            let lines: Vec<String> =
+                child.stdout_lines().unwrap().try_collect().await.unwrap();
+            assert!(child.stdout_lines().is_none());
+            (lines, child.wait().await.unwrap())
+        });
+
+        assert_eq!(lines, vec!["one", "two"]);
+        assert!(status.success());
+    }
+}
+
+mod stream_ext {
+    use super::*;
+    use trpl::StreamExt;
+
+    #[test]
+    fn merge_alternates_when_both_are_always_ready() {
+        let items: Vec<&str> = trpl::block_on(async {
+            let fast = futures::stream::repeat("fast");
+            let other = futures::stream::repeat("other");
+            fast.merge(other).take(6).collect().await
+        });
+
+        assert_eq!(items, ["fast", "other", "fast", "other", "fast", "other"]);
+    }
+
+    #[test]
+    fn merge_all_takes_turns() {
+        let items: Vec<u32> = trpl::block_on(async {
+            let ready_forever = futures::stream::repeat(0);
+            let (tx, rx) = trpl::channel();
+            tx.send(1).unwrap();
+            tx.send(1).unwrap();
+            drop(tx);
+            let short = trpl::stream_from_iter([2]);
+
+            trpl::merge_all([
+                futures::StreamExt::boxed(ready_forever),
+                futures::StreamExt::boxed(trpl::ReceiverStream::new(rx)),
+                futures::StreamExt::boxed(short),
+            ])
+            .take(7)
+            .collect()
+            .await
+        });
+
+        assert_eq!(items, [0, 1, 2, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn merge_all_waits_for_pending_streams() {
+        use std::pin::pin;
+        use trpl::test::{assert_next_pending, assert_next_ready};
+
+        let (tx, rx) = trpl::channel();
+        let empty = trpl::stream_from_iter(Vec::<u32>::new());
+        let mut merged = pin!(trpl::merge_all([
+            futures::StreamExt::boxed(trpl::ReceiverStream::new(rx)),
+            futures::StreamExt::boxed(empty),
+        ]));
+
+        for n in 1..=3 {
+            assert_next_pending(merged.as_mut());
+            tx.send(n).unwrap();
+            assert_eq!(assert_next_ready(merged.as_mut()), Some(n));
+        }
+        assert_next_pending(merged.as_mut());
+        drop(tx);
+        assert_eq!(assert_next_ready(merged.as_mut()), None);
+    }
+
+    #[test]
+    fn ready_chunks_only_takes_what_is_ready() {
+        let chunks: Vec<Vec<u32>> = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            // A burst of three, and then one more after a pause.
+            for n in 1..=3 {
+                tx.send(n).unwrap();
+            }
+            trpl::spawn_task(async move {
+                trpl::sleep(Duration::from_millis(10)).await;
+                tx.send(4).unwrap();
+            });
+
+            trpl::ReceiverStream::new(rx)
+                .ready_chunks(10)
+                .collect()
+                .await
+        });
+
+        assert_eq!(chunks, vec![vec![1, 2, 3], vec![4]]);
+    }
+
+    #[test]
+    fn take_for_stops_at_the_deadline() {
+        let items: Vec<u32> = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            tx.send(1).unwrap();
+            trpl::spawn_task(async move {
+                trpl::sleep(Duration::from_millis(200)).await;
+                tx.send(2).unwrap();
+            });
+
+            trpl::ReceiverStream::new(rx)
+                .take_for(Duration::from_millis(10))
+                .collect()
+                .await
+        });
+
+        // The stream ended at the deadline, without producing an error or
+        // waiting for the sender to hang up.
+        assert_eq!(items, vec![1]);
+    }
+
+    #[test]
+    fn sample_keeps_the_latest_item_per_window() {
+        let items: Vec<u32> = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            // Everything in the first window arrives at once, so only the last
+            // of those survives.
+            for n in 1..=3 {
+                tx.send(n).unwrap();
+            }
+            trpl::spawn_task(async move {
+                trpl::sleep(Duration::from_millis(50)).await;
+                tx.send(4).unwrap();
+            });
+
+            trpl::ReceiverStream::new(rx)
+                .sample(Duration::from_millis(20))
+                .collect()
+                .await
+        });
+
+        assert_eq!(items, vec![3, 4]);
+    }
+
+    #[test]
+    fn catch_unwind_ends_the_stream_after_a_panic() {
+        let results: Vec<Result<u32, String>> = trpl::block_on(async {
+            trpl::stream_from_iter([1, 2, 3, 4])
+                .map(|n| {
+                    if n == 3 {
+                        panic!("three is right out");
+                    }
+                    n
+                })
+                .catch_unwind()
+                .map(|result| {
+                    result.map_err(|payload| {
+                        payload.downcast_ref::<&str>().unwrap().to_string()
+                    })
+                })
+                .collect()
+                .await
+        });
+
+        assert_eq!(
+            results,
+            vec![Ok(1), Ok(2), Err(String::from("three is right out"))]
+        );
+    }
+
+    #[test]
+    fn partition() {
+        let (small, large): (Vec<u32>, Vec<u32>) = trpl::block_on(async {
+            let (small, large) = trpl::stream_from_iter([1, 20, 3, 40, 5])
+                .partition(|n| *n < 10);
+            trpl::join(small.collect(), large.collect()).await
+        });
+
+        assert_eq!(small, vec![1, 3, 5]);
+        assert_eq!(large, vec![20, 40]);
+    }
+
+    #[test]
+    fn partition_with_one_half_dropped() {
+        let odds: Vec<u32> = trpl::block_on(async {
+            let (_, odds) =
+                trpl::stream_from_iter(1..=5).partition(|n| n % 2 == 0);
+            odds.collect().await
+        });
+
+        assert_eq!(odds, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn partition_waits_for_items() {
+        let (messages, intervals) = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            trpl::spawn_task(async move {
+                for message in ["a", "tick", "b", "tick"] {
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    tx.send(message).unwrap();
+                }
+            });
+
+            let (intervals, messages) = trpl::ReceiverStream::new(rx)
+                .partition(|message| *message == "tick");
+            trpl::join(
+                messages.collect::<Vec<_>>(),
+                intervals.collect::<Vec<_>>(),
+            )
+            .await
+        });
+
+        assert_eq!(messages, vec!["a", "b"]);
+        assert_eq!(intervals, vec!["tick", "tick"]);
+    }
+
+    #[test]
+    fn next_or() {
+        trpl::block_on(async {
+            let (tx, rx) = trpl::channel::<u32>();
+            let mut numbers = trpl::ReceiverStream::new(rx);
+
+            let outcome = numbers.next_or(async { "other" }).await;
+            assert!(matches!(outcome, trpl::Either::Right("other")));
+
+            tx.send(1).unwrap();
+            drop(tx);
+            let never = std::future::pending::<()>();
+            let outcome = numbers.next_or(never).await;
+            assert!(matches!(outcome, trpl::Either::Left(Some(1))));
+
+            let outcome = numbers.next_or(std::future::pending::<()>()).await;
+            assert!(matches!(outcome, trpl::Either::Left(None)));
+        });
+    }
+
+    #[test]
+    fn inspect() {
+        let (seen, errors, items) = trpl::block_on(async {
+            let mut seen = vec![];
+            let mut errors = vec![];
+            let items: Vec<_> = trpl::stream_from_iter(["1", "x", "3"])
+                .inspect(|s| seen.push(s.to_string()))
+                .map(|s| s.parse::<u32>())
+                .inspect_err(|e| errors.push(e.to_string()))
+                .collect()
+                .await;
+            (seen, errors, items)
+        });
+
+        assert_eq!(seen, vec!["1", "x", "3"]);
+        assert_eq!(errors, vec!["invalid digit found in string"]);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2], Ok(3));
+    }
+
+    #[test]
+    fn try_for_each_stops_at_first_error() {
+        let (result, handled) = trpl::block_on(async {
+            let handled = std::cell::RefCell::new(vec![]);
+            let result =
+                trpl::stream_from_iter([Ok(1), Ok(2), Ok(3), Err("x")])
+                    .try_for_each(|n| {
+                        let handled = &handled;
+                        async move {
+                            if n == 2 {
+                                return Err("two");
+                            }
+                            handled.borrow_mut().push(n);
+                            Ok(())
+                        }
+                    })
+                    .await;
+            (result, handled.into_inner())
+        });
+
+        assert_eq!(result, Err("two"));
+        assert_eq!(handled, vec![1]);
+    }
+
+    #[test]
+    fn async_closures() {
+        let (evens, most_running) = trpl::block_on(async {
+            let evens: Vec<u32> = trpl::stream_from_iter(1..=6)
+                .filter_async(async |n| {
+                    trpl::yield_now().await;
+                    n % 2 == 0
+                })
+                .then(async |n| n * 10)
+                .collect()
+                .await;
+
+            let running = std::cell::Cell::new(0);
+            let most_running = std::cell::Cell::new(0);
+            trpl::stream_from_iter(1..=10)
+                .for_each_concurrent(Some(3), async |_| {
+                    running.set(running.get() + 1);
+                    most_running.set(most_running.get().max(running.get()));
+                    trpl::sleep(Duration::from_millis(1)).await;
+                    running.set(running.get() - 1);
+                })
+                .await;
+
+            (evens, most_running.get())
+        });
+
+        assert_eq!(evens, vec![20, 40, 60]);
+        assert_eq!(most_running, 3);
+    }
+
+    #[test]
+    fn then_with_a_closure_returning_a_future() {
+        let doubled: Vec<u32> = trpl::block_on(
+            trpl::stream_from_iter(1..=3_u32)
+                .then(|n| async move { n * 2 })
+                .collect(),
+        );
+        assert_eq!(doubled, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn try_collect_stops_at_first_error() {
+        let (polled, result) = trpl::block_on(async {
+            let polled = std::cell::Cell::new(0);
+            let result = trpl::stream_from_iter(["1", "2", "x", "4"])
+                .map(|n| {
+                    polled.set(polled.get() + 1);
+                    n.parse::<u32>()
+                })
+                .try_collect::<Vec<_>>()
+                .await;
+            (polled.get(), result)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(polled, 3);
+    }
+
+    #[test]
+    fn group_by() {
+        let groups = trpl::block_on(async {
+            let numbers = trpl::stream_from_iter([3, 12, 7, 25, 18]);
+            let mut groups = numbers.group_by(|n| n / 10);
+
+            let mut handles = vec![];
+            while let Some((tens, group)) = groups.next().await {
+                assert_eq!(*group.key(), tens);
+                handles.push(trpl::spawn_task(async move {
+                    (tens, group.collect::<Vec<_>>().await)
+                }));
+            }
+
+            let mut groups = vec![];
+            for handle in handles {
+                groups.push(handle.await.unwrap());
+            }
+            groups
+        });
+
+        assert_eq!(
+            groups,
+            vec![(0, vec![3, 7]), (1, vec![12, 18]), (2, vec![25])]
+        );
+    }
+
+    #[test]
+    fn group_by_with_groups_dropped() {
+        let keys: Vec<bool> = trpl::block_on(async {
+            trpl::stream_from_iter(1..=10)
+                .group_by(|n| n % 2 == 0)
+                .map(|(key, _group)| key)
+                .collect()
+                .await
+        });
+
+        assert_eq!(keys, vec![false, true]);
+    }
+}
+
+#[test]
+fn re_exported_interval_stream_works() {
+    use trpl::{IntervalStream, StreamExt};
+
+    trpl::block_on(async {
+        let mut interval_stream =
+            IntervalStream::new(trpl::interval(Duration::from_millis(1)))
+                .take(1);
+
+        assert!(interval_stream.next().await.is_some());
+        assert!(interval_stream.next().await.is_none());
+    });
+}
+
+#[test]
+fn prelude() {
+    use trpl::prelude::*;
+
+    fn evens(stream: impl Stream<Item = u32>) -> impl Stream<Item = u32> {
+        stream.filter(|n| n % 2 == 0)
+    }
+
+    let result: Vec<u32> = trpl::block_on(async {
+        let (tx, rx): (Sender<u32>, Receiver<u32>) = trpl::channel();
+        for n in 1..=4 {
+            tx.send(n).unwrap();
+        }
+        drop(tx);
+
+        evens(ReceiverStream::new(rx)).collect().await
+    });
+
+    assert_eq!(result, vec![2, 4]);
+}
+
+mod with_seed {
+    use super::*;
+    use trpl::test::with_seed;
+
+    fn interleaving(seed: u64) -> Vec<(u32, u32)> {
+        with_seed(seed, async {
+            let (tx, mut rx) = trpl::channel();
+            for task in 0..3 {
+                let tx = tx.clone();
+                trpl::spawn_task(async move {
+                    for step in 0..3 {
+                        tx.send((task, step)).unwrap();
+                        trpl::yield_now().await;
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut order = vec![];
+            while let Some(message) = rx.recv().await {
+                order.push(message);
+            }
+            order
+        })
+    }
+
+    #[test]
+    fn same_seed_same_order() {
+        for seed in 0..10 {
+            assert_eq!(interleaving(seed), interleaving(seed));
+        }
+    }
+
+    #[test]
+    fn different_seeds_different_orders() {
+        let first = interleaving(0);
+        assert!((1..20).any(|seed| interleaving(seed) != first));
+    }
+
+    #[test]
+    fn high_priority_tasks_go_first() {
+        for seed in 0..10 {
+            let order = with_seed(seed, async {
+                let (tx, mut rx) = trpl::channel();
+                for task in 0..3 {
+                    let priority = if task == 2 {
+                        trpl::Priority::High
+                    } else {
+                        trpl::Priority::Normal
+                    };
+                    let tx = tx.clone();
+                    trpl::spawn_task_with_priority(priority, async move {
+                        for step in 0..3 {
+                            tx.send((task, step)).unwrap();
+                            trpl::yield_now().await;
+                        }
+                    });
+                }
+                drop(tx);
+
+                let mut order = vec![];
+                while let Some(message) = rx.recv().await {
+                    order.push(message);
+                }
+                order
+            });
+
+            assert_eq!(order[..3], [(2, 0), (2, 1), (2, 2)], "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn join_handles_and_timers() {
+        let result = with_seed(42, async {
+            let handle = trpl::spawn_task(async {
+                trpl::sleep(Duration::from_millis(1)).await;
+                "slept"
+            });
+            handle.await.unwrap()
+        });
+
+        assert_eq!(result, "slept");
+    }
+
+    #[test]
+    fn panics_restore_the_runtime() {
+        let panicked = std::panic::catch_unwind(|| {
+            with_seed(0, async { panic!("in the seeded future") })
+        });
+        assert!(panicked.is_err());
+
+        // If the seeded scheduler were still the current one, this task would
+        // go to it and never run.
+        let result = trpl::block_on(async {
+            let handle = trpl::spawn_task(async { "ran" });
+            trpl::race(handle, trpl::sleep(Duration::from_secs(5))).await
+        });
+        assert!(matches!(result, Either::Left(Ok("ran"))));
+    }
+
+    #[test]
+    fn unfinished_tasks_are_dropped() {
+        let witness = std::sync::Arc::new(());
+        let in_task = std::sync::Arc::clone(&witness);
+        with_seed(0, async move {
+            let (started_tx, mut started_rx) = trpl::channel();
+            trpl::spawn_task(async move {
+                let _witness = in_task;
+                // Waiting on a channel it holds the other end of means the
+                // task is never woken, and the channel holds its waker.
+                let (tx, mut rx) = trpl::channel::<()>();
+                started_tx.send(()).unwrap();
+                rx.recv().await;
+                drop(tx);
+            });
+            started_rx.recv().await;
+        });
+
+        assert_eq!(std::sync::Arc::strong_count(&witness), 1);
+    }
+}
+
+mod poll_assertions {
+    use std::pin::pin;
+
+    use trpl::StreamExt;
+    use trpl::test::{
+        assert_next_pending, assert_next_ready, assert_pending, assert_ready,
+        poll_once,
+    };
+
+    #[test]
+    fn futures() {
+        let (tx, mut rx) = trpl::channel();
+        let mut received = pin!(rx.recv());
+
+        assert_pending(received.as_mut());
+        tx.send(1).unwrap();
+        assert_eq!(assert_ready(received.as_mut()), Some(1));
+    }
+
+    #[test]
+    fn streams() {
+        let (tx, rx) = trpl::channel();
+        let mut stream = pin!(trpl::ReceiverStream::new(rx).map(|n| n * 2));
+
+        assert_next_pending(stream.as_mut());
+        tx.send(21).unwrap();
+        assert_eq!(assert_next_ready(stream.as_mut()), Some(42));
+        drop(tx);
+        assert_eq!(assert_next_ready(stream.as_mut()), None);
+    }
+
+    #[test]
+    fn poll_once_does_not_loop() {
+        let mut future = pin!(trpl::yield_now());
+        assert!(poll_once(future.as_mut()).is_pending());
+        assert!(poll_once(future.as_mut()).is_ready());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected the future to be pending")]
+    fn assert_pending_fails() {
+        assert_pending(pin!(async { 1 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected the future to be ready")]
+    fn assert_ready_fails() {
+        let (_tx, mut rx) = trpl::channel::<u32>();
+        assert_ready(pin!(rx.recv()));
+    }
+}
+
+mod recording_sender {
+    use super::*;
+    use trpl::StreamExt;
+    use trpl::test::{RecordingSender, recording_channel};
+
+    #[test]
+    fn records_values_in_order() {
+        let (tx, rx) = recording_channel();
+        let recording = tx.recording();
+        let other = tx.clone();
+
+        tx.send(1).unwrap();
+        other.send(2).unwrap();
+        drop((tx, other));
+
+        let received: Vec<u32> =
+            trpl::block_on(trpl::ReceiverStream::new(rx).collect());
+        assert_eq!(received, vec![1, 2]);
+        assert_eq!(recording.values(), vec![1, 2]);
+    }
+
+    #[test]
+    fn records_timing() {
+        let recording = trpl::block_on(async {
+            let (tx, rx) = trpl::channel();
+            let tx = RecordingSender::new(tx);
+            let recording = tx.recording();
+
+            for n in 1..=3 {
+                tx.send(n).unwrap();
+                trpl::sleep(Duration::from_millis(5)).await;
+            }
+
+            drop(rx);
+            recording
+        });
+
+        let records = recording.records();
+        assert_eq!(records.len(), 3);
+        assert!(
+            records
+                .windows(2)
+                .all(|pair| pair[1].elapsed - pair[0].elapsed
+                    >= Duration::from_millis(5))
+        );
+    }
+
+    #[test]
+    fn failed_sends_are_not_recorded() {
+        let (tx, rx) = recording_channel();
+        drop(rx);
+
+        assert!(tx.send("nobody is listening").is_err());
+        assert!(tx.recording().is_empty());
+    }
 }
 
 #[test]
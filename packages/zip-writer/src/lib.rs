@@ -0,0 +1,187 @@
+//! Just enough of a ZIP writer for the book’s own archives: EPUB containers,
+//! and the zips of each chapter’s listings.
+
+use std::io::{self, Write};
+
+use flate2::{write::DeflateEncoder, Compression};
+
+/// Writes a ZIP archive, one file at a time.
+///
+/// The archives the book makes need two things which general-purpose writers
+/// do not always make easy. EPUB needs its `mimetype` entry to come first and
+/// be stored uncompressed, and no archive should depend on when it was built,
+/// so that building it again from the same files gives the same bytes. So
+/// every entry here gets the same fixed timestamp, and the caller picks
+/// whether each one is compressed.
+///
+/// Every entry is a regular file anyone can read, as far as Unix `unzip` is
+/// concerned; directories are implied by the `/`s in the names.
+pub struct Writer<W: Write> {
+    out: W,
+    offset: u32,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// How to store an entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Stored,
+    Deflated,
+}
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+/// Version 2.0 of the format, the first with DEFLATE.
+const VERSION: u16 = 20;
+/// Made by Unix, so that the external attributes are a Unix mode.
+const MADE_BY: u16 = (3 << 8) | VERSION;
+/// A regular file which anyone can read and its owner can write.
+const EXTERNAL_ATTRIBUTES: u32 = 0o100644 << 16;
+/// File names are UTF-8.
+const FLAGS: u16 = 1 << 11;
+/// 00:00:00, in MS-DOS format.
+const TIME: u16 = 0;
+/// 1980-01-01, the earliest date MS-DOS format can hold.
+const DATE: u16 = (1 << 5) | 1;
+
+impl<W: Write> Writer<W> {
+    /// Start an empty archive, written to `out`.
+    pub fn new(out: W) -> Writer<W> {
+        Writer {
+            out,
+            offset: 0,
+            entries: vec![],
+        }
+    }
+
+    /// Add a file named `name`, which may include `/`-separated directories.
+    pub fn add(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        method: Method,
+    ) -> io::Result<()> {
+        let (method, stored) = match method {
+            Method::Stored => (0, data.to_vec()),
+            Method::Deflated => {
+                let mut encoder =
+                    DeflateEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(data)?;
+                (8, encoder.finish()?)
+            }
+        };
+
+        let entry = Entry {
+            name: name.to_string(),
+            method,
+            crc: crc32fast::hash(data),
+            compressed_size: size(stored.len())?,
+            size: size(data.len())?,
+            offset: self.offset,
+        };
+
+        let mut header = vec![];
+        put_u32(&mut header, LOCAL_HEADER);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, entry.method);
+        put_u16(&mut header, TIME);
+        put_u16(&mut header, DATE);
+        put_u32(&mut header, entry.crc);
+        put_u32(&mut header, entry.compressed_size);
+        put_u32(&mut header, entry.size);
+        put_u16(&mut header, size(name.len())? as u16);
+        put_u16(&mut header, 0); // no extra fields
+        header.extend_from_slice(name.as_bytes());
+
+        self.write(&header)?;
+        self.write(&stored)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory, which ends the archive, and hand back the
+    /// underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let start = self.offset;
+        let mut directory = vec![];
+        for entry in &self.entries {
+            put_u32(&mut directory, CENTRAL_HEADER);
+            put_u16(&mut directory, MADE_BY);
+            put_u16(&mut directory, VERSION); // needed to extract
+            put_u16(&mut directory, FLAGS);
+            put_u16(&mut directory, entry.method);
+            put_u16(&mut directory, TIME);
+            put_u16(&mut directory, DATE);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, entry.compressed_size);
+            put_u32(&mut directory, entry.size);
+            put_u16(&mut directory, entry.name.len() as u16);
+            put_u16(&mut directory, 0); // extra field length
+            put_u16(&mut directory, 0); // comment length
+            put_u16(&mut directory, 0); // disk number
+            put_u16(&mut directory, 0); // internal attributes
+            put_u32(&mut directory, EXTERNAL_ATTRIBUTES);
+            put_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| too_large("too many entries"))?;
+        let mut end = vec![];
+        put_u32(&mut end, END_OF_CENTRAL_DIRECTORY);
+        put_u16(&mut end, 0); // this disk
+        put_u16(&mut end, 0); // disk where the directory starts
+        put_u16(&mut end, count); // entries on this disk
+        put_u16(&mut end, count); // entries in total
+        put_u32(&mut end, size(directory.len())?);
+        put_u32(&mut end, start);
+        put_u16(&mut end, 0); // comment length
+
+        self.write(&directory)?;
+        self.write(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.offset = self
+            .offset
+            .checked_add(size(bytes.len())?)
+            .ok_or_else(|| too_large("archive is over 4GB"))?;
+        Ok(())
+    }
+}
+
+/// Sizes and offsets are 32 bits without the ZIP64 extensions, which none of
+/// the book’s archives come anywhere near needing.
+fn size(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| too_large("entry is over 4GB"))
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("ZIP {what}"))
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,67 @@
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+use super::*;
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn first_stored_entry_is_readable_at_a_fixed_offset() {
+    let mut writer = Writer::new(Vec::new());
+    writer
+        .add("mimetype", b"application/epub+zip", Method::Stored)
+        .unwrap();
+    let bytes = writer.finish().unwrap();
+
+    // This is what EPUB readers rely on to recognize the file.
+    assert_eq!(&bytes[30..38], b"mimetype");
+    assert_eq!(&bytes[38..58], b"application/epub+zip");
+    assert_eq!(u16_at(&bytes, 8), 0, "stored, not compressed");
+    assert_eq!(u16_at(&bytes, 28), 0, "no extra fields");
+}
+
+#[test]
+fn deflated_entries_round_trip() {
+    let text = "fn main() {\n    println!(\"Hello, world!\");\n}\n".repeat(20);
+
+    let mut writer = Writer::new(Vec::new());
+    writer.add("a.txt", b"first", Method::Stored).unwrap();
+    writer
+        .add("dir/main.rs", text.as_bytes(), Method::Deflated)
+        .unwrap();
+    let bytes = writer.finish().unwrap();
+
+    // Find the second entry through the central directory, the way readers do.
+    let end = bytes.len() - 22;
+    assert_eq!(u32_at(&bytes, end), END_OF_CENTRAL_DIRECTORY);
+    assert_eq!(u16_at(&bytes, end + 10), 2);
+    let mut at = u32_at(&bytes, end + 16) as usize;
+    at += 46 + u16_at(&bytes, at + 28) as usize; // skip the first entry
+    assert_eq!(u32_at(&bytes, at), CENTRAL_HEADER);
+    assert_eq!(&bytes[at + 46..at + 57], b"dir/main.rs");
+    assert_eq!(u16_at(&bytes, at + 4), MADE_BY);
+    assert_eq!(u32_at(&bytes, at + 38) >> 16, 0o100644);
+    let offset = u32_at(&bytes, at + 42) as usize;
+
+    assert_eq!(u32_at(&bytes, offset), LOCAL_HEADER);
+    assert_eq!(u16_at(&bytes, offset + 8), 8);
+    let compressed_size = u32_at(&bytes, offset + 18) as usize;
+    assert!(compressed_size < text.len());
+    let data_start = offset + 30 + "dir/main.rs".len();
+    let mut decoded = String::new();
+    DeflateDecoder::new(&bytes[data_start..data_start + compressed_size])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, text);
+    assert_eq!(
+        u32_at(&bytes, offset + 14),
+        crc32fast::hash(text.as_bytes())
+    );
+}
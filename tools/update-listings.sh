@@ -0,0 +1,126 @@
+#!/bin/bash
+
+set -eu
+
+# Make the same change to every listing's `Cargo.toml` in one pass, then make
+# sure every listing which built before the change still builds after it.
+#
+#     ./tools/update-listings.sh [--edition YEAR] [--trpl VERSION]
+#                                [--add CRATE[@VERSION]]... [--no-verify]
+#
+# - `--edition` sets `edition` in every package. This only changes the
+#   manifests; use `./tools/update-editions.sh` to migrate the code too.
+# - `--trpl` sets the version of every `trpl` dependency, keeping its `path`,
+#   if it has one.
+# - `--add` adds a dependency to every package with `cargo add`, and can be
+#   given more than once.
+# - `--no-verify` skips building the listings before and after.
+
+usage() {
+    sed -n 's/^#     //p' "$0" >&2
+    exit 1
+}
+
+edition=""
+trpl_version=""
+dependencies=()
+verify=true
+
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --edition) [ $# -ge 2 ] || usage; edition="$2"; shift 2 ;;
+        --trpl) [ $# -ge 2 ] || usage; trpl_version="$2"; shift 2 ;;
+        --add) [ $# -ge 2 ] || usage; dependencies+=("$2"); shift 2 ;;
+        --no-verify) verify=false; shift ;;
+        *) usage ;;
+    esac
+done
+
+if [ -z "$edition" ] && [ -z "$trpl_version" ] && [ ${#dependencies[@]} -eq 0 ]; then
+    usage
+fi
+
+# As with `update-editions.sh`, the changes are easiest to review (and to undo)
+# when they are the only ones in the working directory.
+git diff --quiet || { echo "Git working directory is not clean"; exit 1; }
+
+manifests=()
+while IFS= read -r -d '' f; do
+    manifests+=("$f")
+done < <(find listings -name Cargo.toml -print0 | sort -z)
+
+# Each listing gets checked in its own target directory, cleaned up after:
+# sharing one would mix up the many listings with the same package name, since
+# Cargo would consider one's build fresh for another.
+builds() {
+    local status=0
+    cargo check --quiet --all-targets --manifest-path "$1" > /dev/null 2>&1 \
+        || status=$?
+    cargo clean --quiet --manifest-path "$1" > /dev/null 2>&1 || true
+    return "$status"
+}
+
+# Plenty of listings intentionally do not compile, so what matters is which
+# ones stop building, not which ones fail to.
+building=()
+if [ "$verify" = true ]; then
+    echo "Checking which of the ${#manifests[@]} listings build before updating..."
+    for f in "${manifests[@]}"; do
+        if builds "$f"; then
+            building+=("$f")
+        fi
+    done
+fi
+
+# Run `sed` on a file in place, in a way that works with both BSD and GNU sed.
+sed_in_place() {
+    local file="$1"
+    shift
+    sed -i.bak -E "$@" "$file"
+    rm "${file}.bak"
+}
+
+echo "Updating ${#manifests[@]} manifests..."
+for f in "${manifests[@]}"; do
+    # Virtual workspace manifests have no package to change; their members
+    # get updated on their own.
+    if ! grep -q '^\[package\]' "$f"; then
+        continue
+    fi
+
+    if [ -n "$edition" ]; then
+        sed_in_place "$f" -e "s/^edition = \"[0-9]+\"/edition = \"${edition}\"/"
+    fi
+
+    if [ -n "$trpl_version" ]; then
+        if grep -qE '^trpl = "' "$f"; then
+            sed_in_place "$f" -e "s/^trpl = \"[^\"]*\"/trpl = \"${trpl_version}\"/"
+        elif grep -qE '^trpl = \{.*version = ' "$f"; then
+            sed_in_place "$f" -e "/^trpl = /s/version = \"[^\"]*\"/version = \"${trpl_version}\"/"
+        elif grep -qE '^trpl = \{' "$f"; then
+            sed_in_place "$f" -e "s/^trpl = \{ */trpl = { version = \"${trpl_version}\", /"
+        fi
+    fi
+
+    for dependency in ${dependencies[@]+"${dependencies[@]}"}; do
+        cargo add --quiet --manifest-path "$f" "$dependency"
+    done
+done
+
+if [ "$verify" = true ]; then
+    echo "Checking that the ${#building[@]} listings which built still do..."
+    broken=()
+    for f in ${building[@]+"${building[@]}"}; do
+        if ! builds "$f"; then
+            broken+=("$f")
+        fi
+    done
+
+    if [ ${#broken[@]} -gt 0 ]; then
+        echo "These listings no longer build:"
+        printf '  %s\n' "${broken[@]}"
+        exit 1
+    fi
+fi
+
+echo 'Done.'